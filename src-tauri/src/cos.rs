@@ -1,14 +1,29 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use chrono::Utc;
 use hmac::{Hmac, Mac};
 use sha1::{Sha1, Digest};
-use md5;
 use base64::Engine;
-use reqwest;
+
+use crate::error::CosError;
+use crate::progress::ProgressTracker;
+use crate::storage::{
+    self, DeleteError, DeleteResult, DownloadResult, ListObjectsResult, ObjectInfo, ObjectMetadata, Provider,
+    StorageBackend, UploadOptions, UploadResult, WriteCondition,
+};
+
+mod multipart;
+mod retry;
+pub use multipart::MultipartOptions;
+pub use retry::NetworkOptions;
+pub(crate) use retry::{file_body, send_with_retry, Activity};
 
 type HmacSha1 = Hmac<Sha1>;
 
+// COS 单次批量删除最多支持 1000 个对象
+const MAX_DELETE_KEYS: usize = 1000;
+
 // COS 配置结构体
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CosConfig {
@@ -26,72 +41,8 @@ pub struct CosConfig {
     pub domain: Option<String>,
     #[serde(rename = "Dir")]
     pub dir: Option<String>,
-}
-
-// 上传选项
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UploadOptions {
-    pub callback: Option<String>,
-    pub headers: Option<CosHeaders>,
-}
-
-// 自定义头部选项
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CosHeaders {
-    pub content_type: Option<String>,
-    pub pic_operations: Option<String>,
-}
-
-// 上传结果
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UploadResult {
-    pub success: bool,
-    pub key: String,
-    pub url: String,
-    pub etag: Option<String>,
-    pub size: u64,
-}
-
-// 下载结果
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DownloadResult {
-    pub success: bool,
-    pub file_path: String,
-    pub size: u64,
-}
-
-// 删除结果
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DeleteResult {
-    pub success: bool,
-    pub deleted_count: usize,
-    pub failed_keys: Vec<String>,
-}
-
-// 对象元数据
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ObjectMetadata {
-    pub exists: bool,
-    pub size: Option<u64>,
-    pub etag: Option<String>,
-    pub last_modified: Option<String>,
-    pub content_type: Option<String>,
-}
-
-// 对象列表结果
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListObjectsResult {
-    pub objects: Vec<ObjectInfo>,
-    pub is_truncated: bool,
-    pub next_marker: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ObjectInfo {
-    pub key: String,
-    pub size: u64,
-    pub etag: String,
-    pub last_modified: String,
+    #[serde(rename = "Network", default)]
+    pub network: Option<NetworkOptions>,
 }
 
 // COS 客户端
@@ -99,12 +50,24 @@ pub struct ObjectInfo {
 pub struct CosClient {
     pub config: CosConfig,
     client: reqwest::Client,
+    // 超时与重试策略
+    network: NetworkOptions,
+    // 分块上传断点记录目录
+    checkpoint_dir: PathBuf,
 }
 
 impl CosClient {
     pub fn new(config: CosConfig) -> Self {
-        let client = reqwest::Client::new();
-        Self { config, client }
+        let network = config.network.clone().unwrap_or_default();
+        let client = network.build_client();
+        let checkpoint_dir = std::env::temp_dir().join("imsheet_uploads");
+        Self { config, client, network, checkpoint_dir }
+    }
+
+    // 设置分块上传断点记录目录（应用重启后可继续上传）
+    pub fn with_checkpoint_dir(mut self, dir: PathBuf) -> Self {
+        self.checkpoint_dir = dir;
+        self
     }
 
     // 生成签名
@@ -160,22 +123,14 @@ impl CosClient {
         )
     }
 
+    // 存储桶访问域名
+    fn host(&self) -> String {
+        format!("{}.cos.{}.myqcloud.com", self.config.bucket, self.config.region)
+    }
+
     // 获取完整的对象键
     fn get_full_key(&self, key: &str) -> String {
-        // 如果 key 已经包含了目录前缀，直接返回
-        if let Some(dir) = &self.config.dir {
-            let dir_prefix = if dir.ends_with('/') { dir.clone() } else { format!("{}/", dir) };
-            if key.starts_with(&dir_prefix) {
-                return key.to_string();
-            }
-            return format!("{}{}", dir_prefix, key);
-        } else {
-            // 检查是否已经有 ImSheet/ 前缀
-            if key.starts_with("ImSheet/") {
-                return key.to_string();
-            }
-            return format!("ImSheet/{}", key);
-        }
+        storage::full_key(self.config.dir.as_deref(), key)
     }
 
     // 获取对象URL
@@ -192,8 +147,14 @@ impl CosClient {
         }
     }
 
-    // 上传文件
-    pub async fn upload_file(&self, file_path: &str, key: &str, options: Option<UploadOptions>) -> Result<UploadResult, Box<dyn std::error::Error>> {
+    // 上传文件（超过分块阈值时自动使用分块上传）
+    pub async fn upload_file(
+        &self,
+        file_path: &str,
+        key: &str,
+        options: Option<UploadOptions>,
+        progress: Option<ProgressTracker>,
+    ) -> Result<UploadResult, CosError> {
         // 添加调试日志
         println!("=== COS Upload Debug ===");
         println!("  Bucket: {}", self.config.bucket);
@@ -201,46 +162,128 @@ impl CosClient {
         println!("  Key: {}", key);
         println!("  File path: {}", file_path);
         
-        // 读取原始文件数据
-        let file_data = std::fs::read(file_path)?;
-        let file_size = file_data.len() as u64;
+        let file_size = std::fs::metadata(file_path)?.len();
         
         println!("  File size: {} bytes", file_size);
+
+        if let Some(tracker) = &progress {
+            tracker.set_total(file_size);
+        }
         
-        // 根据文件扩展名确定内容类型
-        let content_type = match std::path::Path::new(file_path).extension().and_then(|s| s.to_str()) {
-            Some("jpg") | Some("jpeg") => "image/jpeg".to_string(),
-            Some("png") => "image/png".to_string(),
-            Some("gif") => "image/gif".to_string(),
-            Some("webp") => "image/webp".to_string(),
-            Some("bmp") => "image/bmp".to_string(),
-            _ => "application/octet-stream".to_string(),
-        };
+        let content_type = content_type_for_path(file_path);
         
         let full_key = self.get_full_key(key);
-        let uri_path = format!("/{}", full_key);
         
         println!("  Full key: {}", full_key);
-        println!("  URI path: {}", uri_path);
         println!("  Content-Type: {}", content_type);
+
+        let pic_operations = options.as_ref()
+            .and_then(|opts| opts.headers.as_ref())
+            .and_then(|headers| headers.pic_operations.clone());
+        let multipart = options.as_ref()
+            .and_then(|opts| opts.multipart.clone())
+            .unwrap_or_default();
+        let condition = options.as_ref().and_then(|opts| opts.condition.as_ref());
+
+        let source = UploadSource {
+            file_path,
+            full_key: &full_key,
+            content_type: &content_type,
+            file_size,
+            pic_operations: pic_operations.as_deref(),
+            condition,
+        };
+
+        // 完成分块上传时无法校验前置条件，条件上传始终使用简单上传
+        let etag = if condition.is_none() && file_size >= multipart.threshold() {
+            println!("  📦 文件超过分块阈值 {} bytes，使用分块上传", multipart.threshold());
+            self.upload_multipart(&source, &multipart, progress.as_ref()).await?
+        } else {
+            self.put_object(&source, progress.as_ref()).await?
+        };
+
+        if let Some(tracker) = &progress {
+            tracker.finish();
+        }
+
+        // 检查是否使用了万象云处理，如果是，获取处理后的实际文件大小
+        let (actual_size, final_key) = if pic_operations.is_some() {
+            println!("  🔍 检测到万象云处理，原文件已被WebP格式覆盖，获取压缩后文件大小...");
+            
+            // 由于fileid与ObjectKey相同，万象云会用WebP格式覆盖原文件
+            // 所以原始key的文件现在就是WebP格式，直接获取其大小
+            println!("  🔄 获取被WebP覆盖后的原文件大小: {}", full_key);
+            
+            // 获取被WebP覆盖后的文件大小
+            match self.get_object_size(&full_key).await {
+                Ok(size) => {
+                    println!("  📏 万象云WebP覆盖后文件大小: {} bytes (原始: {} bytes)", size, file_size);
+                    (size, full_key.clone())
+                },
+                Err(e) => {
+                    println!("  ⚠️ 获取WebP覆盖后文件大小失败，使用原始大小: {}", e);
+                    (file_size, full_key.clone())
+                }
+            }
+        } else {
+            (file_size, full_key.clone())
+        };
+
+        // 使用最终的key构建URL（对于WebP转换，这将是WebP文件的URL）
+        let location_url = format!(
+            "https://{}.cos.{}.myqcloud.com/{}",
+            self.config.bucket,
+            self.config.region,
+            final_key
+        );
+
+        println!("  🎉 Upload completed successfully!");
+        println!("  📍 Location: {}", location_url);
+        println!("  📏 Final size: {} bytes", actual_size);
+        println!("=== End COS Upload Debug ===");
+
+        // 返回结果：对于万象云WebP处理，原文件已被WebP格式覆盖
+        // 所以返回的key仍然是原始key，但内容已经是WebP格式
+        Ok(UploadResult {
+            success: true,
+            key: key.to_string(),  // 返回原始key，因为万象云覆盖了原文件
+            url: location_url,
+            etag,
+            size: actual_size, // 返回实际文件大小（万象云处理后的大小）
+        })
+    }
+
+    // 简单上传：单次 PUT 以流的方式发送整个文件
+    async fn put_object(&self, source: &UploadSource<'_>, progress: Option<&ProgressTracker>) -> Result<Option<String>, CosError> {
+        let uri_path = format!("/{}", source.full_key);
         
         let mut headers = HashMap::new();
         headers.insert("host".to_string(), format!("{}.cos.{}.myqcloud.com", self.config.bucket, self.config.region));
-        headers.insert("content-length".to_string(), file_size.to_string());
-        headers.insert("content-type".to_string(), content_type.clone());
+        headers.insert("content-length".to_string(), source.file_size.to_string());
+        headers.insert("content-type".to_string(), source.content_type.to_string());
         
         // 检查是否需要添加万象处理头部
-        if let Some(opts) = &options {
-            if let Some(custom_headers) = &opts.headers {
-                if let Some(pic_ops) = &custom_headers.pic_operations {
-                    println!("  🌟 添加万象处理头部: {}", pic_ops);
-                    headers.insert("pic-operations".to_string(), pic_ops.clone());
+        if let Some(pic_ops) = source.pic_operations {
+            println!("  🌟 添加万象处理头部: {}", pic_ops);
+            headers.insert("pic-operations".to_string(), pic_ops.to_string());
+        }
+
+        // 条件写入头部参与签名
+        if let Some(condition) = source.condition {
+            if let Some(etag) = condition.if_match.as_deref() {
+                headers.insert("if-match".to_string(), quote_etag(etag));
+            }
+            if let Some(etag) = condition.if_none_match.as_deref() {
+                headers.insert("if-none-match".to_string(), if etag == "*" { etag.to_string() } else { quote_etag(etag) });
+                // 未开启版本控制的存储桶用禁止覆盖头部保证只创建不覆盖
+                if etag == "*" {
+                    headers.insert("x-cos-forbid-overwrite".to_string(), "true".to_string());
                 }
             }
+            println!("  🔒 条件上传: {:?}", condition);
         }
 
         let query_params = HashMap::new();
-        let authorization = self.generate_signature("PUT", &uri_path, &headers, &query_params);
 
         let url = format!(
             "https://{}.cos.{}.myqcloud.com{}",
@@ -250,94 +293,52 @@ impl CosClient {
         );
         
         println!("  Request URL: {}", url);
+        println!("  🚀 Sending upload request...");
 
-        let mut request = self.client
-            .put(&url)
-            .header("Authorization", authorization)
-            .header("Content-Type", content_type)
-            .header("Content-Length", file_size);
-
-        // 添加万象处理头部到实际请求中
-        if let Some(opts) = &options {
-            if let Some(custom_headers) = &opts.headers {
-                if let Some(pic_ops) = &custom_headers.pic_operations {
-                    request = request.header("Pic-Operations", pic_ops);
-                    println!("  🌟 已添加Pic-Operations头部到请求");
-                }
+        let response = self.send_with_retry("PUT Object", |activity| {
+            // 每次尝试重新签名，并从头读取文件
+            let authorization = self.generate_signature("PUT", &uri_path, &headers, &query_params);
+
+            let mut request = self.client
+                .put(&url)
+                .header("Authorization", authorization)
+                .header("Content-Type", source.content_type)
+                .header("Content-Length", source.file_size);
+
+            // 添加万象处理头部到实际请求中
+            if let Some(pic_ops) = source.pic_operations {
+                request = request.header("Pic-Operations", pic_ops);
             }
-        }
 
-        let request = request.body(file_data);
+            for name in ["if-match", "if-none-match", "x-cos-forbid-overwrite"] {
+                if let Some(value) = headers.get(name) {
+                    request = request.header(name, value);
+                }
+            }
 
-        println!("  🚀 Sending upload request...");
-        let response = request.send().await?;
+            // 边读边发送文件内容，每发送一块统计一次进度
+            Ok(request.body(file_body(source.file_path, activity, progress)?))
+        }).await?;
         let status = response.status();
         println!("  📥 Response status: {}", status);
 
         if status.is_success() {
-            let etag = response.headers()
+            Ok(response.headers()
                 .get("etag")
                 .and_then(|v| v.to_str().ok())
-                .map(|s| s.trim_matches('"').to_string());
-
-            // 检查是否使用了万象云处理，如果是，获取处理后的实际文件大小
-            let (actual_size, final_key) = if options.as_ref()
-                .and_then(|opts| opts.headers.as_ref())
-                .and_then(|headers| headers.pic_operations.as_ref())
-                .is_some() 
-            {
-                println!("  🔍 检测到万象云处理，原文件已被WebP格式覆盖，获取压缩后文件大小...");
-                
-                // 由于fileid与ObjectKey相同，万象云会用WebP格式覆盖原文件
-                // 所以原始key的文件现在就是WebP格式，直接获取其大小
-                println!("  🔄 获取被WebP覆盖后的原文件大小: {}", full_key);
-                
-                // 获取被WebP覆盖后的文件大小
-                match self.get_object_size(&full_key).await {
-                    Ok(size) => {
-                        println!("  📏 万象云WebP覆盖后文件大小: {} bytes (原始: {} bytes)", size, file_size);
-                        (size, full_key.clone())
-                    },
-                    Err(e) => {
-                        println!("  ⚠️ 获取WebP覆盖后文件大小失败，使用原始大小: {}", e);
-                        (file_size, full_key.clone())
-                    }
-                }
-            } else {
-                (file_size, full_key.clone())
-            };
-
-            // 使用最终的key构建URL（对于WebP转换，这将是WebP文件的URL）
-            let location_url = format!(
-                "https://{}.cos.{}.myqcloud.com/{}",
-                self.config.bucket,
-                self.config.region,
-                final_key
-            );
-
-            println!("  🎉 Upload completed successfully!");
-            println!("  📍 Location: {}", location_url);
-            println!("  📏 Final size: {} bytes", actual_size);
-            println!("=== End COS Upload Debug ===");
-
-            // 返回结果：对于万象云WebP处理，原文件已被WebP格式覆盖
-            // 所以返回的key仍然是原始key，但内容已经是WebP格式
-            Ok(UploadResult {
-                success: true,
-                key: key.to_string(),  // 返回原始key，因为万象云覆盖了原文件
-                url: location_url,
-                etag,
-                size: actual_size, // 返回实际文件大小（万象云处理后的大小）
-            })
+                .map(|s| s.trim_matches('"').to_string()))
         } else {
-            let error_text = response.text().await?;
-            println!("  ❌ Upload failed: {} - {}", status, error_text);
-            Err(format!("Upload failed: {} - {}", status, error_text).into())
+            Err(CosError::from_response(response).await)
         }
     }
 
-    // 下载文件
-    pub async fn download_file(&self, key: &str, save_path: &str) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+    // 下载文件（流式写入磁盘）
+    pub async fn download_file(
+        &self,
+        key: &str,
+        save_path: &str,
+        progress: Option<ProgressTracker>,
+    ) -> Result<DownloadResult, CosError> {
         let full_key = self.get_full_key(key);
         let uri_path = format!("/{}", full_key);
         
@@ -345,7 +346,6 @@ impl CosClient {
         headers.insert("host".to_string(), format!("{}.cos.{}.myqcloud.com", self.config.bucket, self.config.region));
 
         let query_params = HashMap::new();
-        let authorization = self.generate_signature("GET", &uri_path, &headers, &query_params);
 
         let url = format!(
             "https://{}.cos.{}.myqcloud.com{}",
@@ -354,44 +354,41 @@ impl CosClient {
             uri_path
         );
 
-        let response = self.client
-            .get(&url)
-            .header("Authorization", authorization)
-            .send()
-            .await?;
+        let response = self.send_with_retry("GET Object", |_| {
+            let authorization = self.generate_signature("GET", &uri_path, &headers, &query_params);
+            Ok(self.client.get(&url).header("Authorization", authorization))
+        }).await?;
 
         let status = response.status();
         
         if status.is_success() {
-            let content_length = response.content_length().unwrap_or(0);
-            let bytes = response.bytes().await?;
-            
-            std::fs::write(save_path, bytes)?;
+            let etag = storage::response_etag(&response);
+            let size = storage::save_response(response, save_path, progress.as_ref()).await?;
 
             Ok(DownloadResult {
                 success: true,
                 file_path: save_path.to_string(),
-                size: content_length,
+                size,
+                etag,
             })
         } else {
-            let error_text = response.text().await?;
-            Err(format!("Download failed: {} - {}", status, error_text).into())
+            Err(CosError::from_response(response).await)
         }
     }
 
     // 获取对象大小（用于万象云处理后获取实际文件大小）
-    pub async fn get_object_size(&self, key: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    pub async fn get_object_size(&self, key: &str) -> Result<u64, CosError> {
         let metadata = self.head_object(key).await?;
         
         if metadata.exists {
-            metadata.size.ok_or_else(|| "无法获取文件大小".into())
+            metadata.size.ok_or_else(|| CosError::parse("无法获取文件大小"))
         } else {
-            Err("文件不存在".into())
+            Err(CosError::invalid("文件不存在"))
         }
     }
 
     // 检查对象是否存在
-    pub async fn head_object(&self, key: &str) -> Result<ObjectMetadata, Box<dyn std::error::Error>> {
+    pub async fn head_object(&self, key: &str) -> Result<ObjectMetadata, CosError> {
         let full_key = self.get_full_key(key);
         let uri_path = format!("/{}", full_key);
         
@@ -399,7 +396,6 @@ impl CosClient {
         headers.insert("host".to_string(), format!("{}.cos.{}.myqcloud.com", self.config.bucket, self.config.region));
 
         let query_params = HashMap::new();
-        let authorization = self.generate_signature("HEAD", &uri_path, &headers, &query_params);
 
         let url = format!(
             "https://{}.cos.{}.myqcloud.com{}",
@@ -408,11 +404,10 @@ impl CosClient {
             uri_path
         );
 
-        let response = self.client
-            .head(&url)
-            .header("Authorization", authorization)
-            .send()
-            .await?;
+        let response = self.send_with_retry("HEAD Object", |_| {
+            let authorization = self.generate_signature("HEAD", &uri_path, &headers, &query_params);
+            Ok(self.client.head(&url).header("Authorization", authorization))
+        }).await?;
 
         let status = response.status();
 
@@ -453,21 +448,37 @@ impl CosClient {
                 content_type: None,
             })
         } else {
-            let error_text = response.text().await?;
-            Err(format!("Head object failed: {} - {}", status, error_text).into())
+            Err(CosError::from_response(response).await)
         }
     }
 
-    // 删除多个对象
-    pub async fn delete_multiple(&self, keys: Vec<String>) -> Result<DeleteResult, Box<dyn std::error::Error>> {
-        if keys.is_empty() {
-            return Ok(DeleteResult {
-                success: true,
-                deleted_count: 0,
-                failed_keys: vec![],
-            });
+    // 删除多个对象（超过单次请求上限时自动分批）
+    pub async fn delete_multiple(&self, keys: Vec<String>) -> Result<DeleteResult, CosError> {
+        let mut deleted_count = 0;
+        let mut failed_keys = Vec::new();
+
+        println!("=== 批量删除调试信息 ===");
+        println!("删除的文件数量: {}", keys.len());
+
+        for chunk in keys.chunks(MAX_DELETE_KEYS) {
+            let full_keys: Vec<String> = chunk.iter().map(|key| self.get_full_key(key)).collect();
+            let result = self.delete_chunk(&full_keys).await?;
+            deleted_count += result.deleted_count;
+            failed_keys.extend(result.failed_keys);
         }
 
+        println!("成功删除: {} 个文件", deleted_count);
+        println!("删除失败: {} 个文件", failed_keys.len());
+
+        Ok(DeleteResult {
+            success: failed_keys.is_empty(),
+            deleted_count,
+            failed_keys,
+        })
+    }
+
+    // 发送单次 Multi-Object Delete 请求
+    async fn delete_chunk(&self, full_keys: &[String]) -> Result<DeleteResult, CosError> {
         let uri_path = "/";
         let mut headers = HashMap::new();
         headers.insert("host".to_string(), format!("{}.cos.{}.myqcloud.com", self.config.bucket, self.config.region));
@@ -475,15 +486,14 @@ impl CosClient {
 
         // 构造删除请求的 XML
         let mut xml_body = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Delete>\n<Quiet>false</Quiet>\n");
-        for key in &keys {
-            let full_key = self.get_full_key(key);
-            xml_body.push_str(&format!("<Object><Key>{}</Key></Object>\n", full_key));
+        for full_key in full_keys {
+            xml_body.push_str(&format!("<Object><Key>{}</Key></Object>\n", xml_escape(full_key)));
         }
         xml_body.push_str("</Delete>");
 
         // 计算并添加 Content-MD5 头部
         let md5_hash = md5::compute(xml_body.as_bytes());
-        let content_md5 = base64::engine::general_purpose::STANDARD.encode(&md5_hash.0);
+        let content_md5 = base64::engine::general_purpose::STANDARD.encode(md5_hash.0);
         headers.insert("content-md5".to_string(), content_md5.clone());
         headers.insert("content-length".to_string(), xml_body.len().to_string());
 
@@ -491,17 +501,7 @@ impl CosClient {
         let mut query_params = HashMap::new();
         query_params.insert("delete".to_string(), "".to_string());
 
-        println!("=== 批量删除调试信息 ===");
-        println!("删除的文件数量: {}", keys.len());
-        println!("删除的文件列表: {:?}", keys);
-        println!("XML Body:\n{}", xml_body);
-        println!("Content-MD5: {}", content_md5);
-        println!("Headers: {:?}", headers);
-        println!("Query params: {:?}", query_params);
-
-        let authorization = self.generate_signature("POST", uri_path, &headers, &query_params);
-        
-        println!("Authorization: {}", authorization);
+        println!("本批删除数量: {}", full_keys.len());
 
         let url = format!(
             "https://{}.cos.{}.myqcloud.com/?delete",
@@ -509,69 +509,60 @@ impl CosClient {
             self.config.region
         );
 
-        println!("Request URL: {}", url);
-
-        let response = self.client
-            .post(&url)
-            .header("Authorization", authorization)
-            .header("Content-Type", "application/xml")
-            .header("Content-MD5", content_md5)
-            .header("Content-Length", xml_body.len())
-            .body(xml_body)
-            .send()
-            .await?;
+        let response = self.send_with_retry("Delete Multiple Objects", |_| {
+            let authorization = self.generate_signature("POST", uri_path, &headers, &query_params);
+            Ok(self.client
+                .post(&url)
+                .header("Authorization", authorization)
+                .header("Content-Type", "application/xml")
+                .header("Content-MD5", &content_md5)
+                .header("Content-Length", xml_body.len())
+                .body(xml_body.clone()))
+        }).await?;
 
         let status = response.status();
-        let response_text = response.text().await?;
 
         println!("Response status: {}", status);
-        println!("Response body: {}", response_text);
 
         if status.is_success() {
-            // 解析响应XML来确定实际删除的文件
-            println!("✅ 删除请求成功，响应内容:");
-            println!("{}", response_text);
-            
-            // 简单解析删除成功的数量（实际项目中应该用XML解析器）
-            let deleted_count = response_text.matches("<Deleted>").count();
-            let error_count = response_text.matches("<Error>").count();
-            
-            println!("成功删除: {} 个文件", deleted_count);
-            println!("删除失败: {} 个文件", error_count);
-
-            Ok(DeleteResult {
-                success: true,
-                deleted_count,
-                failed_keys: vec![],
-            })
+            let response_text = response.text().await?;
+            parse_delete_result(&response_text)
         } else {
-            println!("❌ 删除请求失败: {} - {}", status, response_text);
-            Err(format!("Delete multiple failed: {} - {}", status, response_text).into())
+            let error = CosError::from_response(response).await;
+            println!("❌ 删除请求失败: {}", error);
+            Err(error)
         }
     }
 
-    // 列出对象
-    pub async fn list_objects(&self, prefix: Option<String>, max_keys: Option<u32>) -> Result<ListObjectsResult, Box<dyn std::error::Error>> {
+    // 列出对象（支持 marker 分页与 delimiter 目录分组）
+    pub async fn list_objects(
+        &self,
+        prefix: Option<String>,
+        max_keys: Option<u32>,
+        marker: Option<String>,
+        delimiter: Option<String>,
+    ) -> Result<ListObjectsResult, CosError> {
         let uri_path = "/";
         let mut headers = HashMap::new();
         headers.insert("host".to_string(), format!("{}.cos.{}.myqcloud.com", self.config.bucket, self.config.region));
 
         let mut query_params = HashMap::new();
-        
-        if let Some(prefix) = prefix {
-            let full_prefix = if let Some(dir) = &self.config.dir {
-                format!("{}/{}", dir, prefix)
-            } else {
-                format!("ImSheet/{}", prefix)
-            };
-            query_params.insert("prefix".to_string(), full_prefix);
-        }
-        
+
+        // 未指定前缀时列出配置目录下的全部对象
+        let full_prefix = self.get_full_key(prefix.as_deref().unwrap_or(""));
+        query_params.insert("prefix".to_string(), full_prefix);
+
         if let Some(max_keys) = max_keys {
             query_params.insert("max-keys".to_string(), max_keys.to_string());
         }
 
-        let authorization = self.generate_signature("GET", uri_path, &headers, &query_params);
+        if let Some(marker) = marker.filter(|m| !m.is_empty()) {
+            query_params.insert("marker".to_string(), marker);
+        }
+
+        if let Some(delimiter) = delimiter.filter(|d| !d.is_empty()) {
+            query_params.insert("delimiter".to_string(), delimiter);
+        }
 
         let mut url = format!(
             "https://{}.cos.{}.myqcloud.com/",
@@ -586,25 +577,357 @@ impl CosClient {
             url.push_str(&format!("?{}", params.join("&")));
         }
 
-        let response = self.client
-            .get(&url)
-            .header("Authorization", authorization)
-            .send()
-            .await?;
+        let response = self.send_with_retry("GET Bucket", |_| {
+            let authorization = self.generate_signature("GET", uri_path, &headers, &query_params);
+            Ok(self.client.get(&url).header("Authorization", authorization))
+        }).await?;
 
         let status = response.status();
 
         if status.is_success() {
-            // 简化处理，返回空列表
-            // 实际应该解析 XML 响应
-            Ok(ListObjectsResult {
-                objects: vec![],
-                is_truncated: false,
-                next_marker: None,
-            })
+            let body = response.text().await?;
+            parse_list_objects(&body)
         } else {
-            let error_text = response.text().await?;
-            Err(format!("List objects failed: {} - {}", status, error_text).into())
+            Err(CosError::from_response(response).await)
         }
     }
-}
\ No newline at end of file
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for CosClient {
+    fn provider(&self) -> Provider {
+        Provider::Cos
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        self.get_full_key(key)
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        self.get_object_url(key)
+    }
+
+    fn supports_conditional_put(&self) -> bool {
+        true
+    }
+
+    async fn put_file(
+        &self,
+        file_path: &str,
+        key: &str,
+        options: Option<UploadOptions>,
+        progress: Option<ProgressTracker>,
+    ) -> Result<UploadResult, CosError> {
+        self.upload_file(file_path, key, options, progress).await
+    }
+
+    async fn get_file(
+        &self,
+        key: &str,
+        save_path: &str,
+        progress: Option<ProgressTracker>,
+    ) -> Result<DownloadResult, CosError> {
+        self.download_file(key, save_path, progress).await
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMetadata, CosError> {
+        self.head_object(key).await
+    }
+
+    async fn delete(&self, keys: Vec<String>) -> Result<DeleteResult, CosError> {
+        self.delete_multiple(keys).await
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<String>,
+        max_keys: Option<u32>,
+        marker: Option<String>,
+        delimiter: Option<String>,
+    ) -> Result<ListObjectsResult, CosError> {
+        self.list_objects(prefix, max_keys, marker, delimiter).await
+    }
+}
+
+// ListBucketResult 响应结构（仅包含需要的字段）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListBucketResultXml {
+    #[serde(default)]
+    is_truncated: bool,
+    next_marker: Option<String>,
+    #[serde(default)]
+    contents: Vec<ContentsXml>,
+    #[serde(default)]
+    common_prefixes: Vec<CommonPrefixXml>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContentsXml {
+    key: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(rename = "ETag", default)]
+    etag: String,
+    #[serde(default)]
+    last_modified: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CommonPrefixXml {
+    prefix: String,
+}
+
+// DeleteResult 响应结构
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DeleteResultXml {
+    #[serde(default)]
+    deleted: Vec<serde::de::IgnoredAny>,
+    #[serde(default)]
+    error: Vec<DeleteErrorXml>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DeleteErrorXml {
+    key: String,
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    message: String,
+}
+
+// 解析 Multi-Object Delete 返回的 XML
+pub(crate) fn parse_delete_result(xml: &str) -> Result<DeleteResult, CosError> {
+    let parsed: DeleteResultXml = quick_xml::de::from_str(xml)
+        .map_err(|e| CosError::parse(format!("解析删除结果失败: {}", e)))?;
+
+    let failed_keys: Vec<DeleteError> = parsed.error
+        .into_iter()
+        .map(|e| DeleteError {
+            key: e.key,
+            code: e.code,
+            message: e.message,
+        })
+        .collect();
+
+    for failed in &failed_keys {
+        println!("  ⚠️ 删除失败: {} - {} {}", failed.key, failed.code, failed.message);
+    }
+
+    Ok(DeleteResult {
+        success: failed_keys.is_empty(),
+        deleted_count: parsed.deleted.len(),
+        failed_keys,
+    })
+}
+
+// 单个对象上传的公共参数
+struct UploadSource<'a> {
+    file_path: &'a str,
+    full_key: &'a str,
+    content_type: &'a str,
+    file_size: u64,
+    pic_operations: Option<&'a str>,
+    // 条件写入，仅简单上传支持
+    condition: Option<&'a WriteCondition>,
+}
+
+// 条件请求头中的 ETag 需要带引号
+fn quote_etag(etag: &str) -> String {
+    format!("\"{}\"", etag.trim_matches('"'))
+}
+
+// 根据文件扩展名确定内容类型
+pub(crate) fn content_type_for_path(file_path: &str) -> String {
+    match std::path::Path::new(file_path).extension().and_then(|s| s.to_str()) {
+        Some("jpg") | Some("jpeg") => "image/jpeg".to_string(),
+        Some("png") => "image/png".to_string(),
+        Some("gif") => "image/gif".to_string(),
+        Some("webp") => "image/webp".to_string(),
+        Some("bmp") => "image/bmp".to_string(),
+        _ => "application/octet-stream".to_string(),
+    }
+}
+
+// 转义 XML 文本中的特殊字符
+pub(crate) fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+// 解析 GET Bucket 返回的 XML
+pub(crate) fn parse_list_objects(xml: &str) -> Result<ListObjectsResult, CosError> {
+    let parsed: ListBucketResultXml = quick_xml::de::from_str(xml)
+        .map_err(|e| CosError::parse(format!("解析对象列表失败: {}", e)))?;
+
+    let objects: Vec<ObjectInfo> = parsed.contents
+        .into_iter()
+        .map(|c| ObjectInfo {
+            key: c.key,
+            size: c.size,
+            etag: c.etag.trim_matches('"').to_string(),
+            last_modified: c.last_modified,
+        })
+        .collect();
+
+    let common_prefixes: Vec<String> = parsed.common_prefixes
+        .into_iter()
+        .map(|p| p.prefix)
+        .collect();
+
+    // 未指定 delimiter 时 COS 可能不返回 NextMarker，此时以本页最后一个键作为下一页起点
+    let next_marker = if parsed.is_truncated {
+        parsed.next_marker
+            .filter(|m| !m.is_empty())
+            .or_else(|| {
+                let last_key = objects.last().map(|o| o.key.clone());
+                let last_prefix = common_prefixes.last().cloned();
+                last_key.max(last_prefix)
+            })
+    } else {
+        None
+    };
+
+    Ok(ListObjectsResult {
+        objects,
+        common_prefixes,
+        is_truncated: parsed.is_truncated,
+        next_marker,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_list_objects_reads_contents_and_prefixes() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult>
+    <Name>bucket-1250000000</Name>
+    <Prefix>imsheet/</Prefix>
+    <Marker></Marker>
+    <MaxKeys>1000</MaxKeys>
+    <Delimiter>/</Delimiter>
+    <IsTruncated>false</IsTruncated>
+    <CommonPrefixes><Prefix>imsheet/2024/</Prefix></CommonPrefixes>
+    <CommonPrefixes><Prefix>imsheet/2025/</Prefix></CommonPrefixes>
+    <Contents>
+        <Key>imsheet/a.png</Key>
+        <LastModified>2025-01-02T03:04:05.000Z</LastModified>
+        <ETag>"d41d8cd98f00b204e9800998ecf8427e"</ETag>
+        <Size>1024</Size>
+        <StorageClass>STANDARD</StorageClass>
+    </Contents>
+</ListBucketResult>"#;
+
+        let result = parse_list_objects(xml).unwrap();
+
+        assert_eq!(result.objects.len(), 1);
+        let object = &result.objects[0];
+        assert_eq!(object.key, "imsheet/a.png");
+        assert_eq!(object.size, 1024);
+        assert_eq!(object.etag, "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(object.last_modified, "2025-01-02T03:04:05.000Z");
+        assert_eq!(result.common_prefixes, vec!["imsheet/2024/", "imsheet/2025/"]);
+        assert!(!result.is_truncated);
+        assert_eq!(result.next_marker, None);
+    }
+
+    #[test]
+    fn parse_list_objects_uses_next_marker() {
+        let xml = r#"<ListBucketResult>
+    <IsTruncated>true</IsTruncated>
+    <NextMarker>imsheet/b.png</NextMarker>
+    <Contents><Key>imsheet/a.png</Key><Size>1</Size></Contents>
+    <Contents><Key>imsheet/b.png</Key><Size>2</Size></Contents>
+</ListBucketResult>"#;
+
+        let result = parse_list_objects(xml).unwrap();
+
+        assert!(result.is_truncated);
+        assert_eq!(result.next_marker.as_deref(), Some("imsheet/b.png"));
+    }
+
+    #[test]
+    fn parse_list_objects_falls_back_to_last_entry() {
+        // 没有 NextMarker 时取本页最后一个对象或目录中较大的一个
+        let xml = r#"<ListBucketResult>
+    <IsTruncated>true</IsTruncated>
+    <Contents><Key>imsheet/a.png</Key><Size>1</Size></Contents>
+    <CommonPrefixes><Prefix>imsheet/b/</Prefix></CommonPrefixes>
+</ListBucketResult>"#;
+        assert_eq!(parse_list_objects(xml).unwrap().next_marker.as_deref(), Some("imsheet/b/"));
+
+        let xml = r#"<ListBucketResult>
+    <IsTruncated>true</IsTruncated>
+    <NextMarker></NextMarker>
+    <Contents><Key>imsheet/c.png</Key><Size>1</Size></Contents>
+    <CommonPrefixes><Prefix>imsheet/b/</Prefix></CommonPrefixes>
+</ListBucketResult>"#;
+        assert_eq!(parse_list_objects(xml).unwrap().next_marker.as_deref(), Some("imsheet/c.png"));
+    }
+
+    #[test]
+    fn parse_list_objects_accepts_empty_result() {
+        let result = parse_list_objects("<ListBucketResult><IsTruncated>false</IsTruncated></ListBucketResult>").unwrap();
+
+        assert!(result.objects.is_empty());
+        assert!(result.common_prefixes.is_empty());
+        assert!(!result.is_truncated);
+    }
+
+    #[test]
+    fn parse_list_objects_rejects_invalid_xml() {
+        assert!(matches!(parse_list_objects("<ListBucketResult>"), Err(CosError::Parse { .. })));
+    }
+
+    #[test]
+    fn parse_delete_result_counts_deleted_and_failed() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<DeleteResult>
+    <Deleted><Key>imsheet/a.png</Key></Deleted>
+    <Deleted><Key>imsheet/b.png</Key></Deleted>
+    <Error>
+        <Key>imsheet/c.png</Key>
+        <Code>AccessDenied</Code>
+        <Message>Access Denied</Message>
+    </Error>
+</DeleteResult>"#;
+
+        let result = parse_delete_result(xml).unwrap();
+
+        assert!(!result.success);
+        assert_eq!(result.deleted_count, 2);
+        assert_eq!(result.failed_keys.len(), 1);
+        let failed = &result.failed_keys[0];
+        assert_eq!(failed.key, "imsheet/c.png");
+        assert_eq!(failed.code, "AccessDenied");
+        assert_eq!(failed.message, "Access Denied");
+    }
+
+    #[test]
+    fn parse_delete_result_succeeds_without_errors() {
+        let xml = "<DeleteResult><Deleted><Key>imsheet/a.png</Key></Deleted></DeleteResult>";
+
+        let result = parse_delete_result(xml).unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.deleted_count, 1);
+        assert!(result.failed_keys.is_empty());
+    }
+}
@@ -0,0 +1,391 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use futures_util::stream::{self, StreamExt};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use super::retry::tracked_body;
+use super::{CosClient, UploadSource};
+use crate::error::{request_id_of, CosError};
+use crate::progress::ProgressTracker;
+
+// 默认超过 8 MB 的文件使用分块上传
+const DEFAULT_THRESHOLD: u64 = 8 * 1024 * 1024;
+// 默认分块大小 2 MB，网络不稳定时单块重传代价较小
+const DEFAULT_PART_SIZE: u64 = 2 * 1024 * 1024;
+// 默认同时上传的分块数量
+const DEFAULT_CONCURRENCY: usize = 4;
+// COS 限制：除最后一块外每块至少 1 MB，最多 10000 块
+const MIN_PART_SIZE: u64 = 1024 * 1024;
+const MAX_PARTS: u64 = 10000;
+
+// 分块上传选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MultipartOptions {
+    pub threshold: Option<u64>,
+    pub part_size: Option<u64>,
+    pub concurrency: Option<usize>,
+}
+
+impl MultipartOptions {
+    pub fn threshold(&self) -> u64 {
+        self.threshold.unwrap_or(DEFAULT_THRESHOLD)
+    }
+
+    // 分块大小需满足 COS 的最小分块和最大分块数限制
+    fn part_size(&self, file_size: u64) -> u64 {
+        let requested = self.part_size.unwrap_or(DEFAULT_PART_SIZE).max(MIN_PART_SIZE);
+        requested.max(file_size.div_ceil(MAX_PARTS))
+    }
+
+    fn concurrency(&self) -> usize {
+        self.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1)
+    }
+}
+
+// 断点记录：保存 UploadId 和已上传分块的 ETag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UploadCheckpoint {
+    key: String,
+    upload_id: String,
+    file_size: u64,
+    part_size: u64,
+    parts: BTreeMap<u32, String>,
+}
+
+impl UploadCheckpoint {
+    fn new(key: &str, upload_id: String, file_size: u64, part_size: u64) -> Self {
+        Self {
+            key: key.to_string(),
+            upload_id,
+            file_size,
+            part_size,
+            parts: BTreeMap::new(),
+        }
+    }
+
+    fn load(path: &Path) -> Option<Self> {
+        let data = std::fs::read(path).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    fn save(&self, path: &Path) -> Result<(), CosError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // 先写临时文件再重命名，避免进程退出时留下不完整的记录
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, serde_json::to_vec(self)?)?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    fn part_count(&self) -> u32 {
+        self.file_size.div_ceil(self.part_size).max(1) as u32
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InitiateMultipartUploadResultXml {
+    upload_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CompleteMultipartUploadResultXml {
+    #[serde(rename = "ETag")]
+    etag: Option<String>,
+}
+
+impl CosClient {
+    // 分块上传：断点记录存在且与当前文件匹配时从中断处继续
+    pub(super) async fn upload_multipart(
+        &self,
+        source: &UploadSource<'_>,
+        options: &MultipartOptions,
+        progress: Option<&ProgressTracker>,
+    ) -> Result<Option<String>, CosError> {
+        let UploadSource { file_path, full_key, content_type, file_size, pic_operations, .. } = *source;
+        let checkpoint_path = self.checkpoint_path(full_key);
+        let part_size = options.part_size(file_size);
+
+        let mut checkpoint = match UploadCheckpoint::load(&checkpoint_path) {
+            Some(checkpoint) if checkpoint.file_size == file_size && checkpoint.part_size == part_size => {
+                println!("  ♻️ 找到断点记录，已上传 {} 个分块，继续上传", checkpoint.parts.len());
+                checkpoint
+            }
+            stale => {
+                // 文件已变化，放弃旧的分块上传任务
+                if let Some(stale) = stale {
+                    let _ = self.abort_multipart_upload(&stale.key, &stale.upload_id).await;
+                }
+                let upload_id = self.initiate_multipart_upload(full_key, content_type).await?;
+                let checkpoint = UploadCheckpoint::new(full_key, upload_id, file_size, part_size);
+                checkpoint.save(&checkpoint_path)?;
+                checkpoint
+            }
+        };
+
+        let expired = match self.upload_parts(file_path, &mut checkpoint, &checkpoint_path, options.concurrency(), progress).await {
+            Ok(()) => false,
+            Err(e) if e.code() == Some("NoSuchUpload") => true,
+            Err(e) => return Err(e),
+        };
+
+        if expired {
+            // 断点记录中的 UploadId 已过期或被清理，重新开始上传
+            println!("  ⚠️ UploadId 已失效，重新开始分块上传");
+            let upload_id = self.initiate_multipart_upload(full_key, content_type).await?;
+            checkpoint = UploadCheckpoint::new(full_key, upload_id, file_size, part_size);
+            checkpoint.save(&checkpoint_path)?;
+            if let Some(tracker) = progress {
+                tracker.reset();
+            }
+            self.upload_parts(file_path, &mut checkpoint, &checkpoint_path, options.concurrency(), progress).await?;
+        }
+
+        let etag = self.complete_multipart_upload(&checkpoint, pic_operations).await?;
+        let _ = std::fs::remove_file(&checkpoint_path);
+
+        Ok(etag)
+    }
+
+    // 并行上传所有未完成的分块，每完成一块立即写入断点记录
+    async fn upload_parts(
+        &self,
+        file_path: &str,
+        checkpoint: &mut UploadCheckpoint,
+        checkpoint_path: &Path,
+        concurrency: usize,
+        progress: Option<&ProgressTracker>,
+    ) -> Result<(), CosError> {
+        let part_count = checkpoint.part_count();
+        let part_size = checkpoint.part_size;
+        let file_size = checkpoint.file_size;
+        let full_key = checkpoint.key.clone();
+        let upload_id = checkpoint.upload_id.clone();
+        let uploaded = checkpoint.parts.clone();
+
+        println!("  📦 分块数量: {}，分块大小: {} bytes，并发数: {}", part_count, part_size, concurrency);
+
+        let mut results = stream::iter(1..=part_count)
+            .map(|part_number| {
+                let recorded_etag = uploaded.get(&part_number).cloned();
+                let full_key = &full_key;
+                let upload_id = &upload_id;
+                async move {
+                    let offset = (part_number as u64 - 1) * part_size;
+                    let length = part_size.min(file_size - offset);
+                    let data = read_part(file_path, offset, length).await?;
+
+                    // 已记录的 ETag 与本地分块的 MD5 一致时无需重新上传
+                    if let Some(etag) = recorded_etag {
+                        if etag.eq_ignore_ascii_case(&format!("{:x}", md5::compute(&data))) {
+                            return Ok((part_number, etag, length, false));
+                        }
+                    }
+
+                    let etag = self.upload_part(full_key, upload_id, part_number, data).await?;
+                    Ok::<_, CosError>((part_number, etag, length, true))
+                }
+            })
+            .buffer_unordered(concurrency);
+
+        while let Some(result) = results.next().await {
+            let (part_number, etag, length, uploaded_now) = result?;
+            // 分块完成（或断点记录中已存在）时计入进度
+            if let Some(tracker) = progress {
+                tracker.advance(length);
+            }
+            if uploaded_now {
+                println!("  ✅ 分块 {}/{} 上传完成", part_number, part_count);
+                checkpoint.parts.insert(part_number, etag);
+                checkpoint.save(checkpoint_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // 初始化分块上传，返回 UploadId
+    async fn initiate_multipart_upload(&self, full_key: &str, content_type: &str) -> Result<String, CosError> {
+        let uri_path = format!("/{}", full_key);
+
+        let mut headers = HashMap::new();
+        headers.insert("host".to_string(), self.host());
+        headers.insert("content-type".to_string(), content_type.to_string());
+
+        let mut query_params = HashMap::new();
+        query_params.insert("uploads".to_string(), "".to_string());
+
+        let url = format!("https://{}{}?uploads", self.host(), uri_path);
+
+        let response = self.send_with_retry("Initiate Multipart Upload", |_| {
+            let authorization = self.generate_signature("POST", &uri_path, &headers, &query_params);
+            Ok(self.client
+                .post(&url)
+                .header("Authorization", authorization)
+                .header("Content-Type", content_type))
+        }).await?;
+
+        if response.status().is_success() {
+            let response_text = response.text().await?;
+            let parsed: InitiateMultipartUploadResultXml = quick_xml::de::from_str(&response_text)
+                .map_err(|e| CosError::parse(format!("解析 UploadId 失败: {}", e)))?;
+            println!("  🆔 UploadId: {}", parsed.upload_id);
+            Ok(parsed.upload_id)
+        } else {
+            Err(CosError::from_response(response).await)
+        }
+    }
+
+    // 上传单个分块，返回分块 ETag
+    async fn upload_part(&self, full_key: &str, upload_id: &str, part_number: u32, data: Bytes) -> Result<String, CosError> {
+        let uri_path = format!("/{}", full_key);
+
+        let mut headers = HashMap::new();
+        headers.insert("host".to_string(), self.host());
+        headers.insert("content-length".to_string(), data.len().to_string());
+
+        let mut query_params = HashMap::new();
+        query_params.insert("partNumber".to_string(), part_number.to_string());
+        query_params.insert("uploadId".to_string(), upload_id.to_string());
+
+        let url = format!(
+            "https://{}{}?partNumber={}&uploadId={}",
+            self.host(),
+            uri_path,
+            part_number,
+            urlencoding::encode(upload_id)
+        );
+
+        let response = self.send_with_retry(&format!("Upload Part {}", part_number), |activity| {
+            let authorization = self.generate_signature("PUT", &uri_path, &headers, &query_params);
+            Ok(self.client
+                .put(&url)
+                .header("Authorization", authorization)
+                .header("Content-Length", data.len())
+                .body(tracked_body(data.clone(), activity)))
+        }).await?;
+
+        let status = response.status();
+
+        if status.is_success() {
+            response.headers()
+                .get("etag")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.trim_matches('"').to_string())
+                .ok_or_else(|| CosError::parse(format!("分块 {} 响应缺少 ETag", part_number)))
+        } else {
+            Err(CosError::from_response(response).await)
+        }
+    }
+
+    // 完成分块上传，万象处理头部需要在此请求中携带
+    async fn complete_multipart_upload(&self, checkpoint: &UploadCheckpoint, pic_operations: Option<&str>) -> Result<Option<String>, CosError> {
+        let uri_path = format!("/{}", checkpoint.key);
+
+        let mut xml_body = String::from("<CompleteMultipartUpload>\n");
+        for (part_number, etag) in &checkpoint.parts {
+            xml_body.push_str(&format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>\"{}\"</ETag></Part>\n",
+                part_number, etag
+            ));
+        }
+        xml_body.push_str("</CompleteMultipartUpload>");
+
+        let mut headers = HashMap::new();
+        headers.insert("host".to_string(), self.host());
+        headers.insert("content-type".to_string(), "application/xml".to_string());
+        headers.insert("content-length".to_string(), xml_body.len().to_string());
+        if let Some(pic_ops) = pic_operations {
+            headers.insert("pic-operations".to_string(), pic_ops.to_string());
+        }
+
+        let mut query_params = HashMap::new();
+        query_params.insert("uploadId".to_string(), checkpoint.upload_id.clone());
+
+        let url = format!(
+            "https://{}{}?uploadId={}",
+            self.host(),
+            uri_path,
+            urlencoding::encode(&checkpoint.upload_id)
+        );
+
+        let response = self.send_with_retry("Complete Multipart Upload", |_| {
+            let authorization = self.generate_signature("POST", &uri_path, &headers, &query_params);
+            let mut request = self.client
+                .post(&url)
+                .header("Authorization", authorization)
+                .header("Content-Type", "application/xml")
+                .header("Content-Length", xml_body.len());
+
+            if let Some(pic_ops) = pic_operations {
+                request = request.header("Pic-Operations", pic_ops);
+            }
+
+            Ok(request.body(xml_body.clone()))
+        }).await?;
+        let status = response.status();
+        let request_id = request_id_of(&response);
+        let response_text = response.text().await?;
+
+        // 完成请求即使返回 200，响应体中也可能是错误信息
+        if status.is_success() && !response_text.contains("<Error>") {
+            let parsed: CompleteMultipartUploadResultXml = quick_xml::de::from_str(&response_text)
+                .map_err(|e| CosError::parse(format!("解析分块上传结果失败: {}", e)))?;
+            println!("  🧩 分块合并完成");
+            Ok(parsed.etag.map(|etag| etag.trim_matches('"').to_string()))
+        } else {
+            Err(CosError::from_status(status.as_u16(), &response_text, request_id))
+        }
+    }
+
+    // 终止分块上传并清理已上传的分块
+    async fn abort_multipart_upload(&self, full_key: &str, upload_id: &str) -> Result<(), CosError> {
+        let uri_path = format!("/{}", full_key);
+
+        let mut headers = HashMap::new();
+        headers.insert("host".to_string(), self.host());
+
+        let mut query_params = HashMap::new();
+        query_params.insert("uploadId".to_string(), upload_id.to_string());
+
+        let url = format!(
+            "https://{}{}?uploadId={}",
+            self.host(),
+            uri_path,
+            urlencoding::encode(upload_id)
+        );
+
+        let response = self.send_with_retry("Abort Multipart Upload", |_| {
+            let authorization = self.generate_signature("DELETE", &uri_path, &headers, &query_params);
+            Ok(self.client.delete(&url).header("Authorization", authorization))
+        }).await?;
+
+        let status = response.status();
+        if status.is_success() || status == 404 {
+            Ok(())
+        } else {
+            Err(CosError::from_response(response).await)
+        }
+    }
+
+    // 断点记录文件路径，按存储桶和对象键区分
+    fn checkpoint_path(&self, full_key: &str) -> PathBuf {
+        let id = md5::compute(format!("{}/{}/{}", self.config.bucket, self.config.region, full_key));
+        self.checkpoint_dir.join(format!("{:x}.json", id))
+    }
+}
+
+// 读取文件中的一个分块
+async fn read_part(file_path: &str, offset: u64, length: u64) -> std::io::Result<Bytes> {
+    let mut file = tokio::fs::File::open(file_path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut buffer = vec![0u8; length as usize];
+    file.read_exact(&mut buffer).await?;
+    Ok(Bytes::from(buffer))
+}
@@ -0,0 +1,188 @@
+use bytes::Bytes;
+use futures_util::{stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio_util::io::ReaderStream;
+
+use super::CosClient;
+use crate::error::CosError;
+use crate::progress::ProgressTracker;
+
+// 请求体分块大小，每发送一块刷新一次传输活动时间
+const BODY_CHUNK_SIZE: usize = 64 * 1024;
+
+// 网络选项：超时与重试策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkOptions {
+    // 建立连接超时（秒）
+    pub connect_timeout_secs: u64,
+    // 读取响应或上传无进展的超时（秒）
+    pub read_timeout_secs: u64,
+    // 最大重试次数，0 表示不重试
+    pub max_retries: u32,
+    // 首次重试等待时间（毫秒），之后按指数增长
+    pub base_delay_ms: u64,
+    // 单次重试最长等待时间（毫秒）
+    pub max_delay_ms: u64,
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 60,
+            max_retries: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 8000,
+        }
+    }
+}
+
+impl NetworkOptions {
+    pub(crate) fn build_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .read_timeout(self.read_timeout())
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new())
+    }
+
+    fn read_timeout(&self) -> Duration {
+        Duration::from_secs(self.read_timeout_secs.max(1))
+    }
+
+    // 指数退避并加入随机抖动，避免多个请求同时重试
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.base_delay_ms.saturating_mul(1u64 << attempt.saturating_sub(1).min(16));
+        let delay = exponential.min(self.max_delay_ms);
+        let jitter = (uuid::Uuid::new_v4().as_u128() % (delay as u128 / 2 + 1)) as u64;
+        Duration::from_millis(delay / 2 + jitter)
+    }
+}
+
+// 传输活动记录：上传数据时刷新，用于检测停滞的请求
+#[derive(Clone)]
+pub(crate) struct Activity(Arc<Mutex<Instant>>);
+
+impl Activity {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(Instant::now())))
+    }
+
+    pub(crate) fn touch(&self) {
+        if let Ok(mut last) = self.0.lock() {
+            *last = Instant::now();
+        }
+    }
+
+    fn idle(&self) -> Duration {
+        self.0.lock().map(|last| last.elapsed()).unwrap_or_default()
+    }
+
+    // 超过 limit 没有任何传输进展时返回超时错误
+    async fn watchdog(&self, limit: Duration) -> CosError {
+        loop {
+            let idle = self.idle();
+            if idle >= limit {
+                return CosError::Network {
+                    message: format!("请求超过 {} 秒没有进展", limit.as_secs()),
+                    timeout: true,
+                };
+            }
+            tokio::time::sleep(limit - idle).await;
+        }
+    }
+}
+
+// 将内存中的数据包装为分块发送的请求体，发送过程中刷新传输活动
+pub(crate) fn tracked_body(data: Bytes, activity: &Activity) -> reqwest::Body {
+    let chunks: Vec<Result<Bytes, std::io::Error>> = (0..data.len())
+        .step_by(BODY_CHUNK_SIZE)
+        .map(|start| Ok(data.slice(start..(start + BODY_CHUNK_SIZE).min(data.len()))))
+        .collect();
+    let activity = activity.clone();
+    reqwest::Body::wrap_stream(stream::iter(chunks).inspect_ok(move |_| activity.touch()))
+}
+
+// 以流的方式读取本地文件作为请求体，每发送一块刷新传输活动并统计进度
+pub(crate) fn file_body(file_path: &str, activity: &Activity, progress: Option<&ProgressTracker>) -> Result<reqwest::Body, CosError> {
+    let file = tokio::fs::File::from_std(std::fs::File::open(file_path)?);
+    let tracker = progress.cloned();
+    if let Some(tracker) = &tracker {
+        tracker.reset();
+    }
+    let activity = activity.clone();
+    let stream = ReaderStream::new(file).inspect_ok(move |chunk| {
+        activity.touch();
+        if let Some(tracker) = &tracker {
+            tracker.advance(chunk.len() as u64);
+        }
+    });
+    Ok(reqwest::Body::wrap_stream(stream))
+}
+
+// 5xx 和 429 (SlowDown) 属于临时错误，可以重试
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+// 带前提条件的写入：If-Match、If-None-Match 或 OSS 的禁止覆盖
+fn is_conditional(request: &reqwest::Request) -> bool {
+    let headers = request.headers();
+    headers.contains_key(reqwest::header::IF_MATCH)
+        || headers.contains_key(reqwest::header::IF_NONE_MATCH)
+        || headers.contains_key("x-oss-forbid-overwrite")
+}
+
+// 发送请求并在网络错误、5xx、429 时按退避策略重试
+// build 在每次尝试时重新调用，以便重新计算签名和创建请求体
+// 条件写入只在连接未建立时重试：请求可能已在服务端生效，重试会因前提条件已改变得到虚假的 412
+pub(crate) async fn send_with_retry<F>(options: &NetworkOptions, operation: &str, mut build: F) -> Result<reqwest::Response, CosError>
+where
+    F: FnMut(&Activity) -> Result<reqwest::RequestBuilder, CosError>,
+{
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        let activity = Activity::new();
+        let (client, request) = build(&activity)?.build_split();
+        let request = request?;
+        let conditional = is_conditional(&request);
+
+        let result = tokio::select! {
+            result = client.execute(request) => result.map_err(|e| (e.is_connect(), CosError::from(e))),
+            error = activity.watchdog(options.read_timeout()) => Err((false, error)),
+        };
+
+        let retryable = match &result {
+            Ok(response) => is_retryable_status(response.status()),
+            Err((connect_failed, _)) => *connect_failed || !conditional,
+        };
+        let result = result.map_err(|(_, error)| error);
+
+        if !retryable || attempt > options.max_retries {
+            return result;
+        }
+
+        let delay = options.backoff(attempt);
+        match &result {
+            Ok(response) => println!("  🔁 {} 返回 {}，{} ms 后第 {} 次重试", operation, response.status(), delay.as_millis(), attempt),
+            Err(e) => println!("  🔁 {} 失败: {}，{} ms 后第 {} 次重试", operation, e, delay.as_millis(), attempt),
+        }
+        drop(result);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+impl CosClient {
+    pub(super) async fn send_with_retry<F>(&self, operation: &str, build: F) -> Result<reqwest::Response, CosError>
+    where
+        F: FnMut(&Activity) -> Result<reqwest::RequestBuilder, CosError>,
+    {
+        send_with_retry(&self.network, operation, build).await
+    }
+}
@@ -0,0 +1,104 @@
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::error::CosError;
+use crate::storage;
+
+mod migrations;
+pub use migrations::{check_schema, migrate, SchemaVersion};
+
+// 数据库文件名，与前端 Database.load('sqlite:imsheet.db') 一致
+const DATABASE_FILE: &str = "imsheet.db";
+
+// 前端 tauri-plugin-sql 将相对路径解析到应用配置目录，Rust 端打开同一个文件
+pub fn database_path(app: &AppHandle) -> Result<PathBuf, CosError> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(DATABASE_FILE))
+        .map_err(|e| CosError::invalid(format!("无法获取应用配置目录: {}", e)))
+}
+
+// 打开图库数据库并执行未完成的结构迁移；前端同时持有连接，写入冲突时等待锁释放
+pub async fn open(app: &AppHandle) -> Result<SqlitePool, CosError> {
+    connect(SqliteConnectOptions::new().filename(database_path(app)?).create_if_missing(false)).await
+}
+
+// 执行结构迁移并返回迁移前后的版本，用于前端初始化和载入云端数据库之后
+pub async fn migrate_database(app: &AppHandle) -> Result<SchemaVersion, CosError> {
+    let pool = pool(SqliteConnectOptions::new().filename(database_path(app)?).create_if_missing(false)).await?;
+    let version = migrate(&pool).await;
+    pool.close().await;
+    version
+}
+
+async fn connect(options: SqliteConnectOptions) -> Result<SqlitePool, CosError> {
+    let pool = pool(options).await?;
+    if let Err(e) = migrate(&pool).await {
+        pool.close().await;
+        return Err(e);
+    }
+    Ok(pool)
+}
+
+async fn pool(options: SqliteConnectOptions) -> Result<SqlitePool, CosError> {
+    Ok(SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options.busy_timeout(Duration::from_secs(10)))
+        .await?)
+}
+
+// imsheet.image_state：0 为回收站，1 为正常
+// 对账时发现存储中已不存在对象的记录标记为 2，不计入统计，也不在图库和回收站中显示
+pub const IMAGE_STATE_MISSING: i64 = 2;
+
+// 云端数据库的相对 key，与前端 DbSyncService.getDbKey 一致
+pub const DATABASE_OBJECT_KEY: &str = "images.db";
+
+// 操作日志的相对目录
+pub const OPLOG_PREFIX: &str = "oplog/";
+
+// 同步锁对象的相对 key
+pub const LOCK_OBJECT_KEY: &str = "sync.lock";
+
+// 图库自身的同步数据（数据库、操作日志、同步锁及条件写入遗留的锁对象），不是图片
+pub fn is_library_metadata(key: &str) -> bool {
+    key == DATABASE_OBJECT_KEY
+        || key == LOCK_OBJECT_KEY
+        || key.starts_with(OPLOG_PREFIX)
+        || key.ends_with(storage::WRITE_LOCK_SUFFIX)
+}
+
+// 打开图库数据库，文件不存在时创建；用于数据库文件丢失后从存储桶重建
+pub async fn open_or_create(app: &AppHandle) -> Result<SqlitePool, CosError> {
+    let path = database_path(app)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    connect(SqliteConnectOptions::new().filename(path).create_if_missing(true)).await
+}
+
+// 按 imsheet 表重新计算统计表中的总大小和数量（回收站中的图片同样计入，已丢失的不计入）
+pub async fn recompute_statistics(connection: &mut sqlx::SqliteConnection) -> Result<(i64, i64), CosError> {
+    let (size, quantity): (i64, i64) =
+        sqlx::query_as("SELECT COALESCE(SUM(image_size), 0), COUNT(*) FROM imsheet WHERE image_state != ?")
+            .bind(IMAGE_STATE_MISSING)
+            .fetch_one(&mut *connection)
+            .await?;
+
+    let updated = sqlx::query("UPDATE imsheet_statistical SET size = ?, quantity = ? WHERE id = 1")
+        .bind(size)
+        .bind(quantity)
+        .execute(&mut *connection)
+        .await?;
+    if updated.rows_affected() == 0 {
+        sqlx::query("INSERT INTO imsheet_statistical (id, size, quantity, last_hash) VALUES (1, ?, ?, 'null')")
+            .bind(size)
+            .bind(quantity)
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    Ok((size, quantity))
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use sqlx::{Connection, SqlitePool};
+use std::path::Path;
+
+use crate::error::CosError;
+
+// 按顺序执行的迁移，第 N 项执行后 user_version 为 N
+// 已发布的迁移不能修改，结构变化只能追加新的迁移
+// 第 1 项兼容引入版本号之前由前端创建的表，因此使用 IF NOT EXISTS
+const MIGRATIONS: &[&[&str]] = &[
+    // 1: 图片表、统计表、镜像表
+    &[
+        "CREATE TABLE IF NOT EXISTS imsheet(
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            image_name TEXT NOT NULL,
+            image_location TEXT NOT NULL,
+            image_path TEXT NOT NULL UNIQUE,
+            image_size INTEGER NOT NULL,
+            image_state INTEGER NOT NULL,
+            create_time INTEGER NOT NULL
+        )",
+        "CREATE TABLE IF NOT EXISTS imsheet_statistical(
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            size INTEGER NOT NULL,
+            quantity INTEGER NOT NULL,
+            last_hash VARCHAR(255) NOT NULL
+        )",
+        "INSERT INTO imsheet_statistical (size, quantity, last_hash)
+         SELECT 0, 0, 'null' WHERE NOT EXISTS (SELECT 1 FROM imsheet_statistical)",
+        "CREATE TABLE IF NOT EXISTS imsheet_mirror(
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            image_path TEXT NOT NULL,
+            target TEXT NOT NULL,
+            image_location TEXT NOT NULL,
+            etag TEXT,
+            create_time INTEGER NOT NULL,
+            UNIQUE(image_path, target)
+        )",
+    ],
+    // 2: 操作日志及各设备的读取游标
+    &[
+        "CREATE TABLE IF NOT EXISTS imsheet_oplog(
+            id TEXT PRIMARY KEY,
+            device_id TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            image_path TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            uploaded INTEGER NOT NULL
+        )",
+        "CREATE INDEX IF NOT EXISTS imsheet_oplog_image_path ON imsheet_oplog(image_path)",
+        "CREATE TABLE IF NOT EXISTS imsheet_oplog_cursor(
+            device_id TEXT PRIMARY KEY,
+            last_key TEXT NOT NULL
+        )",
+    ],
+    // 3: 图片尺寸、类型、内容哈希、原始文件名和来源设备，早于此版本的记录为 NULL
+    &[
+        "ALTER TABLE imsheet ADD COLUMN image_width INTEGER",
+        "ALTER TABLE imsheet ADD COLUMN image_height INTEGER",
+        "ALTER TABLE imsheet ADD COLUMN image_mime TEXT",
+        "ALTER TABLE imsheet ADD COLUMN image_hash TEXT",
+        "ALTER TABLE imsheet ADD COLUMN original_name TEXT",
+        "ALTER TABLE imsheet ADD COLUMN source_device TEXT",
+        "CREATE INDEX IF NOT EXISTS imsheet_image_hash ON imsheet(image_hash)",
+    ],
+    // 4: 感知哈希（16 位十六进制），用于查找相似图片
+    &[
+        "ALTER TABLE imsheet ADD COLUMN image_dhash TEXT",
+        "ALTER TABLE imsheet ADD COLUMN image_phash TEXT",
+    ],
+];
+
+// 当前版本支持的数据库结构版本
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+// 迁移前后的结构版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaVersion {
+    pub previous: i64,
+    pub current: i64,
+}
+
+// 执行尚未执行的迁移，每个迁移与版本号在同一事务中提交
+// 数据库版本高于 SCHEMA_VERSION（由更新的客户端写入）时拒绝打开
+pub async fn migrate(pool: &SqlitePool) -> Result<SchemaVersion, CosError> {
+    let mut connection = pool.acquire().await?;
+    let previous = user_version(&mut connection).await?;
+    ensure_supported(previous)?;
+
+    for (index, statements) in MIGRATIONS.iter().enumerate().skip(previous as usize) {
+        let version = index + 1;
+        let mut transaction = connection.begin().await?;
+        for statement in *statements {
+            sqlx::query(statement).execute(&mut *transaction).await?;
+        }
+        // PRAGMA 不支持参数绑定
+        sqlx::query(&format!("PRAGMA user_version = {}", version))
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        println!("数据库结构已迁移到版本 {}", version);
+    }
+
+    Ok(SchemaVersion { previous, current: SCHEMA_VERSION.max(previous) })
+}
+
+// 只读检查数据库文件的结构版本，用于载入云端数据库前确认本机能够打开
+pub async fn check_schema(path: &Path) -> Result<i64, CosError> {
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+    let mut connection = SqliteConnection::connect_with(&options).await?;
+    let version = user_version(&mut connection).await;
+    connection.close().await?;
+    let version = version?;
+    ensure_supported(version)?;
+    Ok(version)
+}
+
+async fn user_version(connection: &mut SqliteConnection) -> Result<i64, CosError> {
+    Ok(sqlx::query_scalar("PRAGMA user_version").fetch_one(&mut *connection).await?)
+}
+
+fn ensure_supported(version: i64) -> Result<(), CosError> {
+    if version > SCHEMA_VERSION {
+        return Err(CosError::Database {
+            message: format!(
+                "数据库结构版本 {} 高于当前应用支持的版本 {}，请先升级应用",
+                version, SCHEMA_VERSION
+            ),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::CosError;
+
+// 设备标识文件，保存在应用配置目录，不随数据库同步
+const DEVICE_FILE: &str = "device.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DeviceInfo {
+    device_id: String,
+}
+
+// 本机设备 ID，首次调用时生成并保存
+pub async fn device_id(app: &AppHandle) -> Result<String, CosError> {
+    let dir = app.path()
+        .app_config_dir()
+        .map_err(|e| CosError::invalid(format!("无法获取应用配置目录: {}", e)))?;
+    let path = dir.join(DEVICE_FILE);
+
+    if let Ok(content) = tokio::fs::read(&path).await {
+        if let Ok(info) = serde_json::from_slice::<DeviceInfo>(&content) {
+            if !info.device_id.is_empty() {
+                return Ok(info.device_id);
+            }
+        }
+    }
+
+    let info = DeviceInfo { device_id: uuid::Uuid::new_v4().simple().to_string() };
+    tokio::fs::create_dir_all(&dir).await?;
+    tokio::fs::write(&path, serde_json::to_vec_pretty(&info)?).await?;
+    println!("生成设备 ID: {}", info.device_id);
+    Ok(info.device_id)
+}
@@ -0,0 +1,237 @@
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::db::IMAGE_STATE_MISSING;
+use crate::error::CosError;
+use crate::metadata::{self, ImageMetadata};
+use crate::similar::UNDECODABLE_HASH;
+use crate::storage::StorageBackend;
+
+// 未指定时同时下载的对象数
+const DEFAULT_CONCURRENCY: usize = 4;
+
+// 已有的图片记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExistingImage {
+    pub id: i64,
+    pub image_name: String,
+    pub image_location: String,
+    pub image_path: String,
+    pub image_size: i64,
+    pub image_state: i64,
+    pub create_time: i64,
+}
+
+pub type ImageRow = (i64, String, String, String, i64, i64, i64);
+// 哈希列在前的 ImageRow
+pub type HashedImageRow = (String, i64, String, String, String, i64, i64, i64);
+
+impl From<ImageRow> for ExistingImage {
+    fn from((id, image_name, image_location, image_path, image_size, image_state, create_time): ImageRow) -> Self {
+        Self { id, image_name, image_location, image_path, image_size, image_state, create_time }
+    }
+}
+
+// 与 ImageRow 对应的查询字段
+pub const IMAGE_COLUMNS: &str = "id, image_name, image_location, image_path, image_size, image_state, create_time";
+
+// 查找重复图片的选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateOptions {
+    // 下载未记录哈希的图片（早于记录哈希的版本上传或由重建导入）并补写哈希
+    #[serde(default)]
+    pub hash_missing: bool,
+    pub concurrency: Option<usize>,
+}
+
+// 内容完全相同的一组图片，按上传时间排序
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub content_hash: String,
+    pub images: Vec<ExistingImage>,
+}
+
+// 补写哈希失败的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashFailure {
+    pub id: i64,
+    pub image_path: String,
+    pub error: CosError,
+}
+
+// backfill_metadata 的结果
+#[derive(Debug, Default)]
+pub struct Backfill {
+    // 写入了哈希的记录数
+    pub updated: usize,
+    // 其中无法解码、感知哈希记为 UNDECODABLE_HASH 的记录数
+    pub undecodable: usize,
+    pub failed: Vec<HashFailure>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub groups: Vec<DuplicateGroup>,
+    // 本次补写哈希的记录数
+    pub hashed: usize,
+    // 仍没有哈希、未参与比较的记录数
+    pub unhashed: usize,
+    pub failed: Vec<HashFailure>,
+}
+
+// 按内容哈希查找图库中已有的图片，回收站和已丢失的图片不算
+// 只认 backend 中仍然存在的对象：链接与 backend 生成的不一致（切换了存储桶或服务商）或对象已被删除的记录跳过
+pub async fn find_by_hash(
+    pool: &SqlitePool,
+    backend: &dyn StorageBackend,
+    content_hash: &str,
+) -> Result<Option<ExistingImage>, CosError> {
+    let rows: Vec<ImageRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM imsheet WHERE image_hash = ? AND image_state = 1 ORDER BY create_time",
+        IMAGE_COLUMNS
+    ))
+    .bind(content_hash)
+    .fetch_all(pool)
+    .await?;
+
+    for image in rows.into_iter().map(ExistingImage::from) {
+        if image.image_location == backend.object_url(&image.image_path) && backend.head(&image.image_path).await?.exists {
+            return Ok(Some(image));
+        }
+    }
+    Ok(None)
+}
+
+// 找出内容哈希相同的图片（包括回收站中的图片）
+// 补写的哈希按存储中的对象计算，服务端转换过格式的图片与其原始文件的哈希不同
+pub async fn find_duplicates(
+    pool: &SqlitePool,
+    backend: Arc<dyn StorageBackend>,
+    options: DuplicateOptions,
+) -> Result<DuplicateReport, CosError> {
+    let mut report = DuplicateReport::default();
+    if options.hash_missing {
+        let backfill = backfill_metadata(pool, backend, options.concurrency, "image_hash IS NULL").await?;
+        (report.hashed, report.failed) = (backfill.updated, backfill.failed);
+    }
+
+    let rows: Vec<HashedImageRow> = sqlx::query_as(&format!(
+        "SELECT image_hash, {} FROM imsheet
+         WHERE image_state != ? AND image_hash IN (
+             SELECT image_hash FROM imsheet WHERE image_state != ? AND image_hash IS NOT NULL
+             GROUP BY image_hash HAVING COUNT(*) > 1
+         )
+         ORDER BY image_hash, create_time",
+        IMAGE_COLUMNS
+    ))
+    .bind(IMAGE_STATE_MISSING)
+    .bind(IMAGE_STATE_MISSING)
+    .fetch_all(pool)
+    .await?;
+
+    for (content_hash, id, image_name, image_location, image_path, image_size, image_state, create_time) in rows {
+        let image = ExistingImage::from((id, image_name, image_location, image_path, image_size, image_state, create_time));
+        match report.groups.last_mut() {
+            Some(group) if group.content_hash == content_hash => group.images.push(image),
+            _ => report.groups.push(DuplicateGroup { content_hash, images: vec![image] }),
+        }
+    }
+
+    report.unhashed = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM imsheet WHERE image_hash IS NULL AND image_state != ?")
+        .bind(IMAGE_STATE_MISSING)
+        .fetch_one(pool)
+        .await? as usize;
+
+    println!(
+        "查找重复图片: {} 组，补写哈希 {}，未比较 {}，失败 {}",
+        report.groups.len(), report.hashed, report.unhashed, report.failed.len()
+    );
+    Ok(report)
+}
+
+// 下载满足 missing 条件（SQL 表达式）的图片，补写哈希、尺寸和类型
+// 已有的值不会被覆盖，上传时记录的是原始文件的哈希
+// 无法解码的图片感知哈希记为 UNDECODABLE_HASH，之后不再重复下载；下载失败的记录保持原样，下次重试
+pub async fn backfill_metadata(
+    pool: &SqlitePool,
+    backend: Arc<dyn StorageBackend>,
+    concurrency: Option<usize>,
+    missing: &str,
+) -> Result<Backfill, CosError> {
+    let rows: Vec<(i64, String)> = sqlx::query_as(&format!(
+        "SELECT id, image_path FROM imsheet WHERE ({}) AND image_state != ?",
+        missing
+    ))
+    .bind(IMAGE_STATE_MISSING)
+    .fetch_all(pool)
+    .await?;
+    let concurrency = concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+
+    let results: Vec<(i64, String, Result<ImageMetadata, CosError>)> = stream::iter(rows)
+        .map(|(id, image_path)| {
+            let backend = backend.clone();
+            async move {
+                let result = inspect_object(backend.as_ref(), &image_path).await;
+                (id, image_path, result)
+            }
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    let mut backfill = Backfill::default();
+    let mut transaction = pool.begin().await?;
+    for (id, image_path, result) in results {
+        match result {
+            Ok(metadata) => {
+                sqlx::query(
+                    "UPDATE imsheet SET image_hash = COALESCE(image_hash, ?), image_width = COALESCE(image_width, ?),
+                     image_height = COALESCE(image_height, ?), image_mime = COALESCE(image_mime, ?),
+                     image_dhash = COALESCE(image_dhash, ?), image_phash = COALESCE(image_phash, ?) WHERE id = ?",
+                )
+                .bind(&metadata.content_hash)
+                .bind(metadata.width)
+                .bind(metadata.height)
+                .bind(&metadata.mime_type)
+                .bind(metadata.dhash.as_deref().unwrap_or(UNDECODABLE_HASH))
+                .bind(metadata.phash.as_deref().unwrap_or(UNDECODABLE_HASH))
+                .bind(id)
+                .execute(&mut *transaction)
+                .await?;
+                backfill.updated += 1;
+                if metadata.phash.is_none() {
+                    backfill.undecodable += 1;
+                }
+            }
+            Err(error) => {
+                println!("  ❌ 计算哈希失败: {} - {}", image_path, error);
+                backfill.failed.push(HashFailure { id, image_path, error });
+            }
+        }
+    }
+    transaction.commit().await?;
+    backfill.failed.sort_by_key(|failure: &HashFailure| failure.id);
+    Ok(backfill)
+}
+
+async fn inspect_object(backend: &dyn StorageBackend, image_path: &str) -> Result<ImageMetadata, CosError> {
+    let temp_path = temp_path();
+    if let Some(parent) = temp_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let result = match backend.get_file(image_path, &temp_path.to_string_lossy(), None).await {
+        Ok(_) => metadata::inspect(&temp_path, None, String::new()).await,
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_file(&temp_path).await;
+    result
+}
+
+fn temp_path() -> PathBuf {
+    std::env::temp_dir()
+        .join("imsheet_duplicates")
+        .join(uuid::Uuid::new_v4().simple().to_string())
+}
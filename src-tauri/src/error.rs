@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// 服务端返回的错误详情（解析自 XML 错误响应）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceError {
+    pub status: u16,
+    pub code: String,
+    pub message: String,
+    pub request_id: Option<String>,
+}
+
+// COS 操作错误，序列化后通过 Tauri 命令返回给前端
+// 前端根据 kind 区分错误类型，决定提示内容以及是否重试
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CosError {
+    // 鉴权失败：密钥错误、签名错误、无权限
+    Auth(ServiceError),
+    // 存储桶或对象不存在
+    NotFound(ServiceError),
+    // 请求过于频繁或超出配额
+    Throttled(ServiceError),
+    // 条件请求不满足或资源状态冲突
+    Conflict(ServiceError),
+    // 其他服务端错误
+    Service(ServiceError),
+    // 网络错误：连接失败、超时、连接被重置
+    Network { message: String, timeout: bool },
+    // 本地文件读写错误
+    Io { message: String },
+    // 响应内容解析失败
+    Parse { message: String },
+    // 参数或本地状态不合法
+    Invalid { message: String },
+    // 本地数据库读写失败
+    Database { message: String },
+}
+
+// COS XML 错误响应结构
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ErrorXml {
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    message: String,
+    request_id: Option<String>,
+}
+
+impl CosError {
+    // 根据 HTTP 状态码和响应体构造错误，响应体不是 XML 时保留原始文本
+    pub fn from_status(status: u16, body: &str, request_id: Option<String>) -> Self {
+        let parsed: Option<ErrorXml> = quick_xml::de::from_str(body).ok();
+
+        let (code, message, body_request_id) = match parsed {
+            Some(xml) if !xml.code.is_empty() => (xml.code, xml.message, xml.request_id),
+            _ => (status.to_string(), body.trim().to_string(), None),
+        };
+
+        Self::classify(ServiceError {
+            status,
+            code,
+            message,
+            request_id: body_request_id.or(request_id),
+        })
+    }
+
+    // 读取失败响应并转换为错误
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let request_id = request_id_of(&response);
+
+        match response.text().await {
+            Ok(body) => Self::from_status(status, &body, request_id),
+            Err(e) => e.into(),
+        }
+    }
+
+    fn classify(error: ServiceError) -> Self {
+        match (error.status, error.code.as_str()) {
+            (_, "AccessDenied" | "SignatureDoesNotMatch" | "InvalidAccessKeyId" | "RequestTimeTooSkewed" | "ExpiredToken")
+            | (401 | 403, _) => CosError::Auth(error),
+            (_, "NoSuchBucket" | "NoSuchKey" | "NoSuchUpload") | (404, _) => CosError::NotFound(error),
+            (_, "SlowDown" | "QuotaExceeded" | "TooManyRequests") | (429, _) => CosError::Throttled(error),
+            (_, "PreconditionFailed") | (409 | 412, _) => CosError::Conflict(error),
+            _ => CosError::Service(error),
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        CosError::Invalid { message: message.into() }
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        CosError::Parse { message: message.into() }
+    }
+
+    // 条件写入的前置条件不满足（对象已被其他设备修改或已存在）
+    pub fn precondition_failed(message: impl Into<String>) -> Self {
+        CosError::Conflict(ServiceError {
+            status: 412,
+            code: "PreconditionFailed".to_string(),
+            message: message.into(),
+            request_id: None,
+        })
+    }
+
+    // 服务端错误详情，本地错误返回 None
+    pub fn service_error(&self) -> Option<&ServiceError> {
+        match self {
+            CosError::Auth(e)
+            | CosError::NotFound(e)
+            | CosError::Throttled(e)
+            | CosError::Conflict(e)
+            | CosError::Service(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    // 服务端返回的错误码，如 NoSuchUpload
+    pub fn code(&self) -> Option<&str> {
+        self.service_error().map(|e| e.code.as_str())
+    }
+}
+
+// 响应头中的请求 ID，用于向服务商反馈问题
+pub fn request_id_of(response: &reqwest::Response) -> Option<String> {
+    ["x-cos-request-id", "x-amz-request-id", "x-oss-request-id", "x-github-request-id"]
+        .iter()
+        .find_map(|name| response.headers().get(*name))
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+impl fmt::Display for CosError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(e) = self.service_error() {
+            write!(f, "{} {} - {}", e.status, e.code, e.message)?;
+            if let Some(request_id) = &e.request_id {
+                write!(f, " (RequestId: {})", request_id)?;
+            }
+            return Ok(());
+        }
+
+        match self {
+            CosError::Network { message, .. } => write!(f, "网络错误: {}", message),
+            CosError::Io { message } => write!(f, "文件读写失败: {}", message),
+            CosError::Parse { message } => write!(f, "响应解析失败: {}", message),
+            CosError::Invalid { message } => write!(f, "{}", message),
+            CosError::Database { message } => write!(f, "数据库错误: {}", message),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for CosError {}
+
+impl From<reqwest::Error> for CosError {
+    fn from(e: reqwest::Error) -> Self {
+        CosError::Network {
+            message: e.to_string(),
+            timeout: e.is_timeout(),
+        }
+    }
+}
+
+impl From<std::io::Error> for CosError {
+    fn from(e: std::io::Error) -> Self {
+        CosError::Io { message: e.to_string() }
+    }
+}
+
+impl From<quick_xml::DeError> for CosError {
+    fn from(e: quick_xml::DeError) -> Self {
+        CosError::Parse { message: e.to_string() }
+    }
+}
+
+impl From<serde_json::Error> for CosError {
+    fn from(e: serde_json::Error) -> Self {
+        CosError::Parse { message: e.to_string() }
+    }
+}
+
+impl From<sqlx::Error> for CosError {
+    fn from(e: sqlx::Error) -> Self {
+        CosError::Database { message: e.to_string() }
+    }
+}
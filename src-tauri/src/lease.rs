@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+
+use crate::db::LOCK_OBJECT_KEY;
+use crate::error::CosError;
+use crate::storage::{self, StorageBackend, WriteCondition};
+
+// 未指定时租约的有效期
+const DEFAULT_TTL_MS: u64 = 60_000;
+// 有效期内续期的次数
+const RENEWALS_PER_TTL: u64 = 3;
+// 抢占锁对象时的最大尝试次数
+const MAX_ATTEMPTS: usize = 3;
+
+// 锁对象内容；expires_at 为毫秒时间戳，各设备按本机时间判断是否过期
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lease {
+    pub lease_id: String,
+    pub device_id: String,
+    pub acquired_at: i64,
+    pub expires_at: i64,
+}
+
+impl Lease {
+    fn expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now().timestamp_millis()
+    }
+}
+
+// 获取结果：acquired 为 false 时 lease 为其他设备持有的租约
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaseStatus {
+    pub acquired: bool,
+    pub lease: Option<Lease>,
+}
+
+// 本机持有的租约及写入锁对象后的 ETag，续期时以该 ETag 为前提条件
+struct HeldLease {
+    lease: Lease,
+    etag: Option<String>,
+}
+
+struct ActiveLease {
+    backend: Arc<dyn StorageBackend>,
+    held: Arc<Mutex<Option<HeldLease>>>,
+    renewer: JoinHandle<()>,
+}
+
+// 按 client_id 保存本机持有的同步锁，持有期间后台定时续期，退出应用时释放
+#[derive(Default)]
+pub struct SyncLeases {
+    active: Mutex<HashMap<String, ActiveLease>>,
+}
+
+impl SyncLeases {
+    // 获取同步锁；本机已持有且仍有效时直接返回
+    pub async fn acquire(
+        &self,
+        client_id: &str,
+        backend: Arc<dyn StorageBackend>,
+        device_id: &str,
+        ttl_ms: Option<u64>,
+    ) -> Result<LeaseStatus, CosError> {
+        if let Some(lease) = self.current(client_id) {
+            return Ok(LeaseStatus { acquired: true, lease: Some(lease) });
+        }
+
+        let ttl = ttl_ms.unwrap_or(DEFAULT_TTL_MS).max(1_000);
+        let (lease, etag) = match try_acquire(backend.as_ref(), device_id, ttl).await? {
+            Ok(acquired) => acquired,
+            Err(holder) => return Ok(LeaseStatus { acquired: false, lease: holder }),
+        };
+        println!("🔒 已获取同步锁: {}，有效期至 {}", lease.lease_id, lease.expires_at);
+
+        let held = Arc::new(Mutex::new(Some(HeldLease { lease: lease.clone(), etag })));
+        let renewer = tauri::async_runtime::spawn(renew_loop(backend.clone(), held.clone(), ttl));
+        let previous = self.lock().insert(client_id.to_string(), ActiveLease { backend, held, renewer });
+        if let Some(previous) = previous {
+            previous.renewer.abort();
+        }
+
+        Ok(LeaseStatus { acquired: true, lease: Some(lease) })
+    }
+
+    // 释放同步锁：停止续期，锁对象仍属于本机时删除
+    pub async fn release(&self, client_id: &str) -> Result<(), CosError> {
+        let Some(active) = self.lock().remove(client_id) else {
+            return Ok(());
+        };
+        active.renewer.abort();
+        let held = active.held.lock().ok().and_then(|mut held| held.take());
+        match held {
+            Some(held) => release_lock(active.backend.as_ref(), &held.lease).await,
+            None => Ok(()),
+        }
+    }
+
+    // 释放全部同步锁，用于退出应用
+    pub async fn release_all(&self) {
+        let client_ids: Vec<String> = self.lock().keys().cloned().collect();
+        for client_id in client_ids {
+            if let Err(e) = self.release(&client_id).await {
+                println!("⚠️ 释放同步锁失败: {}", e);
+            }
+        }
+    }
+
+    // 本机持有且未过期的租约
+    fn current(&self, client_id: &str) -> Option<Lease> {
+        let active = self.lock();
+        let held = active.get(client_id)?.held.lock().ok()?;
+        held.as_ref().map(|h| h.lease.clone()).filter(|lease| !lease.expired())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ActiveLease>> {
+        self.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// 读取存储中的锁对象，不存在时返回 None；内容无法解析的锁视为已过期
+pub async fn read_lock(backend: &dyn StorageBackend) -> Result<Option<(Option<Lease>, Option<String>)>, CosError> {
+    let metadata = backend.head(LOCK_OBJECT_KEY).await?;
+    if !metadata.exists {
+        return Ok(None);
+    }
+
+    let temp_path = temp_path("read");
+    let result = backend.get_file(LOCK_OBJECT_KEY, &temp_path.to_string_lossy(), None).await;
+    let content = match result {
+        Ok(_) => tokio::fs::read(&temp_path).await.map_err(CosError::from),
+        // 读取前锁已被释放
+        Err(CosError::NotFound(_)) => return Ok(None),
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_file(&temp_path).await;
+
+    Ok(Some((serde_json::from_slice(&content?).ok(), metadata.etag)))
+}
+
+// 锁对象不存在时直接创建；已过期或属于本机时以其 ETag 为前提条件覆盖
+// 其他设备持有有效租约时返回 Ok(Err(持有者))
+async fn try_acquire(
+    backend: &dyn StorageBackend,
+    device_id: &str,
+    ttl: u64,
+) -> Result<Result<(Lease, Option<String>), Option<Lease>>, CosError> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let lease = Lease {
+        lease_id: uuid::Uuid::new_v4().simple().to_string(),
+        device_id: device_id.to_string(),
+        acquired_at: now,
+        expires_at: now + ttl as i64,
+    };
+
+    let mut condition = WriteCondition { if_match: None, if_none_match: Some("*".to_string()) };
+    let mut holder = None;
+    for _ in 0..MAX_ATTEMPTS {
+        match write_lock(backend, &lease, condition.clone()).await {
+            Ok(etag) => return Ok(Ok((lease, etag))),
+            Err(CosError::Conflict(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        // 锁对象已存在，检查是否可以接管
+        condition = match read_lock(backend).await? {
+            None => WriteCondition { if_match: None, if_none_match: Some("*".to_string()) },
+            Some((current, etag)) => {
+                let available = current.as_ref().is_none_or(|c| c.expired() || c.device_id == device_id);
+                holder = current;
+                match etag.filter(|_| available) {
+                    Some(etag) => WriteCondition { if_match: Some(etag), if_none_match: None },
+                    None => return Ok(Err(holder)),
+                }
+            }
+        };
+    }
+    Ok(Err(holder))
+}
+
+// 续期：以上次写入的 ETag 为前提条件延长有效期，锁已被其他设备接管时停止
+async fn renew_loop(backend: Arc<dyn StorageBackend>, held: Arc<Mutex<Option<HeldLease>>>, ttl: u64) {
+    let interval = Duration::from_millis(ttl / RENEWALS_PER_TTL);
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let Some((mut lease, etag)) = held.lock().ok().and_then(|h| h.as_ref().map(|h| (h.lease.clone(), h.etag.clone())))
+        else {
+            return;
+        };
+        // 不知道锁对象当前的 ETag 时无法条件写入，放弃租约而不是无条件覆盖
+        let Some(etag) = etag else {
+            println!("⚠️ 同步锁缺少 ETag，无法安全续期，已放弃: {}", lease.lease_id);
+            if let Ok(mut held) = held.lock() {
+                held.take();
+            }
+            return;
+        };
+        lease.expires_at = chrono::Utc::now().timestamp_millis() + ttl as i64;
+        let condition = WriteCondition { if_match: Some(etag), if_none_match: None };
+
+        match write_lock(backend.as_ref(), &lease, condition).await {
+            Ok(etag) => {
+                if let Ok(mut held) = held.lock() {
+                    if let Some(held) = held.as_mut() {
+                        held.lease = lease;
+                        held.etag = etag;
+                    }
+                }
+            }
+            Err(CosError::Conflict(_)) => {
+                println!("⚠️ 同步锁已被其他设备接管: {}", lease.lease_id);
+                if let Ok(mut held) = held.lock() {
+                    held.take();
+                }
+                return;
+            }
+            // 网络错误时保留租约，下次继续尝试
+            Err(e) => println!("⚠️ 同步锁续期失败: {}", e),
+        }
+    }
+}
+
+// 仍属于本机的锁对象才删除
+async fn release_lock(backend: &dyn StorageBackend, lease: &Lease) -> Result<(), CosError> {
+    if let Some((Some(current), _)) = read_lock(backend).await? {
+        if current.lease_id == lease.lease_id {
+            backend.delete(vec![LOCK_OBJECT_KEY.to_string()]).await?;
+            println!("🔓 已释放同步锁: {}", lease.lease_id);
+        }
+    }
+    Ok(())
+}
+
+async fn write_lock(backend: &dyn StorageBackend, lease: &Lease, condition: WriteCondition) -> Result<Option<String>, CosError> {
+    let temp_path = temp_path(&lease.lease_id);
+    write_temp(&temp_path, lease).await?;
+    let result = storage::put_conditional(backend, &temp_path.to_string_lossy(), LOCK_OBJECT_KEY, condition, None).await;
+    let _ = tokio::fs::remove_file(&temp_path).await;
+    Ok(result?.etag)
+}
+
+async fn write_temp(path: &Path, lease: &Lease) -> Result<(), CosError> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, serde_json::to_vec(lease)?).await?;
+    Ok(())
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join("imsheet_lease")
+        .join(format!("{}_{}.json", name, uuid::Uuid::new_v4().simple()))
+}
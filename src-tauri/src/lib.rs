@@ -1,89 +1,235 @@
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
-use tauri::{State, Window};
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager, RunEvent, State, Window};
 
 mod cos;
-use cos::{CosClient, CosConfig, UploadOptions, UploadResult, DownloadResult, DeleteResult, ObjectMetadata, ListObjectsResult};
+mod db;
+mod device;
+mod duplicates;
+mod error;
+mod lease;
+mod metadata;
+mod migration;
+mod oplog;
+mod progress;
+mod rebuild;
+mod reconcile;
+mod similar;
+mod storage;
+use duplicates::{DuplicateOptions, DuplicateReport, ExistingImage};
+use error::CosError;
+use lease::{Lease, LeaseStatus, SyncLeases};
+use metadata::ImageUploadResult;
+use migration::{MigrationOptions, MigrationReport};
+use oplog::{OplogKey, OplogSyncReport, Operation};
+use progress::ProgressTracker;
+use rebuild::{RebuildOptions, RebuildReport};
+use reconcile::{FixResult, ReconcileFix, ReconcileReport};
+use similar::{SimilarOptions, SimilarReport};
+use storage::{BackendContext, MirrorTarget, NamedBackend, StorageBackend, StorageConfig, UploadOptions, UploadResult, WriteCondition, DownloadResult, DeleteResult, ObjectMetadata, ListObjectsResult};
 
-// 全局 COS 客户端管理器
-type CosClients = Mutex<HashMap<String, CosClient>>;
+// 全局存储客户端管理器，按 client_id 保存已创建的存储后端
+type StorageClients = Mutex<HashMap<String, Arc<dyn StorageBackend>>>;
+// 按 client_id 保存由配置中的 SyncSecret 派生的操作记录签名密钥，密钥不再经命令传递
+type OplogKeys = Mutex<HashMap<String, Arc<OplogKey>>>;
 
-// 初始化 COS 客户端
+// 初始化存储客户端，返回的 client_id 用于后续所有存储命令
+// 配置只在初始化时传入一次，之后的命令复用同一个客户端及其连接池
 #[tauri::command]
-async fn cos_initialize(config: CosConfig, state: State<'_, CosClients>) -> Result<String, String> {
-    let client = CosClient::new(config.clone());
-    let client_id = format!("{}_{}", config.bucket, config.region);
+async fn cos_initialize(
+    app: AppHandle,
+    config: StorageConfig,
+    state: State<'_, StorageClients>,
+    oplog_keys: State<'_, OplogKeys>,
+) -> Result<String, CosError> {
+    // 同一服务商的不同配置（如密钥或目录变化）使用不同的客户端
+    let fingerprint = md5::compute(serde_json::to_vec(&config)?);
+    let client_id = format!("{}_{:x}", config.provider.as_str(), fingerprint);
+
+    if let Some(key) = OplogKey::from_settings(&config.settings) {
+        let mut keys = oplog_keys.lock().map_err(|e| CosError::invalid(format!("Failed to lock oplog keys: {}", e)))?;
+        keys.insert(client_id.clone(), Arc::new(key));
+    }
     
-    let mut clients = state.lock().map_err(|e| format!("Failed to lock clients: {}", e))?;
-    clients.insert(client_id.clone(), client);
+    let mut clients = state.lock().map_err(|e| CosError::invalid(format!("Failed to lock clients: {}", e)))?;
+    if !clients.contains_key(&client_id) {
+        let context = BackendContext { checkpoint_dir: upload_checkpoint_dir(&app) };
+        clients.insert(client_id.clone(), storage::build_backend(config, &context)?);
+    }
     
     Ok(client_id)
 }
 
-// 上传文件
+// 释放不再使用的存储客户端
+#[tauri::command]
+async fn cos_release(client_id: String, state: State<'_, StorageClients>, oplog_keys: State<'_, OplogKeys>) -> Result<(), CosError> {
+    if let Ok(mut keys) = oplog_keys.lock() {
+        keys.remove(&client_id);
+    }
+    let mut clients = state.lock().map_err(|e| CosError::invalid(format!("Failed to lock clients: {}", e)))?;
+    clients.remove(&client_id);
+    Ok(())
+}
+
+// 从客户端池中取出已初始化的客户端
+fn pooled_client(state: &State<'_, StorageClients>, client_id: &str) -> Result<Arc<dyn StorageBackend>, CosError> {
+    let clients = state.lock().map_err(|e| CosError::invalid(format!("Failed to lock clients: {}", e)))?;
+    clients.get(client_id)
+        .cloned()
+        .ok_or_else(|| CosError::invalid(format!("存储客户端未初始化: {}", client_id)))
+}
+
+// 取出客户端的操作记录签名密钥，配置未设置 SyncSecret 时返回错误，不从其他字段派生
+fn pooled_oplog_key(oplog_keys: &State<'_, OplogKeys>, client_id: &str) -> Result<Arc<OplogKey>, CosError> {
+    let keys = oplog_keys.lock().map_err(|e| CosError::invalid(format!("Failed to lock oplog keys: {}", e)))?;
+    keys.get(client_id)
+        .cloned()
+        .ok_or_else(|| CosError::invalid("存储配置未设置同步密钥 SyncSecret，无法签名和校验操作记录"))
+}
+
+// 取出镜像目标对应的客户端
+fn pooled_mirrors(
+    state: &State<'_, StorageClients>,
+    mirrors: Option<Vec<MirrorTarget>>,
+) -> Result<Vec<NamedBackend>, CosError> {
+    mirrors.unwrap_or_default()
+        .into_iter()
+        .map(|mirror| Ok((mirror.name, pooled_client(state, &mirror.client_id)?)))
+        .collect()
+}
+
+// 传输进度事件名，payload 为 TransferProgress
+const TRANSFER_PROGRESS_EVENT: &str = "cos-transfer-progress";
+
+// 为指定任务创建进度统计，进度通过事件推送给前端
+fn progress_tracker(app: &AppHandle, task_id: Option<String>) -> Option<ProgressTracker> {
+    task_id.map(|task_id| {
+        let app = app.clone();
+        ProgressTracker::new(task_id, Arc::new(move |progress| {
+            let _ = app.emit(TRANSFER_PROGRESS_EVENT, progress);
+        }))
+    })
+}
+
+// 分块上传断点记录目录，放在应用数据目录下以便重启后继续上传
+fn upload_checkpoint_dir(app: &AppHandle) -> PathBuf {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("uploads"))
+        .unwrap_or_else(|_| std::env::temp_dir().join("imsheet_uploads"))
+}
+
+// 上传图片，结果附带从原始文件读取的尺寸、类型和哈希
+// 图库中已有相同内容的图片时不上传，返回已有图片（options.force 为 true 时仍上传）
 #[tauri::command]
 async fn cos_upload(
+    app: AppHandle,
     file_path: String,
     key: String,
     options: Option<UploadOptions>,
-    config: CosConfig,
-    _state: State<'_, CosClients>,
-) -> Result<UploadResult, String> {
+    task_id: Option<String>,
+    client_id: String,
+    state: State<'_, StorageClients>,
+) -> Result<ImageUploadResult, CosError> {
+    let client = pooled_client(&state, &client_id)?;
+    let mirrors = pooled_mirrors(&state, options.as_ref().and_then(|opts| opts.mirrors.clone()))?;
+    
     // 添加调试日志
-    println!("COS Upload Debug:");
-    println!("  Bucket: {}", config.bucket);
-    println!("  Region: {}", config.region);
+    println!("Storage Upload Debug:");
+    println!("  Provider: {}", client.provider().as_str());
     println!("  Key: {}", key);
     println!("  File path: {}", file_path);
+    println!("  Mirrors: {}", mirrors.len());
     
-    let client = CosClient::new(config);
-    
-    client.upload_file(&file_path, &key, options)
-        .await
-        .map_err(|e| e.to_string())
+    let (metadata, existing) = inspect_upload(&app, client.as_ref(), &file_path, options.as_ref()).await?;
+    if let Some(existing) = existing {
+        println!("  图库中已有相同图片: {}", existing.image_path);
+        return Ok(ImageUploadResult::duplicate_of(existing, metadata));
+    }
+    let upload = storage::put_mirrored(&client, mirrors, &file_path, &key, options, progress_tracker(&app, task_id))
+        .await?;
+    Ok(ImageUploadResult::new(upload, metadata, &key))
+}
+
+// 读取待上传文件的图片信息（来源设备为本机），未强制上传时按内容哈希查找当前存储中已有的图片
+async fn inspect_upload(
+    app: &AppHandle,
+    client: &dyn StorageBackend,
+    file_path: &str,
+    options: Option<&UploadOptions>,
+) -> Result<(metadata::ImageMetadata, Option<ExistingImage>), CosError> {
+    let original_name = options.and_then(|opts| opts.original_name.clone());
+    let metadata = metadata::inspect(std::path::Path::new(file_path), original_name, device::device_id(app).await?).await?;
+    if options.is_some_and(|opts| opts.force) {
+        return Ok((metadata, None));
+    }
+
+    // 数据库尚未创建时没有可比较的记录；数据库存在但无法打开时返回错误，不静默跳过查重
+    if !tokio::fs::try_exists(db::database_path(app)?).await? {
+        return Ok((metadata, None));
+    }
+    let pool = db::open(app).await?;
+    let existing = duplicates::find_by_hash(&pool, client, &metadata.content_hash).await;
+    pool.close().await;
+    Ok((metadata, existing?))
+}
+
+// 条件上传：对象已被其他设备修改（If-Match）或已存在（If-None-Match: *）时返回 conflict 错误，不覆盖
+#[tauri::command]
+async fn cos_upload_conditional(
+    app: AppHandle,
+    file_path: String,
+    key: String,
+    condition: WriteCondition,
+    task_id: Option<String>,
+    client_id: String,
+    state: State<'_, StorageClients>,
+) -> Result<UploadResult, CosError> {
+    let client = pooled_client(&state, &client_id)?;
+    storage::put_conditional(client.as_ref(), &file_path, &key, condition, progress_tracker(&app, task_id)).await
 }
 
 // 下载文件
 #[tauri::command]
 async fn cos_download(
+    app: AppHandle,
     key: String,
     save_path: String,
-    config: CosConfig,
-    _state: State<'_, CosClients>,
-) -> Result<DownloadResult, String> {
-    let client = CosClient::new(config);
+    task_id: Option<String>,
+    client_id: String,
+    state: State<'_, StorageClients>,
+) -> Result<DownloadResult, CosError> {
+    let client = pooled_client(&state, &client_id)?;
     
-    client.download_file(&key, &save_path)
+    client.get_file(&key, &save_path, progress_tracker(&app, task_id))
         .await
-        .map_err(|e| e.to_string())
 }
 
 // 检查对象是否存在
 #[tauri::command]
 async fn cos_head_object(
     key: String,
-    config: CosConfig,
-    _state: State<'_, CosClients>,
-) -> Result<ObjectMetadata, String> {
-    let client = CosClient::new(config);
+    client_id: String,
+    state: State<'_, StorageClients>,
+) -> Result<ObjectMetadata, CosError> {
+    let client = pooled_client(&state, &client_id)?;
     
-    client.head_object(&key)
+    client.head(&key)
         .await
-        .map_err(|e| e.to_string())
 }
 
 // 批量删除对象
 #[tauri::command]
 async fn cos_delete_multiple(
     keys: Vec<String>,
-    config: CosConfig,
-    _state: State<'_, CosClients>,
-) -> Result<DeleteResult, String> {
-    let client = CosClient::new(config);
+    client_id: String,
+    state: State<'_, StorageClients>,
+) -> Result<DeleteResult, CosError> {
+    let client = pooled_client(&state, &client_id)?;
     
-    client.delete_multiple(keys)
+    client.delete(keys)
         .await
-        .map_err(|e| e.to_string())
 }
 
 // 列出对象
@@ -91,26 +237,243 @@ async fn cos_delete_multiple(
 async fn cos_list_objects(
     prefix: Option<String>,
     max_keys: Option<u32>,
-    config: CosConfig,
-    _state: State<'_, CosClients>,
-) -> Result<ListObjectsResult, String> {
-    let client = CosClient::new(config);
+    marker: Option<String>,
+    delimiter: Option<String>,
+    client_id: String,
+    state: State<'_, StorageClients>,
+) -> Result<ListObjectsResult, CosError> {
+    let client = pooled_client(&state, &client_id)?;
     
-    client.list_objects(prefix, max_keys)
+    client.list(prefix, max_keys, marker, delimiter)
         .await
-        .map_err(|e| e.to_string())
 }
 
 // 获取对象 URL
 #[tauri::command]
 async fn cos_get_object_url(
     key: String,
-    config: CosConfig,
-    _state: State<'_, CosClients>,
-) -> Result<String, String> {
-    let client = CosClient::new(config);
+    client_id: String,
+    state: State<'_, StorageClients>,
+) -> Result<String, CosError> {
+    let client = pooled_client(&state, &client_id)?;
     
-    Ok(client.get_object_url(&key))
+    Ok(client.object_url(&key))
+}
+
+// 执行数据库结构迁移，数据库版本高于应用支持的版本时返回 database 错误
+#[tauri::command]
+async fn db_migrate(app: AppHandle) -> Result<db::SchemaVersion, CosError> {
+    db::migrate_database(&app).await
+}
+
+// 检查下载的数据库文件能否被当前版本打开，返回其结构版本
+#[tauri::command]
+async fn db_check_schema(path: String) -> Result<i64, CosError> {
+    db::check_schema(std::path::Path::new(&path)).await
+}
+
+// 迁移进度事件名，payload 为 MigrationProgress
+const MIGRATION_PROGRESS_EVENT: &str = "library-migration-progress";
+
+// 将整个图库从源存储迁移到目标存储，改写数据库中的链接，返回迁移报告
+// 中断后使用相同参数重新执行即可继续
+#[tauri::command]
+async fn library_migrate(
+    app: AppHandle,
+    source_client_id: String,
+    target_client_id: String,
+    options: Option<MigrationOptions>,
+    task_id: Option<String>,
+    state: State<'_, StorageClients>,
+) -> Result<MigrationReport, CosError> {
+    let source = pooled_client(&state, &source_client_id)?;
+    let target = pooled_client(&state, &target_client_id)?;
+    let pool = db::open(&app).await?;
+
+    let emitter = app.clone();
+    let mut report = migration::migrate_library(
+        &pool,
+        source,
+        target,
+        options.unwrap_or_default(),
+        &std::env::temp_dir().join("imsheet_migration"),
+        move |progress| {
+            let _ = emitter.emit(MIGRATION_PROGRESS_EVENT, progress);
+        },
+        task_id,
+    ).await;
+    pool.close().await;
+
+    let report_dir = app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("migrations"))
+        .unwrap_or_else(|_| std::env::temp_dir().join("imsheet_migrations"));
+    if let Ok(report) = report.as_mut() {
+        migration::save_report(report, &report_dir).await?;
+    }
+    report
+}
+
+// 重建进度事件名，payload 为 RebuildProgress
+const REBUILD_PROGRESS_EVENT: &str = "library-rebuild-progress";
+
+// 数据库丢失或损坏时，按存储桶中的对象重建 imsheet 表和统计表
+#[tauri::command]
+async fn library_rebuild(
+    app: AppHandle,
+    client_id: String,
+    options: Option<RebuildOptions>,
+    task_id: Option<String>,
+    state: State<'_, StorageClients>,
+) -> Result<RebuildReport, CosError> {
+    let backend = pooled_client(&state, &client_id)?;
+    let pool = db::open_or_create(&app).await?;
+
+    let emitter = app.clone();
+    let report = rebuild::rebuild_library(
+        &pool,
+        backend,
+        options.unwrap_or_default(),
+        move |progress| {
+            let _ = emitter.emit(REBUILD_PROGRESS_EVENT, progress);
+        },
+        task_id,
+    ).await;
+    pool.close().await;
+    report
+}
+
+// 对比数据库与存储中的对象，报告孤立对象、悬空记录和大小不一致的记录
+#[tauri::command]
+async fn library_reconcile(app: AppHandle, client_id: String, state: State<'_, StorageClients>) -> Result<ReconcileReport, CosError> {
+    let backend = pooled_client(&state, &client_id)?;
+    let pool = db::open(&app).await?;
+    let report = reconcile::reconcile_library(&pool, backend.as_ref()).await;
+    pool.close().await;
+    report
+}
+
+// 执行对账报告中的修复
+#[tauri::command]
+async fn library_reconcile_fix(
+    app: AppHandle,
+    client_id: String,
+    fix: ReconcileFix,
+    state: State<'_, StorageClients>,
+) -> Result<FixResult, CosError> {
+    let backend = pooled_client(&state, &client_id)?;
+    let pool = db::open(&app).await?;
+    let result = reconcile::apply_fix(&pool, backend, fix).await;
+    pool.close().await;
+    result
+}
+
+// 查找内容完全相同的图片，hash_missing 为 true 时先下载未记录哈希的图片补写哈希
+#[tauri::command]
+async fn library_find_duplicates(
+    app: AppHandle,
+    client_id: String,
+    options: Option<DuplicateOptions>,
+    state: State<'_, StorageClients>,
+) -> Result<DuplicateReport, CosError> {
+    let backend = pooled_client(&state, &client_id)?;
+    let pool = db::open(&app).await?;
+    let report = duplicates::find_duplicates(&pool, backend, options.unwrap_or_default()).await;
+    pool.close().await;
+    report
+}
+
+// 按感知哈希的汉明距离查找相似图片（缩放、重新压缩的副本等），hash_missing 为 true 时先补写哈希
+#[tauri::command]
+async fn library_find_similar(
+    app: AppHandle,
+    client_id: String,
+    options: Option<SimilarOptions>,
+    state: State<'_, StorageClients>,
+) -> Result<SimilarReport, CosError> {
+    let backend = pooled_client(&state, &client_id)?;
+    let pool = db::open(&app).await?;
+    let report = similar::find_similar(&pool, backend, options.unwrap_or_default()).await;
+    pool.close().await;
+    report
+}
+
+// 操作记录的临时目录
+fn oplog_work_dir() -> PathBuf {
+    std::env::temp_dir().join("imsheet_oplog")
+}
+
+// 记录本机对图库的修改（已写入本地数据库），签名后追加到存储中的操作日志
+// 返回是否已上传，未上传的记录在下次 oplog_sync 时重传
+#[tauri::command]
+async fn oplog_record(
+    app: AppHandle,
+    client_id: String,
+    op: Operation,
+    state: State<'_, StorageClients>,
+    oplog_keys: State<'_, OplogKeys>,
+) -> Result<bool, CosError> {
+    let backend = pooled_client(&state, &client_id)?;
+    let key = pooled_oplog_key(&oplog_keys, &client_id)?;
+    let device_id = device::device_id(&app).await?;
+    let pool = db::open(&app).await?;
+    let result = oplog::record(&pool, backend.as_ref(), &key, &device_id, op, &oplog_work_dir()).await;
+    pool.close().await;
+    result
+}
+
+// 上传本机待上传的操作记录，并回放其他设备的新记录
+#[tauri::command]
+async fn oplog_sync(
+    app: AppHandle,
+    client_id: String,
+    state: State<'_, StorageClients>,
+    oplog_keys: State<'_, OplogKeys>,
+) -> Result<OplogSyncReport, CosError> {
+    let backend = pooled_client(&state, &client_id)?;
+    let key = pooled_oplog_key(&oplog_keys, &client_id)?;
+    let device_id = device::device_id(&app).await?;
+    let pool = db::open(&app).await?;
+    let report = oplog::sync(&pool, backend.as_ref(), &key, &device_id, &oplog_work_dir()).await;
+    pool.close().await;
+    report
+}
+
+// 获取同步锁（存储中的租约对象），其他设备持有有效租约时 acquired 为 false
+// 持有期间后台自动续期，调用 sync_lock_release 或退出应用时释放
+#[tauri::command]
+async fn sync_lock_acquire(
+    app: AppHandle,
+    client_id: String,
+    ttl_ms: Option<u64>,
+    state: State<'_, StorageClients>,
+    leases: State<'_, SyncLeases>,
+) -> Result<LeaseStatus, CosError> {
+    let backend = pooled_client(&state, &client_id)?;
+    let device_id = device::device_id(&app).await?;
+    leases.acquire(&client_id, backend, &device_id, ttl_ms).await
+}
+
+// 释放本机持有的同步锁
+#[tauri::command]
+async fn sync_lock_release(client_id: String, leases: State<'_, SyncLeases>) -> Result<(), CosError> {
+    leases.release(&client_id).await
+}
+
+// 读取当前的同步锁，没有设备持有或已过期时返回 None
+#[tauri::command]
+async fn sync_lock_status(client_id: String, state: State<'_, StorageClients>) -> Result<Option<Lease>, CosError> {
+    let backend = pooled_client(&state, &client_id)?;
+    Ok(lease::read_lock(backend.as_ref())
+        .await?
+        .and_then(|(lease, _)| lease)
+        .filter(|lease| lease.expires_at > chrono::Utc::now().timestamp_millis()))
+}
+
+// 探测链接是否可访问，返回与 urls 一一对应的结果
+#[tauri::command]
+async fn storage_probe_urls(urls: Vec<String>, timeout_ms: Option<u64>) -> Result<Vec<bool>, CosError> {
+    Ok(storage::probe_urls(urls, timeout_ms).await)
 }
 
 // 窗口控制命令
@@ -257,63 +620,76 @@ fn greet(name: &str) -> String {
 // 从base64数据上传文件
 #[tauri::command]
 async fn cos_upload_from_base64(
+    app: AppHandle,
     base64_data: String,
     key: String,
     options: Option<UploadOptions>,
-    config: CosConfig,
-    _state: State<'_, CosClients>,
-) -> Result<UploadResult, String> {
+    task_id: Option<String>,
+    client_id: String,
+    state: State<'_, StorageClients>,
+) -> Result<ImageUploadResult, CosError> {
     use base64::{Engine as _, engine::general_purpose};
     use std::fs;
     
-    println!("COS Upload from Base64 Debug:");
-    println!("  Bucket: {}", config.bucket);
-    println!("  Region: {}", config.region);
+    let client = pooled_client(&state, &client_id)?;
+    let mirrors = pooled_mirrors(&state, options.as_ref().and_then(|opts| opts.mirrors.clone()))?;
+    
+    println!("Storage Upload from Base64 Debug:");
+    println!("  Provider: {}", client.provider().as_str());
     println!("  Key: {}", key);
     println!("  Base64 data length: {}", base64_data.len());
     
     // 解码base64数据
     let file_data = general_purpose::STANDARD
         .decode(&base64_data)
-        .map_err(|e| format!("Base64解码失败: {}", e))?;
+        .map_err(|e| CosError::invalid(format!("Base64解码失败: {}", e)))?;
     
     // 创建临时文件
     let temp_dir = std::env::temp_dir();
     let temp_file_path = temp_dir.join(format!("tauri_upload_{}", key.replace('/', "_")));
     
     // 写入临时文件
-    fs::write(&temp_file_path, &file_data)
-        .map_err(|e| format!("写入临时文件失败: {}", e))?;
-    
-    let client = CosClient::new(config);
+    fs::write(&temp_file_path, &file_data)?;
     
-    // 使用现有的upload_file方法
-    let result = client.upload_file(
-        temp_file_path.to_str().unwrap(),
-        &key,
-        options
-    ).await;
+    // 使用现有的put_file方法
+    let result = match inspect_upload(&app, client.as_ref(), temp_file_path.to_str().unwrap(), options.as_ref()).await {
+        Ok((metadata, Some(existing))) => {
+            println!("  图库中已有相同图片: {}", existing.image_path);
+            Ok(ImageUploadResult::duplicate_of(existing, metadata))
+        }
+        Ok((metadata, None)) => storage::put_mirrored(
+            &client,
+            mirrors,
+            temp_file_path.to_str().unwrap(),
+            &key,
+            options,
+            progress_tracker(&app, task_id)
+        ).await.map(|upload| ImageUploadResult::new(upload, metadata, &key)),
+        Err(e) => Err(e),
+    };
     
     // 清理临时文件
     let _ = fs::remove_file(&temp_file_path);
     
-    result.map_err(|e| e.to_string())
+    result
 }
 
 // 处理拖拽上传（别名方法，实际使用cos_upload_from_base64）
 #[tauri::command]
 async fn handle_drag_upload(
+    app: AppHandle,
     file_data: String,
     file_name: String,
     options: Option<UploadOptions>,
-    config: CosConfig,
-    state: State<'_, CosClients>,
-) -> Result<UploadResult, String> {
+    task_id: Option<String>,
+    client_id: String,
+    state: State<'_, StorageClients>,
+) -> Result<ImageUploadResult, CosError> {
     println!("Handle Drag Upload Debug:");
     println!("  File name: {}", file_name);
     
     // 直接调用base64上传方法
-    cos_upload_from_base64(file_data, file_name, options, config, state).await
+    cos_upload_from_base64(app, file_data, file_name, options, task_id, client_id, state).await
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -323,16 +699,34 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_sql::Builder::default().build())
         .plugin(tauri_plugin_clipboard_manager::init())
-        .manage(CosClients::default())
+        .manage(StorageClients::default())
+        .manage(OplogKeys::default())
+        .manage(SyncLeases::default())
         .invoke_handler(tauri::generate_handler![
             greet,
             cos_initialize,
+            cos_release,
             cos_upload,
+            cos_upload_conditional,
             cos_download,
             cos_head_object,
             cos_delete_multiple,
             cos_list_objects,
             cos_get_object_url,
+            storage_probe_urls,
+            db_migrate,
+            db_check_schema,
+            library_migrate,
+            library_rebuild,
+            library_reconcile,
+            library_reconcile_fix,
+            library_find_duplicates,
+            library_find_similar,
+            oplog_record,
+            oplog_sync,
+            sync_lock_acquire,
+            sync_lock_release,
+            sync_lock_status,
             minimize_window,
             maximize_window,
             unmaximize_window,
@@ -351,6 +745,12 @@ pub fn run() {
             cos_upload_from_base64,
             handle_drag_upload
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // 退出前释放同步锁，其他设备无需等待租约过期
+            if let RunEvent::Exit = event {
+                tauri::async_runtime::block_on(app.state::<SyncLeases>().release_all());
+            }
+        });
 }
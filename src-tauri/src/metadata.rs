@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::cos::content_type_for_path;
+use crate::error::CosError;
+use crate::similar::{self, PerceptualHash};
+use crate::duplicates::ExistingImage;
+use crate::storage::{MirroredUploadResult, UploadResult};
+
+// 计算哈希时每次读取的字节数
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+// 上传前从原始文件读取的图片信息，随记录写入 imsheet 表
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    // 无法识别的格式为 None
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub mime_type: Option<String>,
+    // 原始文件内容的 SHA-256（小写十六进制）
+    pub content_hash: String,
+    // 感知哈希（16 位十六进制），无法解码的图片为 None
+    pub dhash: Option<String>,
+    pub phash: Option<String>,
+    pub original_name: Option<String>,
+    pub source_device: String,
+}
+
+// 上传命令的结果：上传结果附带图片信息
+// 图库中已有相同内容的图片时不上传，duplicate 为该图片，key 和 url 指向已有对象
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageUploadResult {
+    #[serde(flatten)]
+    pub upload: MirroredUploadResult,
+    pub metadata: ImageMetadata,
+    pub duplicate: Option<ExistingImage>,
+}
+
+impl ImageUploadResult {
+    // 服务端转换了格式（如 WebP 压缩后 key 的扩展名改变）时，类型按实际保存的对象记录
+    pub fn new(upload: MirroredUploadResult, mut metadata: ImageMetadata, requested_key: &str) -> Self {
+        if upload.upload.key != requested_key {
+            let stored_type = content_type_for_path(&upload.upload.key.to_ascii_lowercase());
+            if stored_type.starts_with("image/") {
+                metadata.mime_type = Some(stored_type);
+            }
+        }
+        Self { upload, metadata, duplicate: None }
+    }
+
+    pub fn duplicate_of(existing: ExistingImage, metadata: ImageMetadata) -> Self {
+        let upload = UploadResult {
+            success: true,
+            key: existing.image_path.clone(),
+            url: existing.image_location.clone(),
+            etag: None,
+            size: existing.image_size as u64,
+        };
+        Self {
+            upload: MirroredUploadResult { upload, mirrors: Vec::new() },
+            metadata,
+            duplicate: Some(existing),
+        }
+    }
+}
+
+// 读取文件的哈希、尺寸和类型；类型优先按文件内容识别，识别失败时按文件名判断
+pub async fn inspect(path: &Path, original_name: Option<String>, source_device: String) -> Result<ImageMetadata, CosError> {
+    let owned_path = path.to_path_buf();
+    let (content_hash, dimensions, mime_type, perceptual) = tokio::task::spawn_blocking(move || read_image(&owned_path))
+        .await
+        .map_err(|e| CosError::invalid(format!("读取图片信息异常退出: {}", e)))??;
+
+    let mime_type = mime_type.or_else(|| {
+        let name = original_name.as_deref().unwrap_or(&path.to_string_lossy()).to_ascii_lowercase();
+        Some(content_type_for_path(&name)).filter(|t| t.starts_with("image/"))
+    });
+
+    Ok(ImageMetadata {
+        width: dimensions.map(|(width, _)| width),
+        height: dimensions.map(|(_, height)| height),
+        mime_type,
+        content_hash,
+        dhash: perceptual.map(|hash| format!("{:016x}", hash.dhash)),
+        phash: perceptual.map(|hash| format!("{:016x}", hash.phash)),
+        original_name,
+        source_device,
+    })
+}
+
+// 计算文件的 SHA-256
+pub fn hash_file(path: &Path) -> Result<String, CosError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+type ImageInfo = (String, Option<(u32, u32)>, Option<String>, Option<PerceptualHash>);
+
+// 尺寸只解析文件头；感知哈希需要解码像素，无法解码时为 None
+fn read_image(path: &Path) -> Result<ImageInfo, CosError> {
+    let content_hash = hash_file(path)?;
+    let reader = image::ImageReader::open(path)?.with_guessed_format()?;
+    let mime_type = reader.format().map(|format| format.to_mime_type().to_string());
+    let dimensions = reader.into_dimensions().ok();
+    let perceptual = similar::perceptual_hash(path);
+    Ok((content_hash, dimensions, mime_type, perceptual))
+}
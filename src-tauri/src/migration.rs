@@ -0,0 +1,330 @@
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::db::OPLOG_PREFIX;
+use crate::error::{CosError, ServiceError};
+use crate::storage::{self, StorageBackend};
+
+// 未指定时同时迁移的对象数
+const DEFAULT_CONCURRENCY: usize = 4;
+
+// 迁移选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MigrationOptions {
+    pub concurrency: Option<usize>,
+    // 只复制和校验对象，不改写数据库
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// 迁移进度（library-migration-progress 事件）
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationProgress {
+    pub task_id: Option<String>,
+    pub processed: usize,
+    pub total: usize,
+    pub image_path: String,
+}
+
+// 迁移失败的图片
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationFailure {
+    pub id: i64,
+    pub image_path: String,
+    pub error: CosError,
+}
+
+// 复制失败的操作日志
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OplogFailure {
+    pub key: String,
+    pub error: CosError,
+}
+
+// 迁移报告，同时写入 report_path 指向的 JSON 文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub total: usize,
+    pub migrated: usize,
+    // 上次中断前已完成迁移的图片
+    pub skipped: usize,
+    pub failed: Vec<MigrationFailure>,
+    // 复制到目标存储的操作日志数，目标中已存在的不计
+    pub oplog_copied: usize,
+    pub oplog_failed: Vec<OplogFailure>,
+    pub report_path: Option<String>,
+}
+
+struct ImageRow {
+    id: i64,
+    image_path: String,
+    image_location: String,
+    image_size: i64,
+}
+
+enum Outcome {
+    Migrated,
+    Skipped,
+}
+
+// 将 imsheet 表中的每张图片从 source 复制到 target，校验后改写 image_path 和 image_location，并复制操作日志
+// 数据库逐行改写，中断后重新执行时跳过已改写的行；已复制但未改写的对象校验通过后不再重复上传
+// 云端数据库不复制：源存储中的快照仍是旧链接，由前端切换配置后推送本地改写后的数据库
+// 同步锁是各存储自己的租约，不复制
+pub async fn migrate_library(
+    pool: &SqlitePool,
+    source: Arc<dyn StorageBackend>,
+    target: Arc<dyn StorageBackend>,
+    options: MigrationOptions,
+    work_dir: &Path,
+    on_progress: impl Fn(MigrationProgress) + Send + Sync,
+    task_id: Option<String>,
+) -> Result<MigrationReport, CosError> {
+    let rows: Vec<ImageRow> = sqlx::query_as::<_, (i64, String, String, i64)>(
+        "SELECT id, image_path, image_location, image_size FROM imsheet ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(id, image_path, image_location, image_size)| ImageRow { id, image_path, image_location, image_size })
+    .collect();
+
+    tokio::fs::create_dir_all(work_dir).await?;
+
+    let total = rows.len();
+    let processed = AtomicUsize::new(0);
+    let concurrency = options.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+    let dry_run = options.dry_run;
+
+    println!("图库迁移: {} -> {}，共 {} 张图片", source.provider().as_str(), target.provider().as_str(), total);
+
+    let results: Vec<(ImageRow, Result<Outcome, CosError>)> = stream::iter(rows)
+        .map(|row| {
+            let source = source.clone();
+            let target = target.clone();
+            let processed = &processed;
+            let on_progress = &on_progress;
+            let task_id = task_id.clone();
+            async move {
+                let result = migrate_one(pool, source.as_ref(), target.as_ref(), &row, work_dir, dry_run).await;
+                on_progress(MigrationProgress {
+                    task_id,
+                    processed: processed.fetch_add(1, Ordering::SeqCst) + 1,
+                    total,
+                    image_path: row.image_path.clone(),
+                });
+                (row, result)
+            }
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    let mut report = MigrationReport {
+        total,
+        migrated: 0,
+        skipped: 0,
+        failed: Vec::new(),
+        oplog_copied: 0,
+        oplog_failed: Vec::new(),
+        report_path: None,
+    };
+    for (row, result) in results {
+        match result {
+            Ok(Outcome::Migrated) => report.migrated += 1,
+            Ok(Outcome::Skipped) => report.skipped += 1,
+            Err(error) => {
+                println!("  ❌ 迁移失败: {} - {}", row.image_path, error);
+                report.failed.push(MigrationFailure { id: row.id, image_path: row.image_path, error });
+            }
+        }
+    }
+    report.failed.sort_by_key(|f| f.id);
+
+    if !dry_run {
+        (report.oplog_copied, report.oplog_failed) = copy_oplog(source.as_ref(), target.as_ref(), concurrency, work_dir).await?;
+    }
+
+    println!(
+        "图库迁移完成: 迁移 {}，跳过 {}，失败 {}，复制操作日志 {}，失败 {}",
+        report.migrated, report.skipped, report.failed.len(), report.oplog_copied, report.oplog_failed.len()
+    );
+    Ok(report)
+}
+
+// 复制 oplog/ 下的操作日志，目标中已有大小相同的对象时跳过；记录内容带签名，原样复制即可
+async fn copy_oplog(
+    source: &dyn StorageBackend,
+    target: &dyn StorageBackend,
+    concurrency: usize,
+    work_dir: &Path,
+) -> Result<(usize, Vec<OplogFailure>), CosError> {
+    let prefix = source.full_key("");
+    let objects = storage::list_all(source, Some(OPLOG_PREFIX)).await?;
+
+    let results: Vec<(String, Result<bool, CosError>)> = stream::iter(objects)
+        .map(|object| {
+            let key = object.key.strip_prefix(&prefix).unwrap_or(&object.key).to_string();
+            async move {
+                let result = async {
+                    let target_meta = target.head(&key).await?;
+                    if target_meta.exists && target_meta.size == Some(object.size) {
+                        return Ok(false);
+                    }
+                    let temp_path = work_dir.join(key.replace(['/', '\\'], "_"));
+                    let result = copy_object(source, target, &key, &key, &temp_path, object.size, Some(&object.etag)).await;
+                    let _ = tokio::fs::remove_file(&temp_path).await;
+                    result.map(|_| true)
+                }
+                .await;
+                (key, result)
+            }
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    let mut copied = 0;
+    let mut failed = Vec::new();
+    for (key, result) in results {
+        match result {
+            Ok(true) => copied += 1,
+            Ok(false) => {}
+            Err(error) => {
+                println!("  ❌ 复制操作日志失败: {} - {}", key, error);
+                failed.push(OplogFailure { key, error });
+            }
+        }
+    }
+    failed.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok((copied, failed))
+}
+
+// 将报告写入 dir 下的 JSON 文件
+pub async fn save_report(report: &mut MigrationReport, dir: &Path) -> Result<(), CosError> {
+    tokio::fs::create_dir_all(dir).await?;
+    let path = dir.join(format!("migration_{}.json", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+    report.report_path = Some(path.to_string_lossy().into_owned());
+    tokio::fs::write(&path, serde_json::to_vec_pretty(report)?).await?;
+    Ok(())
+}
+
+async fn migrate_one(
+    pool: &SqlitePool,
+    source: &dyn StorageBackend,
+    target: &dyn StorageBackend,
+    row: &ImageRow,
+    work_dir: &Path,
+    dry_run: bool,
+) -> Result<Outcome, CosError> {
+    // 旧数据的 image_path 可能带有源目录前缀，迁移后统一保存相对 key
+    let source_prefix = source.full_key("");
+    let key = row.image_path.strip_prefix(&source_prefix).unwrap_or(&row.image_path).to_string();
+    let location = target.object_url(&key);
+    if row.image_path == key && row.image_location == location {
+        return Ok(Outcome::Skipped);
+    }
+
+    let source_meta = source.head(&row.image_path).await?;
+    if !source_meta.exists {
+        return Err(verify_error(404, "NoSuchKey", format!("源存储中不存在: {}", source.full_key(&row.image_path))));
+    }
+    let expected_size = source_meta.size.unwrap_or(row.image_size as u64);
+
+    let target_meta = target.head(&key).await?;
+    let already_copied = target_meta.exists
+        && target_meta.size == Some(expected_size)
+        && etags_agree(source_meta.etag.as_deref(), target_meta.etag.as_deref());
+
+    if !already_copied {
+        let temp_path = work_dir.join(format!("{}_{}", row.id, key.replace(['/', '\\'], "_")));
+        let result = copy_object(source, target, &row.image_path, &key, &temp_path, expected_size, source_meta.etag.as_deref()).await;
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        result?;
+    }
+
+    if !dry_run {
+        let mut transaction = pool.begin().await?;
+        sqlx::query("UPDATE imsheet SET image_path = ?, image_location = ? WHERE id = ?")
+            .bind(&key)
+            .bind(&location)
+            .bind(row.id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("UPDATE imsheet_mirror SET image_path = ? WHERE image_path = ?")
+            .bind(&key)
+            .bind(&row.image_path)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+    }
+
+    Ok(Outcome::Migrated)
+}
+
+// 下载到本地临时文件后上传，并校验大小和 ETag
+async fn copy_object(
+    source: &dyn StorageBackend,
+    target: &dyn StorageBackend,
+    source_key: &str,
+    target_key: &str,
+    temp_path: &Path,
+    expected_size: u64,
+    source_etag: Option<&str>,
+) -> Result<(), CosError> {
+    let temp = temp_path.to_string_lossy();
+
+    let download = source.get_file(source_key, &temp, None).await?;
+    if download.size != expected_size {
+        return Err(verify_error(0, "SizeMismatch", format!(
+            "下载大小不一致: 期望 {} 字节，实际 {} 字节", expected_size, download.size
+        )));
+    }
+
+    let content_md5 = format!("{:x}", md5::compute(tokio::fs::read(temp_path).await?));
+    if source_etag.is_some_and(|etag| is_md5(etag) && !etag.eq_ignore_ascii_case(&content_md5)) {
+        return Err(verify_error(0, "ETagMismatch", format!(
+            "下载内容与源 ETag 不一致: {} != {}", content_md5, source_etag.unwrap_or_default()
+        )));
+    }
+
+    let upload = target.put_file(&temp, target_key, None, None).await?;
+    if upload.size != expected_size {
+        return Err(verify_error(0, "SizeMismatch", format!(
+            "上传大小不一致: 期望 {} 字节，实际 {} 字节", expected_size, upload.size
+        )));
+    }
+    if let Some(etag) = upload.etag.as_deref().filter(|etag| is_md5(etag)) {
+        if !etag.eq_ignore_ascii_case(&content_md5) {
+            return Err(verify_error(0, "ETagMismatch", format!("上传后 ETag 不一致: {} != {}", etag, content_md5)));
+        }
+    }
+
+    Ok(())
+}
+
+// 两端都是内容 MD5 形式的 ETag 时必须一致；分块上传或非对象存储的 ETag 无法比较，只校验大小
+fn etags_agree(source: Option<&str>, target: Option<&str>) -> bool {
+    match (source, target) {
+        (Some(source), Some(target)) if is_md5(source) && is_md5(target) => source.eq_ignore_ascii_case(target),
+        _ => true,
+    }
+}
+
+fn is_md5(etag: &str) -> bool {
+    etag.len() == 32 && etag.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn verify_error(status: u16, code: &str, message: String) -> CosError {
+    let error = ServiceError {
+        status,
+        code: code.to_string(),
+        message,
+        request_id: None,
+    };
+    if status == 404 { CosError::NotFound(error) } else { CosError::Conflict(error) }
+}
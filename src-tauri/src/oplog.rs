@@ -0,0 +1,590 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::db::{self, OPLOG_PREFIX};
+use crate::error::CosError;
+use crate::storage::{self, StorageBackend};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 新增图片时的记录内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageRecord {
+    pub image_name: String,
+    pub image_location: String,
+    pub image_path: String,
+    pub image_size: i64,
+    pub create_time: i64,
+    // 以下字段由较新版本写入，较早的记录中没有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_width: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_height: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_mime: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_device: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_dhash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_phash: Option<String>,
+}
+
+// 新增图片时写入的镜像链接
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorRecord {
+    pub target: String,
+    pub image_location: String,
+    pub etag: Option<String>,
+}
+
+// 对图库的一次修改；recycle 和 restore 的 time 为新的 create_time，与前端改写的值一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Operation {
+    Insert {
+        image: Box<ImageRecord>,
+        #[serde(default)]
+        mirrors: Vec<MirrorRecord>,
+    },
+    Recycle { image_path: String, time: i64 },
+    Restore { image_path: String, time: i64 },
+    Delete { image_path: String },
+}
+
+impl Operation {
+    fn image_path(&self) -> &str {
+        match self {
+            Operation::Insert { image, .. } => &image.image_path,
+            Operation::Recycle { image_path, .. }
+            | Operation::Restore { image_path, .. }
+            | Operation::Delete { image_path } => image_path,
+        }
+    }
+}
+
+// 参与签名的操作记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpRecord {
+    pub id: String,
+    pub device_id: String,
+    // 毫秒时间戳，合并时按 (timestamp, device_id, id) 排序
+    pub timestamp: i64,
+    pub op: Operation,
+}
+
+impl OpRecord {
+    fn order(&self) -> (i64, &str, &str) {
+        (self.timestamp, &self.device_id, &self.id)
+    }
+}
+
+// 存储中的相对 key，同一设备的记录按 key 排序即按时间排序
+fn object_key(device_id: &str, timestamp: i64, id: &str) -> String {
+    format!("{}{}/{:013}-{}.json", OPLOG_PREFIX, device_id, timestamp, id)
+}
+
+// 写入存储的签名记录：record 为 OpRecord 的 JSON 文本，按原始字节签名和校验，校验通过后才解析
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SignedOp {
+    record: String,
+    // HMAC-SHA256(record)，十六进制
+    signature: String,
+}
+
+// 已校验的远端记录，payload 为下载的原文，原样保存到本地
+struct VerifiedOp {
+    record: OpRecord,
+    payload: String,
+}
+
+// 存储配置中的同步密钥字段，各设备需一致
+const SYNC_SECRET_FIELD: &str = "SyncSecret";
+
+// 签名密钥，由各设备共用的同步密钥派生
+pub struct OplogKey([u8; 32]);
+
+impl OplogKey {
+    // 读取存储配置中的同步密钥，未设置时返回 None；不使用存储凭据代替，避免密钥可被推测
+    pub fn from_settings(settings: &serde_json::Value) -> Option<Self> {
+        settings
+            .get(SYNC_SECRET_FIELD)
+            .and_then(|value| value.as_str())
+            .filter(|secret| !secret.is_empty())
+            .map(Self::derive)
+    }
+
+    fn derive(secret: &str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"imsheet-oplog:");
+        hasher.update(secret.as_bytes());
+        Self(hasher.finalize().into())
+    }
+
+    fn sign(&self, record: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC 接受任意长度的密钥");
+        mac.update(record);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn verify(&self, signed: &SignedOp) -> bool {
+        let Ok(signature) = hex::decode(&signed.signature) else {
+            return false;
+        };
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC 接受任意长度的密钥");
+        mac.update(signed.record.as_bytes());
+        mac.verify_slice(&signature).is_ok()
+    }
+}
+
+// 被拒绝的远端记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedOp {
+    pub key: String,
+    pub reason: String,
+}
+
+// 同步结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OplogSyncReport {
+    // 本次上传的本机记录
+    pub uploaded: usize,
+    // 上传失败、仍待上传的本机记录
+    pub pending: usize,
+    // 回放到本地数据库的记录
+    pub applied: usize,
+    // 时间早于同一图片已回放的记录，只保存不回放
+    pub superseded: usize,
+    pub rejected: Vec<RejectedOp>,
+}
+
+// 记录本机已写入本地数据库的操作，并按顺序上传本机待上传的记录（包括本条）；上传失败的记录在下次同步时重传
+pub async fn record(
+    pool: &SqlitePool,
+    backend: &dyn StorageBackend,
+    key: &OplogKey,
+    device_id: &str,
+    op: Operation,
+    work_dir: &Path,
+) -> Result<bool, CosError> {
+    let record = OpRecord {
+        id: uuid::Uuid::new_v4().simple().to_string(),
+        device_id: device_id.to_string(),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        op,
+    };
+    let raw = serde_json::to_string(&record)?;
+    let signed = SignedOp { signature: key.sign(raw.as_bytes()), record: raw };
+    let payload = serde_json::to_string(&signed)?;
+
+    sqlx::query(
+        "INSERT INTO imsheet_oplog (id, device_id, timestamp, image_path, payload, uploaded) VALUES (?, ?, ?, ?, ?, 0)",
+    )
+    .bind(&record.id)
+    .bind(&record.device_id)
+    .bind(record.timestamp)
+    .bind(record.op.image_path())
+    .bind(&payload)
+    .execute(pool)
+    .await?;
+
+    let (_, pending) = upload_pending(pool, backend, device_id, work_dir).await?;
+    Ok(pending == 0)
+}
+
+// 上传本机待上传的记录，再读取其他设备（以及被云端快照覆盖前的本机）的新记录回放到本地数据库
+pub async fn sync(
+    pool: &SqlitePool,
+    backend: &dyn StorageBackend,
+    key: &OplogKey,
+    device_id: &str,
+    work_dir: &Path,
+) -> Result<OplogSyncReport, CosError> {
+    tokio::fs::create_dir_all(work_dir).await?;
+    let mut report = OplogSyncReport::default();
+
+    (report.uploaded, report.pending) = upload_pending(pool, backend, device_id, work_dir).await?;
+
+    let cursors: HashMap<String, String> = sqlx::query_as("SELECT device_id, last_key FROM imsheet_oplog_cursor")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    // 按设备筛选游标之后的记录
+    let prefix = backend.full_key(OPLOG_PREFIX);
+    let mut fresh: Vec<(String, String, String)> = Vec::new();
+    for object in storage::list_all(backend, Some(OPLOG_PREFIX)).await? {
+        let relative = object.key.strip_prefix(&prefix).unwrap_or(&object.key);
+        let Some((device, name)) = relative.split_once('/') else {
+            continue;
+        };
+        if !name.ends_with(".json") || cursors.get(device).is_some_and(|last| name <= last.as_str()) {
+            continue;
+        }
+        fresh.push((device.to_string(), name.to_string(), object.key.clone()));
+    }
+
+    fresh.sort();
+
+    // 下载失败的设备不再推进游标，下次同步时重新读取
+    let mut records = Vec::new();
+    let mut latest: HashMap<String, String> = HashMap::new();
+    let mut blocked: HashSet<String> = HashSet::new();
+    for (device, name, full_key) in fresh {
+        let id = name.trim_end_matches(".json").rsplit('-').next().unwrap_or_default().to_string();
+        let known: Option<i64> = sqlx::query_scalar("SELECT 1 FROM imsheet_oplog WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(pool)
+            .await?;
+
+        if known.is_none() {
+            match fetch(backend, key, &full_key, &device, &id, work_dir).await {
+                Ok(Ok(verified)) => records.push(verified),
+                Ok(Err(reason)) => {
+                    println!("  ❌ 拒绝操作记录 {}: {}", full_key, reason);
+                    report.rejected.push(RejectedOp { key: full_key, reason });
+                }
+                Err(e) => {
+                    println!("  ⚠️ 下载操作记录 {} 失败: {}", full_key, e);
+                    blocked.insert(device.clone());
+                }
+            }
+        }
+        if !blocked.contains(&device) {
+            latest.insert(device, name);
+        }
+    }
+
+    records.sort_by(|a, b| a.record.order().cmp(&b.record.order()));
+
+    let mut transaction = pool.begin().await?;
+    for verified in &records {
+        if apply(&mut transaction, &verified.record).await? {
+            report.applied += 1;
+        } else {
+            report.superseded += 1;
+        }
+        sqlx::query(
+            "INSERT OR IGNORE INTO imsheet_oplog (id, device_id, timestamp, image_path, payload, uploaded)
+             VALUES (?, ?, ?, ?, ?, 1)",
+        )
+        .bind(&verified.record.id)
+        .bind(&verified.record.device_id)
+        .bind(verified.record.timestamp)
+        .bind(verified.record.op.image_path())
+        .bind(&verified.payload)
+        .execute(&mut *transaction)
+        .await?;
+    }
+    for (device, last_key) in &latest {
+        sqlx::query(
+            "INSERT INTO imsheet_oplog_cursor (device_id, last_key) VALUES (?, ?)
+             ON CONFLICT(device_id) DO UPDATE SET last_key = excluded.last_key",
+        )
+        .bind(device)
+        .bind(last_key)
+        .execute(&mut *transaction)
+        .await?;
+    }
+    if !records.is_empty() {
+        db::recompute_statistics(&mut transaction).await?;
+    }
+    transaction.commit().await?;
+
+    println!(
+        "操作日志同步: 上传 {}，待上传 {}，回放 {}，已过时 {}，拒绝 {}",
+        report.uploaded, report.pending, report.applied, report.superseded, report.rejected.len()
+    );
+    Ok(report)
+}
+
+// 按 key 的顺序上传本机待上传的记录，遇到失败即停止，返回上传数和仍待上传数
+// 其他设备按 key 记录读取游标，较早的记录在较晚的记录之后出现会被游标跳过
+async fn upload_pending(
+    pool: &SqlitePool,
+    backend: &dyn StorageBackend,
+    device_id: &str,
+    work_dir: &Path,
+) -> Result<(usize, usize), CosError> {
+    let pending: Vec<(String, i64, String)> = sqlx::query_as(
+        "SELECT id, timestamp, payload FROM imsheet_oplog WHERE uploaded = 0 AND device_id = ? ORDER BY timestamp, id",
+    )
+    .bind(device_id)
+    .fetch_all(pool)
+    .await?;
+
+    let total = pending.len();
+    for (uploaded, (id, timestamp, payload)) in pending.into_iter().enumerate() {
+        let object_key = object_key(device_id, timestamp, &id);
+        if let Err(e) = upload(pool, &id, &object_key, &payload, backend, work_dir).await {
+            println!("  ⚠️ 操作记录 {} 上传失败，之后的 {} 条记录下次同步时一并重试: {}", id, total - uploaded - 1, e);
+            return Ok((uploaded, total - uploaded));
+        }
+    }
+    Ok((total, 0))
+}
+
+// 上传单条记录（payload 原样写入）
+async fn upload(
+    pool: &SqlitePool,
+    id: &str,
+    object_key: &str,
+    payload: &str,
+    backend: &dyn StorageBackend,
+    work_dir: &Path,
+) -> Result<(), CosError> {
+    tokio::fs::create_dir_all(work_dir).await?;
+    let temp_path = work_dir.join(format!("{}.json", id));
+    tokio::fs::write(&temp_path, payload).await?;
+
+    // key 含记录 id，不会被其他记录覆盖，重传时写入相同内容，不需要条件写入
+    let result = backend.put_file(&temp_path.to_string_lossy(), object_key, None, None).await;
+    let _ = tokio::fs::remove_file(&temp_path).await;
+    result?;
+
+    sqlx::query("UPDATE imsheet_oplog SET uploaded = 1 WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// 下载并校验远端记录；下载失败返回 Err，内容无效返回 Ok(Err(拒绝原因))
+// 先校验签名再解析 record，未通过校验的内容不会被解析
+async fn fetch(
+    backend: &dyn StorageBackend,
+    key: &OplogKey,
+    full_key: &str,
+    device: &str,
+    id: &str,
+    work_dir: &Path,
+) -> Result<Result<VerifiedOp, String>, CosError> {
+    let temp_path = work_dir.join(format!("remote_{}.json", id));
+    let result = backend.get_file(full_key, &temp_path.to_string_lossy(), None).await;
+    let content = match result {
+        Ok(_) => tokio::fs::read(&temp_path).await.map_err(CosError::from),
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_file(&temp_path).await;
+
+    let payload = match String::from_utf8(content?) {
+        Ok(payload) => payload,
+        Err(e) => return Ok(Err(format!("无法解析: {}", e))),
+    };
+    let signed: SignedOp = match serde_json::from_str(&payload) {
+        Ok(signed) => signed,
+        Err(e) => return Ok(Err(format!("无法解析: {}", e))),
+    };
+    if !key.verify(&signed) {
+        return Ok(Err("签名无效".to_string()));
+    }
+    let record: OpRecord = match serde_json::from_str(&signed.record) {
+        Ok(record) => record,
+        Err(e) => return Ok(Err(format!("无法解析记录内容: {}", e))),
+    };
+    if record.device_id != device || record.id != id {
+        return Ok(Err("记录内容与存储路径不一致".to_string()));
+    }
+    Ok(Ok(VerifiedOp { record, payload }))
+}
+
+// 回放一条记录：同一图片以 (timestamp, device_id, id) 最新的记录为准，较早的记录不回放
+async fn apply(connection: &mut sqlx::SqliteConnection, record: &OpRecord) -> Result<bool, CosError> {
+    let image_path = record.op.image_path();
+    let newest: Option<(i64, String, String)> = sqlx::query_as(
+        "SELECT timestamp, device_id, id FROM imsheet_oplog WHERE image_path = ?
+         ORDER BY timestamp DESC, device_id DESC, id DESC LIMIT 1",
+    )
+    .bind(image_path)
+    .fetch_optional(&mut *connection)
+    .await?;
+    if newest.is_some_and(|(timestamp, device_id, id)| (timestamp, device_id.as_str(), id.as_str()) > record.order()) {
+        return Ok(false);
+    }
+
+    match &record.op {
+        Operation::Insert { image, mirrors } => {
+            sqlx::query(
+                "INSERT INTO imsheet (image_name, image_location, image_path, image_size, image_state, create_time,
+                 image_width, image_height, image_mime, image_hash, original_name, source_device, image_dhash, image_phash)
+                 VALUES (?, ?, ?, ?, 1, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                 ON CONFLICT(image_path) DO UPDATE SET image_name = excluded.image_name,
+                 image_location = excluded.image_location, image_size = excluded.image_size,
+                 image_state = 1, create_time = excluded.create_time,
+                 image_width = COALESCE(excluded.image_width, image_width),
+                 image_height = COALESCE(excluded.image_height, image_height),
+                 image_mime = COALESCE(excluded.image_mime, image_mime),
+                 image_hash = COALESCE(excluded.image_hash, image_hash),
+                 original_name = COALESCE(excluded.original_name, original_name),
+                 source_device = COALESCE(excluded.source_device, source_device),
+                 image_dhash = COALESCE(excluded.image_dhash, image_dhash),
+                 image_phash = COALESCE(excluded.image_phash, image_phash)",
+            )
+            .bind(&image.image_name)
+            .bind(&image.image_location)
+            .bind(&image.image_path)
+            .bind(image.image_size)
+            .bind(image.create_time)
+            .bind(image.image_width)
+            .bind(image.image_height)
+            .bind(&image.image_mime)
+            .bind(&image.image_hash)
+            .bind(&image.original_name)
+            .bind(&image.source_device)
+            .bind(&image.image_dhash)
+            .bind(&image.image_phash)
+            .execute(&mut *connection)
+            .await?;
+            for mirror in mirrors {
+                sqlx::query(
+                    "INSERT OR REPLACE INTO imsheet_mirror (image_path, target, image_location, etag, create_time)
+                     VALUES (?, ?, ?, ?, ?)",
+                )
+                .bind(&image.image_path)
+                .bind(&mirror.target)
+                .bind(&mirror.image_location)
+                .bind(&mirror.etag)
+                .bind(image.create_time)
+                .execute(&mut *connection)
+                .await?;
+            }
+        }
+        Operation::Recycle { image_path, time } | Operation::Restore { image_path, time } => {
+            let state = if matches!(record.op, Operation::Recycle { .. }) { 0 } else { 1 };
+            sqlx::query("UPDATE imsheet SET image_state = ?, create_time = ? WHERE image_path = ?")
+                .bind(state)
+                .bind(time)
+                .bind(image_path)
+                .execute(&mut *connection)
+                .await?;
+        }
+        Operation::Delete { image_path } => {
+            sqlx::query("DELETE FROM imsheet WHERE image_path = ?")
+                .bind(image_path)
+                .execute(&mut *connection)
+                .await?;
+            sqlx::query("DELETE FROM imsheet_mirror WHERE image_path = ?")
+                .bind(image_path)
+                .execute(&mut *connection)
+                .await?;
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use std::str::FromStr;
+
+    const IMAGE_PATH: &str = "imsheet/a.png";
+
+    async fn memory_pool() -> SqlitePool {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:").unwrap();
+        let pool = SqlitePoolOptions::new().max_connections(1).connect_with(options).await.unwrap();
+        db::migrate(&pool).await.unwrap();
+        pool
+    }
+
+    fn record(id: &str, device_id: &str, timestamp: i64, op: Operation) -> OpRecord {
+        OpRecord { id: id.to_string(), device_id: device_id.to_string(), timestamp, op }
+    }
+
+    fn insert(name: &str) -> Operation {
+        Operation::Insert {
+            image: Box::new(ImageRecord {
+                image_name: name.to_string(),
+                image_location: format!("https://example.com/{}", IMAGE_PATH),
+                image_path: IMAGE_PATH.to_string(),
+                image_size: 1,
+                create_time: 1,
+                image_width: None,
+                image_height: None,
+                image_mime: None,
+                image_hash: None,
+                original_name: None,
+                source_device: None,
+                image_dhash: None,
+                image_phash: None,
+            }),
+            mirrors: Vec::new(),
+        }
+    }
+
+    fn recycle(time: i64) -> Operation {
+        Operation::Recycle { image_path: IMAGE_PATH.to_string(), time }
+    }
+
+    fn restore(time: i64) -> Operation {
+        Operation::Restore { image_path: IMAGE_PATH.to_string(), time }
+    }
+
+    // 与 merge 相同：回放后写入本地日志，作为之后比较的依据
+    async fn replay(pool: &SqlitePool, record: &OpRecord) -> bool {
+        let mut connection = pool.acquire().await.unwrap();
+        let applied = apply(&mut connection, record).await.unwrap();
+        sqlx::query(
+            "INSERT OR IGNORE INTO imsheet_oplog (id, device_id, timestamp, image_path, payload, uploaded)
+             VALUES (?, ?, ?, ?, '', 1)",
+        )
+        .bind(&record.id)
+        .bind(&record.device_id)
+        .bind(record.timestamp)
+        .bind(record.op.image_path())
+        .execute(&mut *connection)
+        .await
+        .unwrap();
+        applied
+    }
+
+    async fn image(pool: &SqlitePool) -> Option<(String, i64)> {
+        sqlx::query_as("SELECT image_name, image_state FROM imsheet WHERE image_path = ?")
+            .bind(IMAGE_PATH)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn apply_skips_records_older_than_newest() {
+        let pool = memory_pool().await;
+
+        assert!(replay(&pool, &record("1", "device-a", 100, insert("a.png"))).await);
+        assert!(replay(&pool, &record("2", "device-b", 300, recycle(300))).await);
+        // 较早的恢复记录晚到，不覆盖较新的回收
+        assert!(!replay(&pool, &record("3", "device-a", 200, restore(200))).await);
+
+        assert_eq!(image(&pool).await, Some(("a.png".to_string(), 0)));
+    }
+
+    #[tokio::test]
+    async fn apply_older_insert_does_not_resurrect_deleted_image() {
+        let pool = memory_pool().await;
+
+        assert!(replay(&pool, &record("2", "device-b", 200, Operation::Delete { image_path: IMAGE_PATH.to_string() })).await);
+        assert!(!replay(&pool, &record("1", "device-a", 100, insert("a.png"))).await);
+
+        assert_eq!(image(&pool).await, None);
+    }
+
+    #[tokio::test]
+    async fn apply_breaks_timestamp_ties_by_device_id() {
+        let pool = memory_pool().await;
+
+        assert!(replay(&pool, &record("1", "device-b", 100, insert("b.png"))).await);
+        assert!(!replay(&pool, &record("2", "device-a", 100, insert("a.png"))).await);
+        assert!(replay(&pool, &record("3", "device-c", 100, insert("c.png"))).await);
+
+        assert_eq!(image(&pool).await, Some(("c.png".to_string(), 1)));
+    }
+}
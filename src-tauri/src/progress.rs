@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// 进度事件最小发送间隔，避免频繁触发前端渲染
+const EMIT_INTERVAL: Duration = Duration::from_millis(100);
+
+// 传输进度（通过 Tauri 事件发送给前端）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferProgress {
+    pub task_id: String,
+    pub transferred: u64,
+    pub total: u64,
+    // 最近一次统计区间内的速度，单位 bytes/s
+    pub speed: u64,
+}
+
+pub type ProgressSink = Arc<dyn Fn(TransferProgress) + Send + Sync>;
+
+struct TrackerState {
+    total: u64,
+    transferred: u64,
+    last_emit: Instant,
+    last_transferred: u64,
+}
+
+// 传输进度统计，可在并行分块之间共享
+#[derive(Clone)]
+pub struct ProgressTracker {
+    task_id: String,
+    sink: ProgressSink,
+    state: Arc<Mutex<TrackerState>>,
+}
+
+impl ProgressTracker {
+    pub fn new(task_id: String, sink: ProgressSink) -> Self {
+        let state = TrackerState {
+            total: 0,
+            transferred: 0,
+            last_emit: Instant::now(),
+            last_transferred: 0,
+        };
+        Self {
+            task_id,
+            sink,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn set_total(&self, total: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.total = total;
+        }
+    }
+
+    // 重新开始传输时清零已传输字节数
+    pub fn reset(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.transferred = 0;
+            state.last_transferred = 0;
+        }
+    }
+
+    // 记录新传输的字节数，按时间间隔节流发送事件
+    pub fn advance(&self, bytes: u64) {
+        let progress = match self.state.lock() {
+            Ok(mut state) => {
+                state.transferred += bytes;
+                if state.last_emit.elapsed() < EMIT_INTERVAL {
+                    return;
+                }
+                Some(self.snapshot(&mut state))
+            }
+            Err(_) => None,
+        };
+
+        if let Some(progress) = progress {
+            (self.sink)(progress);
+        }
+    }
+
+    // 传输结束时发送最终进度
+    pub fn finish(&self) {
+        let progress = match self.state.lock() {
+            Ok(mut state) => {
+                state.total = state.total.max(state.transferred);
+                Some(self.snapshot(&mut state))
+            }
+            Err(_) => None,
+        };
+
+        if let Some(progress) = progress {
+            (self.sink)(progress);
+        }
+    }
+
+    fn snapshot(&self, state: &mut TrackerState) -> TransferProgress {
+        let elapsed = state.last_emit.elapsed().as_secs_f64();
+        let delta = state.transferred.saturating_sub(state.last_transferred);
+        let speed = if elapsed > 0.0 { (delta as f64 / elapsed) as u64 } else { 0 };
+
+        state.last_emit = Instant::now();
+        state.last_transferred = state.transferred;
+
+        TransferProgress {
+            task_id: self.task_id.clone(),
+            transferred: state.transferred,
+            total: state.total,
+            speed,
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::cos::content_type_for_path;
+use crate::db::{self, IMAGE_STATE_MISSING};
+use crate::error::{CosError, ServiceError};
+use crate::storage::{self, ObjectInfo, StorageBackend};
+
+// 未指定时同时查询的对象数
+const DEFAULT_CONCURRENCY: usize = 8;
+
+// 重建选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RebuildOptions {
+    pub concurrency: Option<usize>,
+    // 只扫描存储桶并生成报告，不写入数据库
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// 重建进度（library-rebuild-progress 事件）
+#[derive(Debug, Clone, Serialize)]
+pub struct RebuildProgress {
+    pub task_id: Option<String>,
+    pub processed: usize,
+    pub total: usize,
+    pub key: String,
+}
+
+// 查询失败的对象
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebuildFailure {
+    pub key: String,
+    pub error: CosError,
+}
+
+// 重建报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RebuildReport {
+    // 存储桶中列出的对象数
+    pub scanned: usize,
+    // 新增的记录
+    pub inserted: usize,
+    // 已有记录，更新了链接和大小
+    pub updated: usize,
+    // 数据库、操作日志、非图片等被忽略的对象
+    pub ignored: usize,
+    pub failed: Vec<RebuildFailure>,
+    // 重建后的统计值
+    pub size: i64,
+    pub quantity: i64,
+}
+
+// 从存储桶中的对象生成的图片记录
+pub struct ImportedImage {
+    pub image_name: String,
+    pub image_location: String,
+    pub image_path: String,
+    // 完整 key，旧数据的 image_path 可能以此保存
+    pub full_path: String,
+    pub image_size: i64,
+    pub image_mime: String,
+    pub create_time: i64,
+}
+
+// 列出配置目录下的全部对象，逐个查询大小、类型和修改时间，重建 imsheet 表和统计表
+// 已有记录保留状态（包括回收站）和创建时间，只更新链接和大小；存储桶中不存在的记录不会删除
+pub async fn rebuild_library(
+    pool: &SqlitePool,
+    backend: Arc<dyn StorageBackend>,
+    options: RebuildOptions,
+    on_progress: impl Fn(RebuildProgress) + Send + Sync,
+    task_id: Option<String>,
+) -> Result<RebuildReport, CosError> {
+    let objects = storage::list_all(backend.as_ref(), None).await?;
+    let total = objects.len();
+    let processed = AtomicUsize::new(0);
+    let concurrency = options.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+
+    println!("重建图库: {} 中共 {} 个对象", backend.full_key(""), total);
+
+    let results: Vec<(String, Result<Option<ImportedImage>, CosError>)> = stream::iter(objects)
+        .map(|object| {
+            let backend = backend.clone();
+            let processed = &processed;
+            let on_progress = &on_progress;
+            let task_id = task_id.clone();
+            async move {
+                let result = import_object(backend.as_ref(), &object).await;
+                on_progress(RebuildProgress {
+                    task_id,
+                    processed: processed.fetch_add(1, Ordering::SeqCst) + 1,
+                    total,
+                    key: object.key.clone(),
+                });
+                (object.key, result)
+            }
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    let mut report = RebuildReport { scanned: total, ..Default::default() };
+    let mut images = Vec::new();
+    for (key, result) in results {
+        match result {
+            Ok(Some(image)) => images.push(image),
+            Ok(None) => report.ignored += 1,
+            Err(error) => {
+                println!("  ❌ 查询对象失败: {} - {}", key, error);
+                report.failed.push(RebuildFailure { key, error });
+            }
+        }
+    }
+    report.failed.sort_by(|a, b| a.key.cmp(&b.key));
+    images.sort_by_key(|image| image.create_time);
+
+    let mut transaction = pool.begin().await?;
+    for image in &images {
+        if upsert_image(&mut transaction, image).await? {
+            report.inserted += 1;
+        } else {
+            report.updated += 1;
+        }
+    }
+    (report.size, report.quantity) = db::recompute_statistics(&mut transaction).await?;
+    if options.dry_run {
+        transaction.rollback().await?;
+    } else {
+        transaction.commit().await?;
+    }
+
+    println!(
+        "重建图库完成: 新增 {}，更新 {}，忽略 {}，失败 {}",
+        report.inserted, report.updated, report.ignored, report.failed.len()
+    );
+    Ok(report)
+}
+
+// 查询单个对象，不是图片时返回 None
+pub async fn import_object(backend: &dyn StorageBackend, object: &ObjectInfo) -> Result<Option<ImportedImage>, CosError> {
+    let prefix = backend.full_key("");
+    let key = object.key.strip_prefix(&prefix).unwrap_or(&object.key);
+    if db::is_library_metadata(key) {
+        return Ok(None);
+    }
+
+    let metadata = backend.head(key).await?;
+    if !metadata.exists {
+        return Err(CosError::NotFound(ServiceError {
+            status: 404,
+            code: "NoSuchKey".to_string(),
+            message: format!("对象已不存在: {}", object.key),
+            request_id: None,
+        }));
+    }
+
+    // 服务端未记录类型或记为通用二进制时按扩展名判断
+    let content_type = metadata
+        .content_type
+        .filter(|t| !t.is_empty() && t != "application/octet-stream")
+        .unwrap_or_else(|| content_type_for_path(&key.to_ascii_lowercase()));
+    if !content_type.starts_with("image/") {
+        return Ok(None);
+    }
+
+    let create_time = metadata
+        .last_modified
+        .as_deref()
+        .and_then(parse_time)
+        .or_else(|| parse_time(&object.last_modified))
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+
+    Ok(Some(ImportedImage {
+        image_name: key.rsplit('/').next().unwrap_or(key).to_string(),
+        image_location: backend.object_url(key),
+        image_path: key.to_string(),
+        full_path: object.key.clone(),
+        image_size: metadata.size.unwrap_or(object.size) as i64,
+        image_mime: content_type,
+        create_time,
+    }))
+}
+
+// 插入记录，image_path 已存在时更新链接和大小，已标记丢失的记录恢复为正常；返回是否为新增
+// 存储中只能得到类型，尺寸和哈希留空
+pub async fn upsert_image(connection: &mut sqlx::SqliteConnection, image: &ImportedImage) -> Result<bool, CosError> {
+    let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM imsheet WHERE image_path IN (?, ?) LIMIT 1")
+        .bind(&image.image_path)
+        .bind(&image.full_path)
+        .fetch_optional(&mut *connection)
+        .await?;
+
+    match existing {
+        Some(id) => {
+            sqlx::query(
+                "UPDATE imsheet SET image_location = ?, image_size = ?, image_mime = COALESCE(image_mime, ?),
+                 image_state = CASE WHEN image_state = ? THEN 1 ELSE image_state END WHERE id = ?",
+            )
+            .bind(&image.image_location)
+            .bind(image.image_size)
+            .bind(&image.image_mime)
+            .bind(IMAGE_STATE_MISSING)
+            .bind(id)
+            .execute(&mut *connection)
+            .await?;
+            Ok(false)
+        }
+        None => {
+            sqlx::query(
+                "INSERT INTO imsheet (image_name, image_location, image_path, image_size, image_state, create_time, image_mime)
+                 VALUES (?, ?, ?, ?, 1, ?, ?)",
+            )
+            .bind(&image.image_name)
+            .bind(&image.image_location)
+            .bind(&image.image_path)
+            .bind(image.image_size)
+            .bind(image.create_time)
+            .bind(&image.image_mime)
+            .execute(&mut *connection)
+            .await?;
+            Ok(true)
+        }
+    }
+}
+
+// 解析 HTTP 日期（RFC 2822）或列表中的 ISO 8601 时间，返回毫秒时间戳
+fn parse_time(value: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc2822(value)
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(value))
+        .ok()
+        .map(|t| t.timestamp_millis())
+}
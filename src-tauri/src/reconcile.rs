@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::db::{self, IMAGE_STATE_MISSING};
+use crate::error::CosError;
+use crate::rebuild;
+use crate::storage::{self, ObjectInfo, StorageBackend};
+
+// 存储中存在、数据库中没有记录的对象
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanObject {
+    // 相对 key
+    pub key: String,
+    pub size: u64,
+    pub last_modified: String,
+}
+
+// 数据库中有记录、存储中对象已不存在
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanglingRow {
+    pub id: i64,
+    pub image_path: String,
+    pub image_state: i64,
+}
+
+// 数据库记录的大小与存储中的对象不一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SizeMismatch {
+    pub id: i64,
+    pub image_path: String,
+    pub recorded_size: i64,
+    pub object_size: u64,
+}
+
+// 对账报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    pub object_count: usize,
+    pub row_count: usize,
+    pub orphans: Vec<OrphanObject>,
+    pub dangling: Vec<DanglingRow>,
+    pub size_mismatches: Vec<SizeMismatch>,
+}
+
+// 对账后执行的修复
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ReconcileFix {
+    // 为孤立对象补建记录
+    ImportOrphans { keys: Vec<String> },
+    // 从存储中删除孤立对象
+    DeleteOrphans { keys: Vec<String> },
+    // 将对象已丢失的记录标记为丢失
+    MarkDangling { ids: Vec<i64> },
+    // 按存储中的对象大小更正记录
+    UpdateSizes { ids: Vec<i64> },
+}
+
+// 修复失败的条目，target 为 key 或记录 id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixFailure {
+    pub target: String,
+    pub error: CosError,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FixResult {
+    pub applied: usize,
+    // 复查后已无需修复的条目（如孤立对象已有记录、对象已恢复）
+    pub skipped: usize,
+    pub failed: Vec<FixFailure>,
+}
+
+struct ImageRow {
+    id: i64,
+    image_path: String,
+    image_size: i64,
+    image_state: i64,
+}
+
+// 对比 imsheet 表与存储中的对象列表，找出孤立对象、悬空记录和大小不一致的记录
+// 已标记丢失的记录不再报告为悬空记录
+pub async fn reconcile_library(pool: &SqlitePool, backend: &dyn StorageBackend) -> Result<ReconcileReport, CosError> {
+    let prefix = backend.full_key("");
+
+    let mut objects: HashMap<String, ObjectInfo> = storage::list_all(backend, None)
+        .await?
+        .into_iter()
+        .filter(|object| !db::is_library_metadata(object.key.strip_prefix(&prefix).unwrap_or(&object.key)))
+        .map(|object| (object.key.clone(), object))
+        .collect();
+    let object_count = objects.len();
+
+    let rows = load_rows(pool).await?;
+    let row_count = rows.len();
+
+    let mut dangling = Vec::new();
+    let mut size_mismatches = Vec::new();
+    for row in rows {
+        // 旧数据的 image_path 可能已带目录前缀，full_key 不会重复添加
+        match objects.remove(&backend.full_key(&row.image_path)) {
+            Some(object) if object.size as i64 != row.image_size => size_mismatches.push(SizeMismatch {
+                id: row.id,
+                image_path: row.image_path,
+                recorded_size: row.image_size,
+                object_size: object.size,
+            }),
+            Some(_) => {}
+            None if row.image_state != IMAGE_STATE_MISSING => dangling.push(DanglingRow {
+                id: row.id,
+                image_path: row.image_path,
+                image_state: row.image_state,
+            }),
+            None => {}
+        }
+    }
+
+    let mut orphans: Vec<OrphanObject> = objects
+        .into_values()
+        .map(|object| OrphanObject {
+            key: object.key.strip_prefix(&prefix).unwrap_or(&object.key).to_string(),
+            size: object.size,
+            last_modified: object.last_modified,
+        })
+        .collect();
+    orphans.sort_by(|a, b| a.key.cmp(&b.key));
+
+    println!(
+        "图库对账: 对象 {}，记录 {}，孤立对象 {}，悬空记录 {}，大小不一致 {}",
+        object_count, row_count, orphans.len(), dangling.len(), size_mismatches.len()
+    );
+
+    Ok(ReconcileReport {
+        object_count,
+        row_count,
+        orphans,
+        dangling,
+        size_mismatches,
+    })
+}
+
+// 执行修复；每个条目执行前重新查询存储，报告生成后发生的变化不会被误修
+// 改写数据库的修复完成后重新计算统计表
+pub async fn apply_fix(pool: &SqlitePool, backend: Arc<dyn StorageBackend>, fix: ReconcileFix) -> Result<FixResult, CosError> {
+    let mut result = FixResult::default();
+    let writes_database = !matches!(fix, ReconcileFix::DeleteOrphans { .. });
+
+    match fix {
+        ReconcileFix::ImportOrphans { keys } => {
+            for key in keys {
+                let object = ObjectInfo {
+                    key: backend.full_key(&key),
+                    size: 0,
+                    etag: String::new(),
+                    last_modified: String::new(),
+                };
+                let outcome = match rebuild::import_object(backend.as_ref(), &object).await {
+                    Ok(Some(image)) => {
+                        let mut connection = pool.acquire().await?;
+                        rebuild::upsert_image(&mut connection, &image).await.map(Some)
+                    }
+                    Ok(None) => Ok(None),
+                    Err(error) => Err(error),
+                };
+                match outcome {
+                    Ok(Some(true)) => result.applied += 1,
+                    Ok(_) => result.skipped += 1,
+                    Err(error) => result.failed.push(FixFailure { target: key, error }),
+                }
+            }
+        }
+        ReconcileFix::DeleteOrphans { keys } => {
+            for key in keys {
+                // 删除前确认仍没有记录引用该对象
+                let referenced: Option<i64> = sqlx::query_scalar("SELECT id FROM imsheet WHERE image_path IN (?, ?) LIMIT 1")
+                    .bind(&key)
+                    .bind(backend.full_key(&key))
+                    .fetch_optional(pool)
+                    .await?;
+                if referenced.is_some() {
+                    result.skipped += 1;
+                    continue;
+                }
+                match backend.delete(vec![key.clone()]).await {
+                    Ok(deleted) if deleted.success => result.applied += 1,
+                    Ok(deleted) => result.failed.push(FixFailure {
+                        target: key,
+                        error: CosError::invalid(
+                            deleted.failed_keys.first().map(|f| f.message.clone()).unwrap_or_default(),
+                        ),
+                    }),
+                    Err(error) => result.failed.push(FixFailure { target: key, error }),
+                }
+            }
+        }
+        ReconcileFix::MarkDangling { ids } => {
+            for row in load_rows_by_id(pool, &ids).await? {
+                match backend.head(&row.image_path).await {
+                    Ok(metadata) if metadata.exists => result.skipped += 1,
+                    Ok(_) => {
+                        sqlx::query("UPDATE imsheet SET image_state = ? WHERE id = ?")
+                            .bind(IMAGE_STATE_MISSING)
+                            .bind(row.id)
+                            .execute(pool)
+                            .await?;
+                        result.applied += 1;
+                    }
+                    Err(error) => result.failed.push(FixFailure { target: row.id.to_string(), error }),
+                }
+            }
+        }
+        ReconcileFix::UpdateSizes { ids } => {
+            for row in load_rows_by_id(pool, &ids).await? {
+                match backend.head(&row.image_path).await {
+                    Ok(metadata) => match metadata.size.filter(|_| metadata.exists) {
+                        Some(size) if size as i64 != row.image_size => {
+                            sqlx::query("UPDATE imsheet SET image_size = ? WHERE id = ?")
+                                .bind(size as i64)
+                                .bind(row.id)
+                                .execute(pool)
+                                .await?;
+                            result.applied += 1;
+                        }
+                        _ => result.skipped += 1,
+                    },
+                    Err(error) => result.failed.push(FixFailure { target: row.id.to_string(), error }),
+                }
+            }
+        }
+    }
+
+    if writes_database && result.applied > 0 {
+        let mut connection = pool.acquire().await?;
+        db::recompute_statistics(&mut connection).await?;
+    }
+
+    println!("对账修复: 完成 {}，跳过 {}，失败 {}", result.applied, result.skipped, result.failed.len());
+    Ok(result)
+}
+
+async fn load_rows(pool: &SqlitePool) -> Result<Vec<ImageRow>, CosError> {
+    Ok(sqlx::query_as::<_, (i64, String, i64, i64)>(
+        "SELECT id, image_path, image_size, image_state FROM imsheet ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(id, image_path, image_size, image_state)| ImageRow { id, image_path, image_size, image_state })
+    .collect())
+}
+
+async fn load_rows_by_id(pool: &SqlitePool, ids: &[i64]) -> Result<Vec<ImageRow>, CosError> {
+    let mut rows = load_rows(pool).await?;
+    rows.retain(|row| ids.contains(&row.id));
+    Ok(rows)
+}
@@ -0,0 +1,307 @@
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::db::IMAGE_STATE_MISSING;
+use crate::duplicates::{self, ExistingImage, HashFailure, HashedImageRow, IMAGE_COLUMNS};
+use crate::error::CosError;
+use crate::storage::StorageBackend;
+
+// pHash 缩放后的边长，取 DCT 左上角 PHASH_LOW × PHASH_LOW 的低频系数
+const PHASH_SIZE: usize = 32;
+const PHASH_LOW: usize = 8;
+// 未指定时判定为相似的最大汉明距离（64 位中不同的位数）
+const DEFAULT_THRESHOLD: u32 = 10;
+// 补写时无法解码的图片，感知哈希列记为空字符串，不再重复下载
+pub const UNDECODABLE_HASH: &str = "";
+
+// 图片的感知哈希
+#[derive(Debug, Clone, Copy)]
+pub struct PerceptualHash {
+    pub dhash: u64,
+    pub phash: u64,
+}
+
+// 比较使用的哈希
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    // 相邻像素的明暗差异，计算快，对裁剪和旋转敏感
+    Dhash,
+    // DCT 低频分量，对缩放、重新压缩更稳定
+    #[default]
+    Phash,
+}
+
+impl HashAlgorithm {
+    fn column(self) -> &'static str {
+        match self {
+            HashAlgorithm::Dhash => "image_dhash",
+            HashAlgorithm::Phash => "image_phash",
+        }
+    }
+}
+
+// 查找相似图片的选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimilarOptions {
+    #[serde(default)]
+    pub algorithm: HashAlgorithm,
+    // 最大汉明距离，0 ~ 64
+    pub threshold: Option<u32>,
+    // 下载未记录感知哈希的图片并补写
+    #[serde(default)]
+    pub hash_missing: bool,
+    pub concurrency: Option<usize>,
+}
+
+// 相似分组中的图片，distance 为与组内第一张图片的汉明距离
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarImage {
+    #[serde(flatten)]
+    pub image: ExistingImage,
+    pub distance: u32,
+}
+
+// 一组相似图片，按上传时间排序
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarGroup {
+    pub images: Vec<SimilarImage>,
+    pub total_size: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimilarReport {
+    pub groups: Vec<SimilarGroup>,
+    // 本次补写哈希的记录数
+    pub hashed: usize,
+    // 本次下载后无法解码、已标记不再补写的记录数
+    pub undecodable: usize,
+    // 仍没有感知哈希（未补写或无法解码）、未参与比较的记录数
+    pub unhashed: usize,
+    pub failed: Vec<HashFailure>,
+}
+
+// 解码图片计算 dHash 和 pHash，无法解码时返回 None
+pub fn perceptual_hash(path: &Path) -> Option<PerceptualHash> {
+    let image = image::ImageReader::open(path).ok()?.with_guessed_format().ok()?.decode().ok()?;
+    let gray = image.to_luma8();
+    Some(PerceptualHash {
+        dhash: dhash(&image::imageops::resize(&gray, 9, 8, FilterType::Triangle)),
+        phash: phash(&image::imageops::resize(&gray, PHASH_SIZE as u32, PHASH_SIZE as u32, FilterType::Triangle)),
+    })
+}
+
+// 9×8 灰度图中每行相邻像素比较，左侧较亮时该位为 1
+fn dhash(pixels: &image::GrayImage) -> u64 {
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if pixels.get_pixel(x, y)[0] > pixels.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+// 32×32 灰度图做二维 DCT-II，低频系数大于其中位数时该位为 1
+fn phash(pixels: &image::GrayImage) -> u64 {
+    let cosines: Vec<Vec<f64>> = (0..PHASH_LOW)
+        .map(|u| {
+            (0..PHASH_SIZE)
+                .map(|x| ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * PHASH_SIZE) as f64).cos())
+                .collect()
+        })
+        .collect();
+
+    // 先按行变换，再按列变换，只计算需要的低频部分
+    let mut rows = vec![[0f64; PHASH_LOW]; PHASH_SIZE];
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (u, value) in row.iter_mut().enumerate() {
+            *value = (0..PHASH_SIZE)
+                .map(|x| pixels.get_pixel(x as u32, y as u32)[0] as f64 * cosines[u][x])
+                .sum();
+        }
+    }
+    let mut coefficients = Vec::with_capacity(PHASH_LOW * PHASH_LOW);
+    for cosine in &cosines {
+        coefficients.extend((0..PHASH_LOW).map(|u| rows.iter().zip(cosine).map(|(row, c)| row[u] * c).sum::<f64>()));
+    }
+
+    let mut sorted = coefficients.clone();
+    sorted.sort_by(f64::total_cmp);
+    let median = (sorted[PHASH_LOW * PHASH_LOW / 2 - 1] + sorted[PHASH_LOW * PHASH_LOW / 2]) / 2.0;
+    coefficients.iter().fold(0u64, |hash, &value| (hash << 1) | u64::from(value > median))
+}
+
+// 按感知哈希的汉明距离对图片分组（包括回收站中的图片）
+// 距离在阈值内的图片连通成一组，组内两两之间的距离可能超过阈值
+pub async fn find_similar(
+    pool: &SqlitePool,
+    backend: Arc<dyn StorageBackend>,
+    options: SimilarOptions,
+) -> Result<SimilarReport, CosError> {
+    let column = options.algorithm.column();
+    let threshold = options.threshold.unwrap_or(DEFAULT_THRESHOLD).min(64);
+    let mut report = SimilarReport::default();
+    if options.hash_missing {
+        let missing = format!("{} IS NULL", column);
+        let backfill = duplicates::backfill_metadata(pool, backend, options.concurrency, &missing).await?;
+        report.hashed = backfill.updated - backfill.undecodable;
+        (report.undecodable, report.failed) = (backfill.undecodable, backfill.failed);
+    }
+
+    let rows: Vec<HashedImageRow> = sqlx::query_as(&format!(
+        "SELECT {}, {} FROM imsheet WHERE {} IS NOT NULL AND {} != ? AND image_state != ? ORDER BY create_time",
+        column, IMAGE_COLUMNS, column, column
+    ))
+    .bind(UNDECODABLE_HASH)
+    .bind(IMAGE_STATE_MISSING)
+    .fetch_all(pool)
+    .await?;
+
+    let mut hashes = Vec::with_capacity(rows.len());
+    let mut images = Vec::with_capacity(rows.len());
+    for (hash, id, image_name, image_location, image_path, image_size, image_state, create_time) in rows {
+        if let Ok(hash) = u64::from_str_radix(&hash, 16) {
+            hashes.push(hash);
+            images.push(ExistingImage::from((id, image_name, image_location, image_path, image_size, image_state, create_time)));
+        }
+    }
+
+    // 两两比较，距离在阈值内的合并到同一组
+    let mut parents: Vec<usize> = (0..hashes.len()).collect();
+    for i in 0..hashes.len() {
+        for j in (i + 1)..hashes.len() {
+            if (hashes[i] ^ hashes[j]).count_ones() <= threshold {
+                let (a, b) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                if a != b {
+                    parents[b.max(a)] = a.min(b);
+                }
+            }
+        }
+    }
+
+    // 行按上传时间排序，根节点为组内最早的图片，分组按其出现顺序排列
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); hashes.len()];
+    for index in 0..hashes.len() {
+        let root = find_root(&mut parents, index);
+        members[root].push(index);
+    }
+    for (root, indexes) in members.into_iter().enumerate() {
+        if indexes.len() < 2 {
+            continue;
+        }
+        let images: Vec<SimilarImage> = indexes
+            .into_iter()
+            .map(|index| SimilarImage {
+                image: images[index].clone(),
+                distance: (hashes[root] ^ hashes[index]).count_ones(),
+            })
+            .collect();
+        let total_size = images.iter().map(|similar| similar.image.image_size).sum();
+        report.groups.push(SimilarGroup { images, total_size });
+    }
+
+    report.unhashed = sqlx::query_scalar::<_, i64>(&format!(
+        "SELECT COUNT(*) FROM imsheet WHERE ({} IS NULL OR {} = ?) AND image_state != ?",
+        column, column
+    ))
+    .bind(UNDECODABLE_HASH)
+    .bind(IMAGE_STATE_MISSING)
+    .fetch_one(pool)
+    .await? as usize;
+
+    println!(
+        "查找相似图片: {} 组（{}，阈值 {}），补写哈希 {}，无法解码 {}，未比较 {}，失败 {}",
+        report.groups.len(), column, threshold, report.hashed, report.undecodable, report.unhashed, report.failed.len()
+    );
+    Ok(report)
+}
+
+fn find_root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    // 明暗分布不均匀的图案：对角渐变叠加几个不同位置的亮斑
+    fn pattern() -> GrayImage {
+        let spots = [(12.0, 20.0, 9.0), (44.0, 14.0, 6.0), (30.0, 48.0, 12.0)];
+        GrayImage::from_fn(64, 64, |x, y| {
+            let (x, y) = (x as f64, y as f64);
+            let light: f64 = spots
+                .iter()
+                .map(|(cx, cy, r)| 120.0 * (-((x - cx).powi(2) + (y - cy).powi(2)) / (2.0 * r * r)).exp())
+                .sum();
+            Luma([(40.0 + (x + y) * 0.6 + light).min(255.0) as u8])
+        })
+    }
+
+    // 与 perceptual_hash 相同的缩放和计算
+    fn hashes(gray: &GrayImage) -> PerceptualHash {
+        PerceptualHash {
+            dhash: dhash(&image::imageops::resize(gray, 9, 8, FilterType::Triangle)),
+            phash: phash(&image::imageops::resize(gray, PHASH_SIZE as u32, PHASH_SIZE as u32, FilterType::Triangle)),
+        }
+    }
+
+    fn distance(a: u64, b: u64) -> u32 {
+        (a ^ b).count_ones()
+    }
+
+    #[test]
+    fn hashes_are_deterministic() {
+        let first = hashes(&pattern());
+        let second = hashes(&pattern());
+
+        assert_eq!(first.dhash, second.dhash);
+        assert_eq!(first.phash, second.phash);
+        assert_ne!(first.dhash, 0);
+        assert_ne!(first.phash, 0);
+    }
+
+    #[test]
+    fn resized_copy_stays_within_threshold() {
+        let image = pattern();
+        let original = hashes(&image);
+
+        for size in [40, 256] {
+            let resized = hashes(&image::imageops::resize(&image, size, size, FilterType::Lanczos3));
+            assert!(distance(original.dhash, resized.dhash) <= DEFAULT_THRESHOLD);
+            assert!(distance(original.phash, resized.phash) <= DEFAULT_THRESHOLD);
+        }
+    }
+
+    #[test]
+    fn inverted_image_is_not_similar() {
+        let original = pattern();
+        let mut inverted = original.clone();
+        image::imageops::invert(&mut inverted);
+
+        let (original, inverted) = (hashes(&original), hashes(&inverted));
+        assert!(distance(original.dhash, inverted.dhash) > DEFAULT_THRESHOLD);
+        assert!(distance(original.phash, inverted.phash) > DEFAULT_THRESHOLD);
+    }
+
+    #[test]
+    fn undecodable_file_has_no_hash() {
+        let path = std::env::temp_dir().join(format!("imsheet_similar_test_{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"not an image").unwrap();
+
+        let hash = perceptual_hash(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(hash.is_none());
+    }
+}
@@ -0,0 +1,523 @@
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+use crate::cos::{CosClient, CosConfig, MultipartOptions};
+use crate::error::CosError;
+use crate::progress::ProgressTracker;
+
+mod git;
+mod local;
+mod mirror;
+mod oss;
+mod s3;
+mod sftp;
+mod webdav;
+pub use git::{GitPlatform, GitRepoClient, GitRepoConfig};
+pub use local::{LocalClient, LocalConfig};
+pub use mirror::{probe_urls, put_mirrored, MirrorTarget, MirroredUploadResult, NamedBackend};
+pub use oss::{OssClient, OssConfig};
+pub use s3::{S3Client, S3Config};
+pub use sftp::{SftpClient, SftpConfig};
+pub use webdav::{WebDavClient, WebDavConfig};
+
+// 未配置目录时使用的默认对象前缀
+pub const DEFAULT_DIR: &str = "ImSheet";
+// 未指定 max_keys 时单页返回的条目数
+const DEFAULT_MAX_KEYS: usize = 1000;
+
+// 存储服务商，前端配置中的 Provider 字段，缺省为腾讯云 COS
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    Cos,
+    // S3 兼容存储（AWS S3、MinIO 等）
+    S3,
+    // 阿里云 OSS
+    Oss,
+    // WebDAV（Nextcloud、nginx-dav 等自建服务）
+    WebDav,
+    // SFTP（自有 VPS + Web 服务器）
+    Sftp,
+    // 本地目录（离线使用、暂存与测试）
+    Local,
+    // GitHub 仓库（contents API）
+    GitHub,
+    // Gitea 仓库（contents API）
+    Gitea,
+}
+
+impl Provider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::Cos => "cos",
+            Provider::S3 => "s3",
+            Provider::Oss => "oss",
+            Provider::WebDav => "webdav",
+            Provider::Sftp => "sftp",
+            Provider::Local => "local",
+            Provider::GitHub => "github",
+            Provider::Gitea => "gitea",
+        }
+    }
+}
+
+// 存储配置：Provider 决定后端类型，其余字段由对应后端解析
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    #[serde(rename = "Provider", default)]
+    pub provider: Provider,
+    #[serde(flatten)]
+    pub settings: serde_json::Value,
+}
+
+// 创建后端时需要的本地环境
+#[derive(Debug, Clone)]
+pub struct BackendContext {
+    // 分块上传断点记录目录
+    pub checkpoint_dir: PathBuf,
+}
+
+// 上传选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadOptions {
+    pub callback: Option<String>,
+    pub headers: Option<CosHeaders>,
+    pub multipart: Option<MultipartOptions>,
+    // 同时上传的镜像目标，仅由上传命令使用，后端忽略该字段
+    #[serde(default)]
+    pub mirrors: Option<Vec<MirrorTarget>>,
+    // 条件写入，由 put_conditional 设置，仅 supports_conditional_put 的后端读取
+    #[serde(default)]
+    pub condition: Option<WriteCondition>,
+    // 用户选择的原始文件名，仅由上传命令记录到图片信息，后端忽略该字段
+    #[serde(default)]
+    pub original_name: Option<String>,
+    // 图库中已有相同内容的图片时仍然上传，仅由上传命令读取
+    #[serde(default)]
+    pub force: bool,
+}
+
+// 没有服务端条件写入的后端在对象旁创建的锁对象后缀（以 .part 结尾，列举时跳过）
+pub(crate) const WRITE_LOCK_SUFFIX: &str = ".lock.part";
+// 锁对象超过该时间仍存在时视为写入方崩溃遗留，可以删除
+pub(crate) const WRITE_LOCK_TTL_SECS: i64 = 60;
+
+// 条件写入：if_match 为期望的当前 ETag，if_none_match 为 "*" 时仅在对象不存在时写入
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WriteCondition {
+    pub if_match: Option<String>,
+    pub if_none_match: Option<String>,
+}
+
+impl WriteCondition {
+    // 条件请求头，ETag 带引号，"*" 原样发送
+    fn request_headers(&self) -> Vec<(&'static str, String)> {
+        let quote = |etag: &str| if etag == "*" { etag.to_string() } else { format!("\"{}\"", etag.trim_matches('"')) };
+        let mut headers = Vec::new();
+        if let Some(etag) = self.if_match.as_deref() {
+            headers.push(("if-match", quote(etag)));
+        }
+        if let Some(etag) = self.if_none_match.as_deref() {
+            headers.push(("if-none-match", quote(etag)));
+        }
+        headers
+    }
+
+    // 按 head 得到的元数据检查条件，由没有服务端条件写入的后端在持有锁时调用
+    fn check(&self, metadata: &ObjectMetadata) -> Result<(), CosError> {
+        let current = metadata.etag.as_deref().filter(|_| metadata.exists).map(|etag| etag.trim_matches('"'));
+        if let Some(expected) = self.if_match.as_deref() {
+            if current != Some(expected.trim_matches('"')) {
+                return Err(CosError::precondition_failed(format!(
+                    "对象已被修改: 期望 ETag {}，当前 {}", expected, current.unwrap_or("不存在")
+                )));
+            }
+        }
+        if let Some(none_match) = self.if_none_match.as_deref() {
+            if metadata.exists && (none_match == "*" || current == Some(none_match.trim_matches('"'))) {
+                return Err(CosError::precondition_failed("对象已存在"));
+            }
+        }
+        Ok(())
+    }
+}
+
+// 自定义头部选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CosHeaders {
+    pub content_type: Option<String>,
+    // COS 数据万象处理规则（Pic-Operations）
+    pub pic_operations: Option<String>,
+    // OSS 图片处理参数，如 image/format,webp/quality,q_80 或 style/<name>，处理结果覆盖原文件
+    pub image_process: Option<String>,
+}
+
+// 上传结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadResult {
+    pub success: bool,
+    pub key: String,
+    pub url: String,
+    pub etag: Option<String>,
+    pub size: u64,
+}
+
+// 下载结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadResult {
+    pub success: bool,
+    pub file_path: String,
+    pub size: u64,
+    // 下载内容对应的 ETag，与 head 返回的格式一致，用作下一次条件写入的前提
+    pub etag: Option<String>,
+}
+
+// 删除结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteResult {
+    pub success: bool,
+    pub deleted_count: usize,
+    pub failed_keys: Vec<DeleteError>,
+}
+
+// 单个对象删除失败的原因
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteError {
+    pub key: String,
+    pub code: String,
+    pub message: String,
+}
+
+// 对象元数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectMetadata {
+    pub exists: bool,
+    pub size: Option<u64>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_type: Option<String>,
+}
+
+// 对象列表结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListObjectsResult {
+    pub objects: Vec<ObjectInfo>,
+    pub common_prefixes: Vec<String>,
+    pub is_truncated: bool,
+    pub next_marker: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    pub etag: String,
+    pub last_modified: String,
+}
+
+// 存储后端：各服务商实现同一组对象操作，Tauri 命令只依赖该接口
+// key 均为相对 key，由后端按 storage::full_key 的规则补全目录前缀
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    fn provider(&self) -> Provider;
+
+    // 补全目录前缀后的完整对象键
+    fn full_key(&self, key: &str) -> String;
+
+    // 对象的访问链接
+    fn object_url(&self, key: &str) -> String;
+
+    // put_file 是否由服务端校验 UploadOptions.condition
+    fn supports_conditional_put(&self) -> bool {
+        false
+    }
+
+    async fn put_file(
+        &self,
+        file_path: &str,
+        key: &str,
+        options: Option<UploadOptions>,
+        progress: Option<ProgressTracker>,
+    ) -> Result<UploadResult, CosError>;
+
+    async fn get_file(
+        &self,
+        key: &str,
+        save_path: &str,
+        progress: Option<ProgressTracker>,
+    ) -> Result<DownloadResult, CosError>;
+
+    // 对象不存在时返回 exists: false，而不是错误
+    async fn head(&self, key: &str) -> Result<ObjectMetadata, CosError>;
+
+    async fn delete(&self, keys: Vec<String>) -> Result<DeleteResult, CosError>;
+
+    // prefix 为空时列出配置目录下的全部对象，marker 用于翻页
+    async fn list(
+        &self,
+        prefix: Option<String>,
+        max_keys: Option<u32>,
+        marker: Option<String>,
+        delimiter: Option<String>,
+    ) -> Result<ListObjectsResult, CosError>;
+}
+
+// 根据配置创建对应的存储后端
+pub fn build_backend(config: StorageConfig, context: &BackendContext) -> Result<Arc<dyn StorageBackend>, CosError> {
+    match config.provider {
+        Provider::Cos => {
+            let config: CosConfig = serde_json::from_value(config.settings)?;
+            Ok(Arc::new(CosClient::new(config).with_checkpoint_dir(context.checkpoint_dir.clone())))
+        }
+        Provider::S3 => {
+            let config: S3Config = serde_json::from_value(config.settings)?;
+            Ok(Arc::new(S3Client::new(config)?))
+        }
+        Provider::Oss => {
+            let config: OssConfig = serde_json::from_value(config.settings)?;
+            Ok(Arc::new(OssClient::new(config)))
+        }
+        Provider::WebDav => {
+            let config: WebDavConfig = serde_json::from_value(config.settings)?;
+            Ok(Arc::new(WebDavClient::new(config)?))
+        }
+        Provider::Sftp => {
+            let config: SftpConfig = serde_json::from_value(config.settings)?;
+            Ok(Arc::new(SftpClient::new(config)))
+        }
+        Provider::Local => {
+            let config: LocalConfig = serde_json::from_value(config.settings)?;
+            Ok(Arc::new(LocalClient::new(config)?))
+        }
+        Provider::GitHub => {
+            let config: GitRepoConfig = serde_json::from_value(config.settings)?;
+            Ok(Arc::new(GitRepoClient::new(config, GitPlatform::GitHub)?))
+        }
+        Provider::Gitea => {
+            let config: GitRepoConfig = serde_json::from_value(config.settings)?;
+            Ok(Arc::new(GitRepoClient::new(config, GitPlatform::Gitea)?))
+        }
+    }
+}
+
+// 条件上传：前置条件不满足时返回 CosError::Conflict，不覆盖存储中的对象
+// 不支持条件写入的后端直接返回错误，不退化为无锁的先比对再上传
+pub async fn put_conditional(
+    backend: &dyn StorageBackend,
+    file_path: &str,
+    key: &str,
+    condition: WriteCondition,
+    progress: Option<ProgressTracker>,
+) -> Result<UploadResult, CosError> {
+    if !backend.supports_conditional_put() {
+        return Err(CosError::invalid(format!(
+            "{} 存储不支持条件写入，无法安全地与其他设备同步",
+            backend.provider().as_str()
+        )));
+    }
+    let options = UploadOptions {
+        callback: None,
+        headers: None,
+        multipart: None,
+        mirrors: None,
+        condition: Some(condition),
+        original_name: None,
+        force: false,
+    };
+    backend.put_file(file_path, key, Some(options), progress).await
+}
+
+// 按目录拼接对象键，已带目录前缀的 key 原样返回
+pub fn full_key(dir: Option<&str>, key: &str) -> String {
+    let dir = dir.unwrap_or(DEFAULT_DIR);
+    let dir_prefix = if dir.ends_with('/') { dir.to_string() } else { format!("{}/", dir) };
+    if key.starts_with(&dir_prefix) {
+        return key.to_string();
+    }
+    format!("{}{}", dir_prefix, key)
+}
+
+// 响应头中的 ETag（去掉引号）
+pub(crate) fn response_etag(response: &reqwest::Response) -> Option<String> {
+    response.headers()
+        .get("etag")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.trim_matches('"').to_string())
+}
+
+// 将响应体流式写入本地文件，返回写入的字节数
+pub(crate) async fn save_response(
+    response: reqwest::Response,
+    save_path: &str,
+    progress: Option<&ProgressTracker>,
+) -> Result<u64, CosError> {
+    if let Some(tracker) = progress {
+        tracker.set_total(response.content_length().unwrap_or(0));
+    }
+
+    let mut file = tokio::fs::File::create(save_path).await?;
+    let mut stream = response.bytes_stream();
+    let mut size = 0u64;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        size += chunk.len() as u64;
+        if let Some(tracker) = progress {
+            tracker.advance(chunk.len() as u64);
+        }
+    }
+    file.flush().await?;
+
+    if let Some(tracker) = progress {
+        tracker.finish();
+    }
+
+    Ok(size)
+}
+
+// URI 编码：只保留 RFC 3986 非保留字符，encode_slash 为 false 时保留路径分隔符
+pub(crate) fn uri_encode(input: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// 按 marker 翻页列出 prefix（为空时为配置目录）下的全部对象（不含目录），返回的 key 为完整 key
+pub async fn list_all(backend: &dyn StorageBackend, prefix: Option<&str>) -> Result<Vec<ObjectInfo>, CosError> {
+    let mut objects = Vec::new();
+    let mut marker: Option<String> = None;
+    loop {
+        let page = backend.list(prefix.map(str::to_string), Some(1000), marker.clone(), None).await?;
+        objects.extend(page.objects.into_iter().filter(|o| !o.key.ends_with('/')));
+        match page.next_marker.filter(|m| page.is_truncated && Some(m) != marker.as_ref()) {
+            Some(next) => marker = Some(next),
+            None => break,
+        }
+    }
+    Ok(objects)
+}
+
+// 没有服务端分页的后端：对完整列表按 marker 和 max_keys 截取一页
+// entries 按键排序，值为 None 的键是目录，放入 common_prefixes
+pub(crate) fn paginate(
+    entries: BTreeMap<String, Option<ObjectInfo>>,
+    marker: Option<&str>,
+    max_keys: Option<u32>,
+) -> ListObjectsResult {
+    let limit = max_keys.map(|m| m as usize).unwrap_or(DEFAULT_MAX_KEYS);
+    let mut remaining = entries
+        .into_iter()
+        .filter(|(key, _)| marker.is_none_or(|m| m.is_empty() || key.as_str() > m))
+        .peekable();
+
+    let mut objects = Vec::new();
+    let mut common_prefixes = Vec::new();
+    let mut last_key = None;
+    for (key, object) in remaining.by_ref().take(limit) {
+        match object {
+            Some(object) => objects.push(object),
+            None => common_prefixes.push(key.clone()),
+        }
+        last_key = Some(key);
+    }
+
+    let is_truncated = remaining.peek().is_some();
+    ListObjectsResult {
+        objects,
+        common_prefixes,
+        is_truncated,
+        next_marker: if is_truncated { last_key } else { None },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(keys: &[&str]) -> BTreeMap<String, Option<ObjectInfo>> {
+        keys.iter()
+            .map(|key| {
+                let object = (!key.ends_with('/')).then(|| ObjectInfo {
+                    key: key.to_string(),
+                    size: 1,
+                    etag: String::new(),
+                    last_modified: String::new(),
+                });
+                (key.to_string(), object)
+            })
+            .collect()
+    }
+
+    fn object_keys(result: &ListObjectsResult) -> Vec<&str> {
+        result.objects.iter().map(|o| o.key.as_str()).collect()
+    }
+
+    #[test]
+    fn paginate_returns_everything_within_limit() {
+        let result = paginate(entries(&["a/", "b.png", "c.png"]), None, None);
+
+        assert_eq!(object_keys(&result), vec!["b.png", "c.png"]);
+        assert_eq!(result.common_prefixes, vec!["a/"]);
+        assert!(!result.is_truncated);
+        assert_eq!(result.next_marker, None);
+    }
+
+    #[test]
+    fn paginate_walks_pages_by_marker() {
+        let all = entries(&["a.png", "b/", "c.png", "d.png", "e.png"]);
+
+        let first = paginate(all.clone(), None, Some(2));
+        assert_eq!(object_keys(&first), vec!["a.png"]);
+        assert_eq!(first.common_prefixes, vec!["b/"]);
+        assert!(first.is_truncated);
+        assert_eq!(first.next_marker.as_deref(), Some("b/"));
+
+        let second = paginate(all.clone(), first.next_marker.as_deref(), Some(2));
+        assert_eq!(object_keys(&second), vec!["c.png", "d.png"]);
+        assert!(second.is_truncated);
+        assert_eq!(second.next_marker.as_deref(), Some("d.png"));
+
+        // 最后一页恰好取完时不再返回 next_marker
+        let last = paginate(all, second.next_marker.as_deref(), Some(1));
+        assert_eq!(object_keys(&last), vec!["e.png"]);
+        assert!(!last.is_truncated);
+        assert_eq!(last.next_marker, None);
+    }
+
+    #[test]
+    fn paginate_ignores_empty_marker() {
+        let result = paginate(entries(&["a.png", "b.png"]), Some(""), None);
+
+        assert_eq!(object_keys(&result), vec!["a.png", "b.png"]);
+    }
+
+    #[test]
+    fn paginate_marker_need_not_exist() {
+        let result = paginate(entries(&["a.png", "c.png", "e.png"]), Some("b.png"), Some(1));
+
+        assert_eq!(object_keys(&result), vec!["c.png"]);
+        assert_eq!(result.next_marker.as_deref(), Some("c.png"));
+    }
+
+    #[test]
+    fn paginate_past_end_is_empty() {
+        let result = paginate(entries(&["a.png", "b.png"]), Some("b.png"), Some(10));
+
+        assert!(result.objects.is_empty());
+        assert!(result.common_prefixes.is_empty());
+        assert!(!result.is_truncated);
+    }
+}
@@ -0,0 +1,597 @@
+use async_trait::async_trait;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+
+use super::{
+    uri_encode, DeleteError, DeleteResult, DownloadResult, ListObjectsResult, ObjectInfo, ObjectMetadata, Provider,
+    StorageBackend, UploadOptions, UploadResult,
+};
+use crate::cos::{content_type_for_path, send_with_retry, NetworkOptions};
+use crate::error::{request_id_of, CosError};
+use crate::progress::ProgressTracker;
+
+// 分支被其他提交推进时（409）重新读取 SHA 后重试的次数
+const MAX_CONFLICT_RETRIES: u32 = 3;
+// Gitea 目录树接口单页条目数
+const TREE_PAGE_SIZE: u32 = 1000;
+const USER_AGENT: &str = "ImSheet";
+
+// Git 仓库配置：通过 GitHub / Gitea 的 contents API 提交图片
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitRepoConfig {
+    // 服务器地址：GitHub 默认为 github.com，GitHub Enterprise 或 Gitea 填写站点根地址，如 http://localhost:3000
+    #[serde(rename = "ServerUrl")]
+    pub server_url: Option<String>,
+    #[serde(rename = "Owner")]
+    pub owner: String,
+    #[serde(rename = "Repo")]
+    pub repo: String,
+    #[serde(rename = "Branch", default = "default_branch")]
+    pub branch: String,
+    // 访问令牌，需要仓库内容的读写权限
+    #[serde(rename = "Token")]
+    pub token: String,
+    // 仓库内的存放目录，如 images；为空时直接放在仓库根目录
+    #[serde(rename = "Path")]
+    pub path: Option<String>,
+    // 链接模板，支持 {owner} {repo} {branch} {path} 占位符，
+    // 如 https://cdn.jsdelivr.net/gh/{owner}/{repo}@{branch}/{path}
+    #[serde(rename = "UrlTemplate")]
+    pub url_template: Option<String>,
+    #[serde(rename = "Dir")]
+    pub dir: Option<String>,
+    #[serde(rename = "Network", default)]
+    pub network: Option<NetworkOptions>,
+}
+
+fn default_branch() -> String {
+    "main".to_string()
+}
+
+// 代码托管平台，两者的 contents API 基本一致，创建文件与鉴权方式不同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitPlatform {
+    GitHub,
+    Gitea,
+}
+
+// 每次上传或删除都会在分支上产生一个提交，对象的 ETag 为文件的 blob SHA
+pub struct GitRepoClient {
+    config: GitRepoConfig,
+    platform: GitPlatform,
+    client: reqwest::Client,
+    network: NetworkOptions,
+    // 站点根地址，不含末尾斜杠
+    server_url: String,
+    // REST API 根地址
+    api_url: String,
+}
+
+// contents API 返回的文件信息
+#[derive(Debug, Deserialize)]
+struct ContentEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    sha: String,
+    #[serde(default)]
+    size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct WriteResponse {
+    content: Option<ContentEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TreeResponse {
+    #[serde(default)]
+    tree: Vec<TreeEntry>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+    sha: String,
+    #[serde(default)]
+    size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrorJson {
+    #[serde(default)]
+    message: String,
+}
+
+impl GitRepoClient {
+    pub fn new(config: GitRepoConfig, platform: GitPlatform) -> Result<Self, CosError> {
+        let server_url = config.server_url
+            .as_deref()
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty());
+
+        let (server_url, api_url) = match (platform, server_url) {
+            (GitPlatform::GitHub, None) => ("https://github.com".to_string(), "https://api.github.com".to_string()),
+            (GitPlatform::GitHub, Some(url)) if url == "https://github.com" => {
+                (url, "https://api.github.com".to_string())
+            }
+            (GitPlatform::GitHub, Some(url)) => {
+                let api_url = format!("{}/api/v3", url);
+                (url, api_url)
+            }
+            (GitPlatform::Gitea, Some(url)) => {
+                let api_url = format!("{}/api/v1", url);
+                (url, api_url)
+            }
+            (GitPlatform::Gitea, None) => return Err(CosError::invalid("Gitea 需要配置 ServerUrl")),
+        };
+
+        let network = config.network.clone().unwrap_or_default();
+        let client = network.build_client();
+
+        Ok(Self { config, platform, client, network, server_url, api_url })
+    }
+
+    fn get_full_key(&self, key: &str) -> String {
+        super::full_key(self.config.dir.as_deref(), key)
+    }
+
+    // 仓库内存放目录前缀，为空或以 / 结尾
+    fn path_prefix(&self) -> String {
+        match self.config.path.as_deref().map(|p| p.trim_matches('/')).filter(|p| !p.is_empty()) {
+            Some(path) => format!("{}/", path),
+            None => String::new(),
+        }
+    }
+
+    // 对象键在仓库中的文件路径
+    fn repo_path(&self, full_key: &str) -> String {
+        format!("{}{}", self.path_prefix(), full_key)
+    }
+
+    fn repo_url(&self, suffix: &str) -> String {
+        format!(
+            "{}/repos/{}/{}/{}",
+            self.api_url,
+            uri_encode(&self.config.owner, true),
+            uri_encode(&self.config.repo, true),
+            suffix
+        )
+    }
+
+    fn contents_url(&self, repo_path: &str) -> String {
+        self.repo_url(&format!("contents/{}", uri_encode(repo_path, false)))
+    }
+
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let authorization = match self.platform {
+            GitPlatform::GitHub => format!("Bearer {}", self.config.token),
+            GitPlatform::Gitea => format!("token {}", self.config.token),
+        };
+        self.client
+            .request(method, url)
+            .header("Authorization", authorization)
+            .header("User-Agent", USER_AGENT)
+            .header("Accept", "application/json")
+    }
+
+    // 文件当前的 blob SHA，不存在时返回 None
+    async fn file_entry(&self, repo_path: &str) -> Result<Option<ContentEntry>, CosError> {
+        let url = self.contents_url(repo_path);
+        let branch = self.config.branch.clone();
+
+        let response = send_with_retry(&self.network, "Git Get Contents", |_| {
+            Ok(self.request(reqwest::Method::GET, &url).query(&[("ref", &branch)]))
+        }).await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        // 路径为目录时返回数组，按不存在处理
+        let value: serde_json::Value = response.json().await?;
+        if !value.is_object() {
+            return Ok(None);
+        }
+        let entry: ContentEntry = serde_json::from_value(value)?;
+        Ok(Some(entry).filter(|e| e.kind == "file"))
+    }
+
+    // 创建或更新文件，sha 为 None 时表示新建
+    async fn write_file(&self, repo_path: &str, content: &str, sha: Option<&str>) -> Result<ContentEntry, CosError> {
+        let url = self.contents_url(repo_path);
+        let action = if sha.is_some() { "Update" } else { "Upload" };
+        let mut body = json!({
+            "message": format!("{} {}", action, repo_path),
+            "content": content,
+            "branch": self.config.branch,
+        });
+        if let Some(sha) = sha {
+            body["sha"] = json!(sha);
+        }
+
+        // GitHub 用 PUT 同时处理新建和更新；Gitea 新建用 POST，更新用 PUT
+        let method = match (self.platform, sha) {
+            (GitPlatform::Gitea, None) => reqwest::Method::POST,
+            _ => reqwest::Method::PUT,
+        };
+
+        let response = send_with_retry(&self.network, "Git Put Contents", |_| {
+            Ok(self.request(method.clone(), &url).json(&body))
+        }).await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let result: WriteResponse = response.json().await?;
+        result.content.ok_or_else(|| CosError::parse("提交响应缺少 content 字段"))
+    }
+
+    // 按 blob SHA 删除文件
+    async fn delete_file(&self, repo_path: &str, sha: &str) -> Result<(), CosError> {
+        let url = self.contents_url(repo_path);
+        let body = json!({
+            "message": format!("Delete {}", repo_path),
+            "sha": sha,
+            "branch": self.config.branch,
+        });
+
+        let response = send_with_retry(&self.network, "Git Delete Contents", |_| {
+            Ok(self.request(reqwest::Method::DELETE, &url).json(&body))
+        }).await?;
+
+        if response.status().is_success() || response.status() == reqwest::StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(api_error(response).await)
+        }
+    }
+
+    // 读取分支上的完整目录树（仅文件）
+    async fn tree(&self) -> Result<Vec<TreeEntry>, CosError> {
+        let mut entries = Vec::new();
+        let mut page = 1u32;
+
+        loop {
+            let query: Vec<(&str, String)> = match self.platform {
+                GitPlatform::GitHub => vec![("recursive", "1".to_string())],
+                GitPlatform::Gitea => vec![
+                    ("recursive", "true".to_string()),
+                    ("page", page.to_string()),
+                    ("per_page", TREE_PAGE_SIZE.to_string()),
+                ],
+            };
+
+            let Some(result) = self.fetch_tree(&self.config.branch, &query).await? else {
+                return Ok(entries);
+            };
+            match self.platform {
+                GitPlatform::Gitea if result.truncated => page += 1,
+                // GitHub 的递归目录树超过上限时被截断且不能翻页，改为逐层读取
+                GitPlatform::GitHub if result.truncated => return self.walk_tree().await,
+                _ => {}
+            }
+            entries.extend(result.tree.into_iter().filter(|e| e.kind == "blob"));
+            if !result.truncated {
+                return Ok(entries);
+            }
+        }
+    }
+
+    // 不使用 recursive，逐个读取子目录；单层目录仍被截断时返回错误，不返回不完整的列表
+    async fn walk_tree(&self) -> Result<Vec<TreeEntry>, CosError> {
+        let mut entries = Vec::new();
+        let mut pending = vec![(String::new(), self.config.branch.clone())];
+
+        while let Some((base, tree)) = pending.pop() {
+            let Some(result) = self.fetch_tree(&tree, &[]).await? else {
+                continue;
+            };
+            if result.truncated {
+                return Err(CosError::invalid(format!("目录 /{} 的条目过多，GitHub 返回的目录树不完整", base)));
+            }
+            for entry in result.tree {
+                let path = if base.is_empty() { entry.path } else { format!("{}/{}", base, entry.path) };
+                match entry.kind.as_str() {
+                    "tree" => pending.push((path, entry.sha)),
+                    "blob" => entries.push(TreeEntry { path, ..entry }),
+                    _ => {}
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    // 读取一个目录树对象（分支名或 SHA）；分支不存在或仓库为空（GitHub 返回 409）时为 None
+    async fn fetch_tree(&self, tree: &str, query: &[(&str, String)]) -> Result<Option<TreeResponse>, CosError> {
+        let url = self.repo_url(&format!("git/trees/{}", uri_encode(tree, true)));
+        let response = send_with_retry(&self.network, "Git Get Tree", |_| {
+            Ok(self.request(reqwest::Method::GET, &url).query(query))
+        }).await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::CONFLICT {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(api_error(response).await);
+        }
+        Ok(Some(response.json().await?))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for GitRepoClient {
+    fn provider(&self) -> Provider {
+        match self.platform {
+            GitPlatform::GitHub => Provider::GitHub,
+            GitPlatform::Gitea => Provider::Gitea,
+        }
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        self.get_full_key(key)
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        let repo_path = uri_encode(&self.repo_path(&self.get_full_key(key)), false);
+        let template = match self.config.url_template.as_deref().filter(|t| !t.is_empty()) {
+            Some(template) => template.to_string(),
+            None => match self.platform {
+                GitPlatform::GitHub if self.server_url == "https://github.com" => {
+                    "https://raw.githubusercontent.com/{owner}/{repo}/{branch}/{path}".to_string()
+                }
+                GitPlatform::GitHub => format!("{}/{{owner}}/{{repo}}/raw/{{branch}}/{{path}}", self.server_url),
+                GitPlatform::Gitea => format!("{}/{{owner}}/{{repo}}/raw/branch/{{branch}}/{{path}}", self.server_url),
+            },
+        };
+
+        template
+            .replace("{owner}", &self.config.owner)
+            .replace("{repo}", &self.config.repo)
+            .replace("{branch}", &self.config.branch)
+            .replace("{path}", &repo_path)
+    }
+
+    // contents API 以 SHA 作为提交前提
+    fn supports_conditional_put(&self) -> bool {
+        true
+    }
+
+    async fn put_file(
+        &self,
+        file_path: &str,
+        key: &str,
+        options: Option<UploadOptions>,
+        progress: Option<ProgressTracker>,
+    ) -> Result<UploadResult, CosError> {
+        let data = tokio::fs::read(file_path).await?;
+        let file_size = data.len() as u64;
+        if let Some(tracker) = &progress {
+            tracker.set_total(file_size);
+        }
+
+        let repo_path = self.repo_path(&self.get_full_key(key));
+        let content = base64::engine::general_purpose::STANDARD.encode(&data);
+
+        let entry = match options.and_then(|opts| opts.condition) {
+            // 条件写入：if_match 作为提交的 SHA，不提供 SHA 时只能新建
+            // 文件已被修改（409）或已存在（422）时服务端拒绝提交，不重新读取重试
+            Some(condition) => {
+                if condition.if_none_match.as_deref().is_some_and(|etag| etag != "*") {
+                    return Err(CosError::invalid("Git 仓库的 if_none_match 仅支持 \"*\""));
+                }
+                match self.write_file(&repo_path, &content, condition.if_match.as_deref()).await {
+                    Err(e) if matches!(e.service_error().map(|e| e.status), Some(409 | 422)) => {
+                        // 请求在服务端已提交、响应丢失后重试也会被拒绝，此时文件内容就是本次写入的内容
+                        match self.file_entry(&repo_path).await? {
+                            Some(entry) if entry.sha == blob_sha(&data) => entry,
+                            _ => return Err(CosError::precondition_failed(format!("文件已被修改或已存在: {}", e))),
+                        }
+                    }
+                    result => result?,
+                }
+            }
+            // 覆盖已有文件需要提供其 SHA；分支被并发提交推进时重新读取后重试
+            None => {
+                let mut attempt = 0;
+                loop {
+                    attempt += 1;
+                    let existing = self.file_entry(&repo_path).await?;
+                    match self.write_file(&repo_path, &content, existing.as_ref().map(|e| e.sha.as_str())).await {
+                        Err(CosError::Conflict(_)) if attempt < MAX_CONFLICT_RETRIES => {}
+                        result => break result?,
+                    }
+                }
+            }
+        };
+
+        if let Some(tracker) = &progress {
+            tracker.advance(file_size);
+            tracker.finish();
+        }
+
+        Ok(UploadResult {
+            success: true,
+            key: key.to_string(),
+            url: self.object_url(key),
+            etag: Some(entry.sha),
+            size: if entry.size > 0 { entry.size } else { file_size },
+        })
+    }
+
+    async fn get_file(
+        &self,
+        key: &str,
+        save_path: &str,
+        progress: Option<ProgressTracker>,
+    ) -> Result<DownloadResult, CosError> {
+        let repo_path = self.repo_path(&self.get_full_key(key));
+        let branch = self.config.branch.clone();
+
+        // GitHub 通过 raw 媒体类型直接返回文件内容，Gitea 使用单独的 raw 接口
+        let response = match self.platform {
+            GitPlatform::GitHub => {
+                let url = self.contents_url(&repo_path);
+                send_with_retry(&self.network, "Git Get Raw", |_| {
+                    Ok(self.request(reqwest::Method::GET, &url)
+                        .header("Accept", "application/vnd.github.raw")
+                        .query(&[("ref", &branch)]))
+                }).await?
+            }
+            GitPlatform::Gitea => {
+                let url = self.repo_url(&format!("raw/{}", uri_encode(&repo_path, false)));
+                send_with_retry(&self.network, "Git Get Raw", |_| {
+                    Ok(self.request(reqwest::Method::GET, &url).query(&[("ref", &branch)]))
+                }).await?
+            }
+        };
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let size = super::save_response(response, save_path, progress.as_ref()).await?;
+        // raw 接口的 ETag 不是 blob SHA，按下载的内容计算，与 head 返回的 SHA 一致
+        let data = tokio::fs::read(save_path).await?;
+
+        Ok(DownloadResult {
+            success: true,
+            file_path: save_path.to_string(),
+            size,
+            etag: Some(blob_sha(&data)),
+        })
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMetadata, CosError> {
+        let repo_path = self.repo_path(&self.get_full_key(key));
+
+        Ok(match self.file_entry(&repo_path).await? {
+            Some(entry) => ObjectMetadata {
+                exists: true,
+                size: Some(entry.size),
+                etag: Some(entry.sha),
+                last_modified: None,
+                content_type: Some(content_type_for_path(key)),
+            },
+            None => ObjectMetadata {
+                exists: false,
+                size: None,
+                etag: None,
+                last_modified: None,
+                content_type: None,
+            },
+        })
+    }
+
+    // contents API 每次只能删除一个文件，且每次删除都是一个提交，因此逐个顺序执行
+    async fn delete(&self, keys: Vec<String>) -> Result<DeleteResult, CosError> {
+        let mut failed_keys = Vec::new();
+
+        for key in &keys {
+            let full_key = self.get_full_key(key);
+            let repo_path = self.repo_path(&full_key);
+
+            let mut attempt = 0;
+            let result = loop {
+                attempt += 1;
+                let result = match self.file_entry(&repo_path).await {
+                    // 文件已不存在时视为删除成功
+                    Ok(None) => Ok(()),
+                    Ok(Some(entry)) => self.delete_file(&repo_path, &entry.sha).await,
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Err(CosError::Conflict(_)) if attempt < MAX_CONFLICT_RETRIES => continue,
+                    result => break result,
+                }
+            };
+
+            if let Err(e) = result {
+                failed_keys.push(DeleteError {
+                    key: full_key,
+                    code: e.code().unwrap_or("GitError").to_string(),
+                    message: e.to_string(),
+                });
+            }
+        }
+
+        Ok(DeleteResult {
+            success: failed_keys.is_empty(),
+            deleted_count: keys.len() - failed_keys.len(),
+            failed_keys,
+        })
+    }
+
+    // 仓库没有按前缀分页的接口：读取整个目录树后由 paginate 截取一页
+    async fn list(
+        &self,
+        prefix: Option<String>,
+        max_keys: Option<u32>,
+        marker: Option<String>,
+        delimiter: Option<String>,
+    ) -> Result<ListObjectsResult, CosError> {
+        let full_prefix = self.get_full_key(prefix.as_deref().unwrap_or(""));
+        let delimiter = delimiter.filter(|d| !d.is_empty());
+        let path_prefix = self.path_prefix();
+
+        let mut entries: BTreeMap<String, Option<ObjectInfo>> = BTreeMap::new();
+        for entry in self.tree().await? {
+            let Some(key) = entry.path.strip_prefix(&path_prefix) else {
+                continue;
+            };
+            let Some(rest) = key.strip_prefix(&full_prefix) else {
+                continue;
+            };
+
+            if let Some(index) = delimiter.as_deref().and_then(|d| rest.find(d).map(|i| i + d.len())) {
+                entries.insert(format!("{}{}", full_prefix, &rest[..index]), None);
+            } else {
+                entries.insert(key.to_string(), Some(ObjectInfo {
+                    key: key.to_string(),
+                    size: entry.size,
+                    etag: entry.sha,
+                    last_modified: String::new(),
+                }));
+            }
+        }
+
+        Ok(super::paginate(entries, marker.as_deref(), max_keys))
+    }
+}
+
+// GitHub / Gitea 的错误响应为 JSON，取 message 字段作为错误描述
+async fn api_error(response: reqwest::Response) -> CosError {
+    let status = response.status().as_u16();
+    let request_id = request_id_of(&response);
+
+    match response.text().await {
+        Ok(body) => {
+            let message = serde_json::from_str::<ApiErrorJson>(&body)
+                .ok()
+                .map(|e| e.message)
+                .filter(|m| !m.is_empty())
+                .unwrap_or(body);
+            CosError::from_status(status, &message, request_id)
+        }
+        Err(e) => e.into(),
+    }
+}
+
+// Git blob 对象的 SHA-1："blob <长度>\0" 加文件内容
+fn blob_sha(data: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", data.len()).as_bytes());
+    hasher.update(data);
+    hex::encode(hasher.finalize())
+}
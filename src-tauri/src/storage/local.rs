@@ -0,0 +1,498 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use super::{
+    DeleteError, DeleteResult, DownloadResult, ListObjectsResult, ObjectInfo, ObjectMetadata, Provider,
+    StorageBackend, UploadOptions, UploadResult, WRITE_LOCK_SUFFIX,
+};
+use crate::cos::content_type_for_path;
+use crate::error::{CosError, ServiceError};
+use crate::progress::ProgressTracker;
+
+// 复制文件的缓冲区大小
+const COPY_CHUNK_SIZE: usize = 256 * 1024;
+
+// 本地目录配置：对象按 get_full_key 的目录结构写入 Root 下
+// 用于离线环境、上传云端前暂存，以及不需要凭据的端到端测试
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalConfig {
+    // 存储根目录
+    #[serde(rename = "Root")]
+    pub root: String,
+    // 根目录对应的 HTTP 访问地址（如本地静态服务器），未设置时返回 file:// 链接
+    #[serde(rename = "UrlPrefix")]
+    pub url_prefix: Option<String>,
+    #[serde(rename = "Dir")]
+    pub dir: Option<String>,
+}
+
+pub struct LocalClient {
+    config: LocalConfig,
+    root: PathBuf,
+}
+
+impl LocalClient {
+    pub fn new(config: LocalConfig) -> Result<Self, CosError> {
+        if config.root.trim().is_empty() {
+            return Err(CosError::invalid("本地存储根目录不能为空"));
+        }
+        let root = PathBuf::from(&config.root);
+        Ok(Self { config, root })
+    }
+
+    fn get_full_key(&self, key: &str) -> String {
+        super::full_key(self.config.dir.as_deref(), key)
+    }
+
+    // 对象键对应的本地路径，拒绝跳出根目录的键
+    fn object_path(&self, full_key: &str) -> Result<PathBuf, CosError> {
+        let mut path = self.root.clone();
+        for segment in full_key.split('/').filter(|s| !s.is_empty()) {
+            if segment == "." || segment == ".." || segment.contains('\\') {
+                return Err(CosError::invalid(format!("非法的对象键: {}", full_key)));
+            }
+            path.push(segment);
+        }
+        Ok(path)
+    }
+
+    fn file_url(&self, path: &Path) -> String {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let path = if path.starts_with('/') { path } else { format!("/{}", path) };
+        // Windows 盘符（如 C:）保持原样，其余路径段逐段编码
+        let encoded: Vec<String> = path
+            .split('/')
+            .map(|segment| {
+                let is_drive = segment.len() == 2 && segment.ends_with(':')
+                    && segment.as_bytes()[0].is_ascii_alphabetic();
+                if is_drive { segment.to_string() } else { super::uri_encode(segment, true) }
+            })
+            .collect();
+        format!("file://{}", encoded.join("/"))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalClient {
+    fn provider(&self) -> Provider {
+        Provider::Local
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        self.get_full_key(key)
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        let full_key = self.get_full_key(key);
+        match self.config.url_prefix.as_deref().filter(|p| !p.is_empty()) {
+            Some(prefix) => format!("{}/{}", prefix.trim_end_matches('/'), full_key),
+            None => {
+                let path = self.object_path(&full_key).unwrap_or_else(|_| self.root.join(&full_key));
+                self.file_url(&std::path::absolute(&path).unwrap_or(path))
+            }
+        }
+    }
+
+    fn supports_conditional_put(&self) -> bool {
+        true
+    }
+
+    async fn put_file(
+        &self,
+        file_path: &str,
+        key: &str,
+        options: Option<UploadOptions>,
+        progress: Option<ProgressTracker>,
+    ) -> Result<UploadResult, CosError> {
+        let full_key = self.get_full_key(key);
+        let target = self.object_path(&full_key)?;
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut source = tokio::fs::File::open(file_path).await?;
+        let file_size = source.metadata().await?.len();
+        if let Some(tracker) = &progress {
+            tracker.set_total(file_size);
+        }
+
+        // 条件写入在对象的锁文件上持有排他锁，比对 ETag 与写入之间不会被其他条件写入插入
+        let condition = options.and_then(|opts| opts.condition);
+        let _lock = match &condition {
+            Some(condition) => {
+                let lock = lock_object(&target).await?;
+                condition.check(&self.head(key).await?)?;
+                Some(lock)
+            }
+            None => None,
+        };
+        let create_only = condition.as_ref().and_then(|c| c.if_none_match.as_deref()) == Some("*");
+
+        // 仅在不存在时创建：直接以 create_new 打开目标文件，已存在时失败
+        // 其他情况先写入临时文件再重命名，避免读取到写了一半的文件
+        let temp_path = target.with_file_name(format!(
+            "{}.part",
+            target.file_name().and_then(|n| n.to_str()).unwrap_or_default()
+        ));
+        let write_path = if create_only { &target } else { &temp_path };
+        let mut output = if create_only {
+            match tokio::fs::OpenOptions::new().write(true).create_new(true).open(&target).await {
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    return Err(CosError::precondition_failed("对象已存在"));
+                }
+                result => result?,
+            }
+        } else {
+            tokio::fs::File::create(&temp_path).await?
+        };
+        let copied = copy_to(&mut source, &mut output, progress.as_ref()).await;
+        drop(output);
+        if let Err(e) = copied {
+            let _ = tokio::fs::remove_file(write_path).await;
+            return Err(e);
+        }
+        if !create_only {
+            tokio::fs::rename(&temp_path, &target).await?;
+        }
+
+        if let Some(tracker) = &progress {
+            tracker.finish();
+        }
+
+        let metadata = tokio::fs::metadata(&target).await?;
+        Ok(UploadResult {
+            success: true,
+            key: key.to_string(),
+            url: self.object_url(key),
+            etag: Some(metadata_etag(&metadata)),
+            size: metadata.len(),
+        })
+    }
+
+    async fn get_file(
+        &self,
+        key: &str,
+        save_path: &str,
+        progress: Option<ProgressTracker>,
+    ) -> Result<DownloadResult, CosError> {
+        let full_key = self.get_full_key(key);
+        let source = self.object_path(&full_key)?;
+
+        match tokio::fs::metadata(&source).await {
+            Ok(metadata) if metadata.is_file() => {}
+            Ok(_) => return Err(not_found(&full_key)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(not_found(&full_key)),
+            Err(e) => return Err(e.into()),
+        }
+
+        // 从打开的文件读取内容和 ETag，读取期间对象被重命名覆盖也不会错配
+        let mut file = match tokio::fs::File::open(&source).await {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(not_found(&full_key)),
+            result => result?,
+        };
+        let metadata = file.metadata().await?;
+        if let Some(tracker) = &progress {
+            tracker.set_total(metadata.len());
+        }
+
+        let mut output = tokio::fs::File::create(save_path).await?;
+        let size = copy_to(&mut file, &mut output, progress.as_ref()).await?;
+        if let Some(tracker) = &progress {
+            tracker.finish();
+        }
+
+        Ok(DownloadResult {
+            success: true,
+            file_path: save_path.to_string(),
+            size,
+            etag: Some(metadata_etag(&metadata)),
+        })
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMetadata, CosError> {
+        let full_key = self.get_full_key(key);
+        let path = self.object_path(&full_key)?;
+
+        match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_file() => Ok(ObjectMetadata {
+                exists: true,
+                size: Some(metadata.len()),
+                etag: Some(metadata_etag(&metadata)),
+                last_modified: modified_time(&metadata)
+                    .map(|t| t.format("%a, %d %b %Y %H:%M:%S GMT").to_string()),
+                content_type: Some(content_type_for_path(key)),
+            }),
+            Ok(_) => Ok(missing_metadata()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(missing_metadata()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, keys: Vec<String>) -> Result<DeleteResult, CosError> {
+        let mut failed_keys = Vec::new();
+
+        for key in &keys {
+            let full_key = self.get_full_key(key);
+            let result = match self.object_path(&full_key) {
+                Ok(path) => match tokio::fs::remove_file(&path).await {
+                    // 文件已不存在时视为删除成功
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                    result => result.map_err(CosError::from),
+                },
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = result {
+                failed_keys.push(DeleteError {
+                    key: full_key,
+                    code: e.code().unwrap_or("LocalError").to_string(),
+                    message: e.to_string(),
+                });
+            }
+        }
+
+        Ok(DeleteResult {
+            success: failed_keys.is_empty(),
+            deleted_count: keys.len() - failed_keys.len(),
+            failed_keys,
+        })
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<String>,
+        max_keys: Option<u32>,
+        marker: Option<String>,
+        delimiter: Option<String>,
+    ) -> Result<ListObjectsResult, CosError> {
+        let full_prefix = self.get_full_key(prefix.as_deref().unwrap_or(""));
+        let recursive = delimiter.filter(|d| !d.is_empty()).is_none();
+        let start_dir = match full_prefix.rfind('/') {
+            Some(index) => full_prefix[..=index].to_string(),
+            None => String::new(),
+        };
+
+        let mut entries: BTreeMap<String, Option<ObjectInfo>> = BTreeMap::new();
+        let mut pending = VecDeque::from([start_dir]);
+
+        while let Some(dir) = pending.pop_front() {
+            let mut children = match tokio::fs::read_dir(self.object_path(&dir)?).await {
+                Ok(children) => children,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            while let Some(child) = children.next_entry().await? {
+                let Some(name) = child.file_name().to_str().map(|n| n.to_string()) else {
+                    continue;
+                };
+                // 跳过写入中的临时文件
+                if name.ends_with(".part") {
+                    continue;
+                }
+
+                let metadata = child.metadata().await?;
+                if metadata.is_dir() {
+                    let key = format!("{}{}/", dir, name);
+                    if recursive {
+                        if key.starts_with(&full_prefix) || full_prefix.starts_with(&key) {
+                            pending.push_back(key);
+                        }
+                    } else if key.starts_with(&full_prefix) {
+                        entries.insert(key, None);
+                    }
+                } else if metadata.is_file() {
+                    let key = format!("{}{}", dir, name);
+                    if key.starts_with(&full_prefix) {
+                        entries.insert(key.clone(), Some(ObjectInfo {
+                            key,
+                            size: metadata.len(),
+                            etag: metadata_etag(&metadata),
+                            last_modified: modified_time(&metadata).map(|t| t.to_rfc3339()).unwrap_or_default(),
+                        }));
+                    }
+                }
+            }
+        }
+
+        Ok(super::paginate(entries, marker.as_deref(), max_keys))
+    }
+}
+
+// 分块复制文件内容并更新进度，返回复制的字节数
+async fn copy_to(
+    source: &mut tokio::fs::File,
+    target: &mut tokio::fs::File,
+    progress: Option<&ProgressTracker>,
+) -> Result<u64, CosError> {
+    let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+    let mut size = 0u64;
+    loop {
+        let read = source.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        target.write_all(&buffer[..read]).await?;
+        size += read as u64;
+        if let Some(tracker) = progress {
+            tracker.advance(read as u64);
+        }
+    }
+    target.flush().await?;
+    Ok(size)
+}
+
+// 对象旁的锁文件（以 .part 结尾，列举时跳过）加排他锁，返回的文件关闭时释放
+// 锁文件保留在原处，删除会让等待中的写入锁住已被删除的文件
+async fn lock_object(target: &Path) -> Result<std::fs::File, CosError> {
+    let lock_path = target.with_file_name(format!(
+        "{}{}",
+        target.file_name().and_then(|n| n.to_str()).unwrap_or_default(),
+        WRITE_LOCK_SUFFIX
+    ));
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::OpenOptions::new().create(true).truncate(false).write(true).open(&lock_path)?;
+        file.lock()?;
+        Ok(file)
+    })
+    .await
+    .map_err(|e| CosError::invalid(format!("获取文件锁异常退出: {}", e)))?
+}
+
+fn modified_time(metadata: &std::fs::Metadata) -> Option<chrono::DateTime<chrono::Utc>> {
+    metadata.modified().ok().map(chrono::DateTime::<chrono::Utc>::from)
+}
+
+// 本地文件没有 ETag，用修改时间（纳秒）和大小生成，文件被覆盖后随之变化
+fn metadata_etag(metadata: &std::fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("{:x}-{:x}", modified, metadata.len())
+}
+
+fn missing_metadata() -> ObjectMetadata {
+    ObjectMetadata {
+        exists: false,
+        size: None,
+        etag: None,
+        last_modified: None,
+        content_type: None,
+    }
+}
+
+fn not_found(full_key: &str) -> CosError {
+    CosError::NotFound(ServiceError {
+        status: 404,
+        code: "NoSuchKey".to_string(),
+        message: format!("对象不存在: {}", full_key),
+        request_id: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{self, WriteCondition};
+
+    // 临时目录中的本地存储及待上传文件，结束时删除
+    struct Fixture {
+        dir: PathBuf,
+        client: LocalClient,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("imsheet_local_test_{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let client = LocalClient::new(LocalConfig {
+                root: dir.join("root").to_string_lossy().to_string(),
+                url_prefix: None,
+                dir: None,
+            })
+            .unwrap();
+            Fixture { dir, client }
+        }
+
+        fn source(&self, name: &str, content: &str) -> String {
+            let path = self.dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            path.to_string_lossy().to_string()
+        }
+
+        async fn read(&self, key: &str) -> String {
+            let save_path = self.dir.join("download");
+            self.client.get_file(key, &save_path.to_string_lossy(), None).await.unwrap();
+            std::fs::read_to_string(save_path).unwrap()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn create_only() -> WriteCondition {
+        WriteCondition { if_match: None, if_none_match: Some("*".to_string()) }
+    }
+
+    fn if_match(etag: &str) -> WriteCondition {
+        WriteCondition { if_match: Some(etag.to_string()), if_none_match: None }
+    }
+
+    fn is_conflict(result: Result<UploadResult, CosError>) -> bool {
+        matches!(result, Err(CosError::Conflict(_)))
+    }
+
+    #[tokio::test]
+    async fn create_only_rejects_existing_object() {
+        let fixture = Fixture::new();
+        let first = fixture.source("first", "first");
+        let second = fixture.source("second", "second");
+
+        storage::put_conditional(&fixture.client, &first, "a.json", create_only(), None).await.unwrap();
+        let result = storage::put_conditional(&fixture.client, &second, "a.json", create_only(), None).await;
+
+        assert!(is_conflict(result));
+        assert_eq!(fixture.read("a.json").await, "first");
+    }
+
+    #[tokio::test]
+    async fn if_match_requires_current_etag() {
+        let fixture = Fixture::new();
+        let first = fixture.source("first", "first");
+        let second = fixture.source("second", "second!");
+        let third = fixture.source("third", "third!!!");
+
+        let created = storage::put_conditional(&fixture.client, &first, "a.json", create_only(), None).await.unwrap();
+        let etag = created.etag.unwrap();
+        assert_eq!(fixture.client.head("a.json").await.unwrap().etag.as_deref(), Some(etag.as_str()));
+
+        let updated = storage::put_conditional(&fixture.client, &second, "a.json", if_match(&etag), None).await.unwrap();
+        assert_ne!(updated.etag.as_deref(), Some(etag.as_str()));
+
+        // 以过期的 ETag 写入失败，内容保持上一次写入的结果
+        let result = storage::put_conditional(&fixture.client, &third, "a.json", if_match(&etag), None).await;
+        assert!(is_conflict(result));
+        assert_eq!(fixture.read("a.json").await, "second!");
+    }
+
+    #[tokio::test]
+    async fn if_match_rejects_missing_object() {
+        let fixture = Fixture::new();
+        let source = fixture.source("source", "content");
+
+        let result = storage::put_conditional(&fixture.client, &source, "a.json", if_match("0-7"), None).await;
+
+        assert!(is_conflict(result));
+        assert!(!fixture.client.head("a.json").await.unwrap().exists);
+    }
+}
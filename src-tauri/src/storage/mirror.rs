@@ -0,0 +1,162 @@
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{CosHeaders, Provider, StorageBackend, UploadOptions, UploadResult};
+use crate::error::CosError;
+use crate::progress::ProgressTracker;
+
+// 未指定时探测单个链接的超时时间
+const DEFAULT_PROBE_TIMEOUT_MS: u64 = 3000;
+
+// 带名称的存储后端，名称来自前端的镜像配置
+pub type NamedBackend = (String, Arc<dyn StorageBackend>);
+
+// 镜像目标：前端配置中的名称与客户端池中的 client_id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorTarget {
+    pub name: String,
+    pub client_id: String,
+}
+
+// 单个镜像的上传结果，失败时 error 不为空，不影响主存储的上传结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorUpload {
+    pub name: String,
+    pub provider: Provider,
+    pub url: Option<String>,
+    pub etag: Option<String>,
+    pub error: Option<CosError>,
+}
+
+// 主存储的上传结果（字段与 UploadResult 相同）附带各镜像的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirroredUploadResult {
+    #[serde(flatten)]
+    pub upload: UploadResult,
+    pub mirrors: Vec<MirrorUpload>,
+}
+
+// 先上传到主存储，成功后并发上传到各镜像
+// 主存储做了服务端图片处理（Pic-Operations / x-oss-process）时，镜像上传的是处理后的文件
+pub async fn put_mirrored(
+    primary: &Arc<dyn StorageBackend>,
+    mirrors: Vec<NamedBackend>,
+    file_path: &str,
+    key: &str,
+    options: Option<UploadOptions>,
+    progress: Option<ProgressTracker>,
+) -> Result<MirroredUploadResult, CosError> {
+    let processed = options.as_ref()
+        .and_then(|opts| opts.headers.as_ref())
+        .is_some_and(|h| h.pic_operations.is_some() || h.image_process.is_some());
+    // 镜像只保留内容类型和分块设置，处理规则只对主存储生效
+    let mirror_options = options.as_ref().map(|opts| UploadOptions {
+        callback: None,
+        headers: opts.headers.as_ref().map(|h| CosHeaders {
+            content_type: h.content_type.clone(),
+            pic_operations: None,
+            image_process: None,
+        }),
+        multipart: opts.multipart.clone(),
+        mirrors: None,
+        condition: None,
+        original_name: None,
+        force: false,
+    });
+
+    let upload = primary.put_file(file_path, key, options, progress).await?;
+    if mirrors.is_empty() {
+        return Ok(MirroredUploadResult { upload, mirrors: Vec::new() });
+    }
+
+    // 处理后的对象下载到临时目录，不写在用户的原始文件旁
+    let temp_dir = std::env::temp_dir().join("imsheet_mirror");
+    let processed_path = temp_dir.join(uuid::Uuid::new_v4().simple().to_string()).to_string_lossy().into_owned();
+    let source = if processed {
+        match tokio::fs::create_dir_all(&temp_dir).await {
+            Ok(()) => primary.get_file(&upload.key, &processed_path, None).await.map(|_| processed_path.clone()),
+            Err(e) => Err(e.into()),
+        }
+    } else {
+        Ok(file_path.to_string())
+    };
+
+    let uploads = mirrors.into_iter().map(|(name, mirror)| {
+        let source = source.clone();
+        let options = mirror_options.clone();
+        let key = upload.key.clone();
+        async move {
+            let result = match source {
+                Ok(source) => mirror.put_file(&source, &key, options, None).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(result) => {
+                    println!("  🪞 镜像上传成功: {} -> {}", name, result.url);
+                    MirrorUpload {
+                        name,
+                        provider: mirror.provider(),
+                        url: Some(result.url),
+                        etag: result.etag,
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    println!("  ⚠️ 镜像上传失败: {} - {}", name, e);
+                    MirrorUpload {
+                        name,
+                        provider: mirror.provider(),
+                        url: None,
+                        etag: None,
+                        error: Some(e),
+                    }
+                }
+            }
+        }
+    });
+    let mirrors = join_all(uploads).await;
+
+    if processed {
+        let _ = tokio::fs::remove_file(&processed_path).await;
+    }
+
+    Ok(MirroredUploadResult { upload, mirrors })
+}
+
+// 并发探测链接是否可访问，用于主存储不可用时切换到镜像链接
+// http(s) 链接返回 2xx/3xx 视为可用，file:// 链接检查文件是否存在
+pub async fn probe_urls(urls: Vec<String>, timeout_ms: Option<u64>) -> Vec<bool> {
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_PROBE_TIMEOUT_MS));
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new());
+
+    join_all(urls.iter().map(|url| probe_url(&client, url))).await
+}
+
+async fn probe_url(client: &reqwest::Client, url: &str) -> bool {
+    if let Some(path) = url.strip_prefix("file://") {
+        let path = urlencoding::decode(path).map(|p| p.into_owned()).unwrap_or_else(|_| path.to_string());
+        // Windows 路径形如 /C:/...，去掉开头的斜杠
+        let path = match path.as_bytes() {
+            [b'/', _, b':', ..] => path[1..].to_string(),
+            _ => path,
+        };
+        return tokio::fs::metadata(path).await.is_ok_and(|m| m.is_file());
+    }
+
+    let available = |response: &reqwest::Response| {
+        response.status().is_success() || response.status().is_redirection()
+    };
+    match client.head(url).send().await {
+        Ok(response) if available(&response) => true,
+        // 部分 CDN 不支持 HEAD，改用 GET 再确认一次
+        Ok(response) if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED => {
+            client.get(url).send().await.is_ok_and(|r| available(&r))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,472 @@
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use std::collections::BTreeMap;
+
+use super::{
+    uri_encode, DeleteResult, DownloadResult, ListObjectsResult, ObjectMetadata, Provider, StorageBackend,
+    UploadOptions, UploadResult, WRITE_LOCK_SUFFIX, WRITE_LOCK_TTL_SECS,
+};
+use crate::cos::{
+    content_type_for_path, file_body, parse_delete_result, parse_list_objects, send_with_retry, xml_escape,
+    NetworkOptions,
+};
+use crate::error::CosError;
+use crate::progress::ProgressTracker;
+
+type HmacSha1 = Hmac<Sha1>;
+
+// DeleteMultipleObjects 单次最多 1000 个对象
+const MAX_DELETE_KEYS: usize = 1000;
+
+// 阿里云 OSS 配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OssConfig {
+    #[serde(rename = "AccessKeyId")]
+    pub access_key_id: String,
+    #[serde(rename = "AccessKeySecret")]
+    pub access_key_secret: String,
+    #[serde(rename = "Bucket")]
+    pub bucket: String,
+    // 地域，如 oss-cn-hangzhou 或 cn-hangzhou
+    #[serde(rename = "Region")]
+    pub region: String,
+    // 自定义服务地址，如内网地址 oss-cn-hangzhou-internal.aliyuncs.com
+    #[serde(rename = "Endpoint")]
+    pub endpoint: Option<String>,
+    // 对外访问域名（CDN 或自定义域名）
+    #[serde(rename = "Domain")]
+    pub domain: Option<String>,
+    #[serde(rename = "Dir")]
+    pub dir: Option<String>,
+    // 图片样式名，生成链接时附加 x-oss-process=style/<name>
+    #[serde(rename = "ImageStyle")]
+    pub image_style: Option<String>,
+    #[serde(rename = "Network", default)]
+    pub network: Option<NetworkOptions>,
+}
+
+// 阿里云 OSS 客户端，使用 OSS 头部签名（HMAC-SHA1）
+pub struct OssClient {
+    config: OssConfig,
+    client: reqwest::Client,
+    network: NetworkOptions,
+    // 不含存储桶的服务地址，如 oss-cn-hangzhou.aliyuncs.com
+    endpoint_host: String,
+}
+
+impl OssClient {
+    pub fn new(config: OssConfig) -> Self {
+        let endpoint_host = match config.endpoint.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+            Some(endpoint) => endpoint
+                .trim_start_matches("https://")
+                .trim_start_matches("http://")
+                .trim_end_matches('/')
+                .to_string(),
+            None if config.region.starts_with("oss-") => format!("{}.aliyuncs.com", config.region),
+            None => format!("oss-{}.aliyuncs.com", config.region),
+        };
+
+        let network = config.network.clone().unwrap_or_default();
+        let client = network.build_client();
+
+        Self { config, client, network, endpoint_host }
+    }
+
+    fn get_full_key(&self, key: &str) -> String {
+        super::full_key(self.config.dir.as_deref(), key)
+    }
+
+    // 存储桶访问域名
+    fn host(&self) -> String {
+        format!("{}.{}", self.config.bucket, self.endpoint_host)
+    }
+
+    // 生成签名：VERB\nContent-MD5\nContent-Type\nDate\nCanonicalizedResource
+    // 不发送 x-oss-* 头部，因此 CanonicalizedOSSHeaders 为空
+    // resource 为 /bucket/key 加上子资源，如 /bucket/?delete
+    fn generate_signature(&self, method: &str, headers: &[(&str, &str)], date: &str, resource: &str) -> String {
+        let header = |name: &str| headers.iter().find(|(key, _)| *key == name).map(|(_, value)| *value).unwrap_or("");
+        // CanonicalizedOSSHeaders：x-oss- 开头的请求头按名称排序，每行 name:value
+        let oss_headers: BTreeMap<String, &str> = headers
+            .iter()
+            .filter(|(key, value)| !value.is_empty() && key.to_ascii_lowercase().starts_with("x-oss-"))
+            .map(|(key, value)| (key.to_ascii_lowercase(), *value))
+            .collect();
+        let canonical_headers: String = oss_headers.iter().map(|(key, value)| format!("{}:{}\n", key, value)).collect();
+
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}\n{}{}",
+            method, header("Content-MD5"), header("Content-Type"), date, canonical_headers, resource
+        );
+
+        let mut mac = HmacSha1::new_from_slice(self.config.access_key_secret.as_bytes()).unwrap();
+        mac.update(string_to_sign.as_bytes());
+        let signature = base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes());
+
+        format!("OSS {}:{}", self.config.access_key_id, signature)
+    }
+
+    // 构造带签名的请求，每次重试时调用以刷新 Date
+    // sub_resource 参与签名，query 仅追加到 URL；headers 中的 Content-MD5、Content-Type 和 x-oss- 头参与签名
+    fn signed_request(
+        &self,
+        method: reqwest::Method,
+        full_key: &str,
+        sub_resource: Option<&str>,
+        query: &BTreeMap<String, String>,
+        headers: &[(&str, &str)],
+    ) -> reqwest::RequestBuilder {
+        let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+
+        let mut resource = format!("/{}/{}", self.config.bucket, full_key);
+        let mut params: Vec<String> = Vec::new();
+        if let Some(sub_resource) = sub_resource {
+            resource.push('?');
+            resource.push_str(sub_resource);
+            params.push(sub_resource.to_string());
+        }
+        params.extend(query.iter().map(|(k, v)| format!("{}={}", uri_encode(k, true), uri_encode(v, true))));
+
+        let authorization = self.generate_signature(method.as_str(), headers, &date, &resource);
+
+        let mut url = format!("https://{}/{}", self.host(), uri_encode(full_key, false));
+        if !params.is_empty() {
+            url.push('?');
+            url.push_str(&params.join("&"));
+        }
+
+        let mut request = self.client
+            .request(method, url)
+            .header("Authorization", authorization)
+            .header("Date", date);
+        for (name, value) in headers.iter().filter(|(_, value)| !value.is_empty()) {
+            request = request.header(*name, *value);
+        }
+        request
+    }
+
+    // 对已上传的图片应用图片处理并覆盖原文件（sys/saveas），效果与 COS 的 Pic-Operations 一致
+    async fn process_and_overwrite(&self, full_key: &str, process: &str) -> Result<(), CosError> {
+        let encode = |value: &str| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(value);
+        let body = format!(
+            "x-oss-process={}|sys/saveas,o_{},b_{}",
+            process,
+            encode(full_key),
+            encode(&self.config.bucket)
+        );
+        let content_type = "application/x-www-form-urlencoded";
+        let query = BTreeMap::new();
+
+        let response = send_with_retry(&self.network, "OSS Image Process", |_| {
+            Ok(self.signed_request(reqwest::Method::POST, full_key, Some("x-oss-process"), &query, &[("Content-Type", content_type)])
+                .body(body.clone()))
+        }).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(CosError::from_response(response).await)
+        }
+    }
+
+    // 条件写入的锁对象，以 x-oss-forbid-overwrite 创建实现互斥，返回其相对 key
+    // 超过 WRITE_LOCK_TTL_SECS 的锁对象视为写入方崩溃遗留，删除后重试一次
+    async fn lock_object(&self, key: &str) -> Result<String, CosError> {
+        let lock = format!("{}{}", key, WRITE_LOCK_SUFFIX);
+        let full_key = self.get_full_key(&lock);
+        let query = BTreeMap::new();
+
+        for attempt in 0..2 {
+            let response = send_with_retry(&self.network, "OSS PUT Lock", |_| {
+                Ok(self.signed_request(reqwest::Method::PUT, &full_key, None, &query, &[
+                    ("Content-Type", "application/octet-stream"),
+                    ("x-oss-forbid-overwrite", "true"),
+                ])
+                    .header("Content-Length", 0))
+            }).await?;
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(lock);
+            }
+            if status != reqwest::StatusCode::CONFLICT {
+                return Err(CosError::from_response(response).await);
+            }
+
+            let expired = self.head(&lock).await?.last_modified
+                .and_then(|t| chrono::DateTime::parse_from_rfc2822(&t).ok())
+                .is_some_and(|t| Utc::now().timestamp() - t.timestamp() > WRITE_LOCK_TTL_SECS);
+            if attempt > 0 || !expired {
+                break;
+            }
+            self.unlock_object(&lock).await;
+        }
+        Err(CosError::precondition_failed("其他设备正在写入该对象"))
+    }
+
+    // 删除锁对象，失败时等待其过期
+    async fn unlock_object(&self, lock: &str) {
+        if let Err(e) = self.delete(vec![lock.to_string()]).await {
+            println!("  ⚠️ 删除锁对象 {} 失败: {}", lock, e);
+        }
+    }
+
+    // 发送单次 DeleteMultipleObjects 请求
+    async fn delete_chunk(&self, full_keys: &[String]) -> Result<DeleteResult, CosError> {
+        let mut xml_body = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Delete>\n<Quiet>false</Quiet>\n");
+        for full_key in full_keys {
+            xml_body.push_str(&format!("<Object><Key>{}</Key></Object>\n", xml_escape(full_key)));
+        }
+        xml_body.push_str("</Delete>");
+
+        let content_md5 = base64::engine::general_purpose::STANDARD.encode(md5::compute(xml_body.as_bytes()).0);
+        let query = BTreeMap::new();
+
+        let response = send_with_retry(&self.network, "OSS Delete Multiple Objects", |_| {
+            Ok(self.signed_request(reqwest::Method::POST, "", Some("delete"), &query, &[
+                ("Content-MD5", &content_md5),
+                ("Content-Type", "application/xml"),
+            ])
+                .body(xml_body.clone()))
+        }).await?;
+
+        if response.status().is_success() {
+            parse_delete_result(&response.text().await?)
+        } else {
+            Err(CosError::from_response(response).await)
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for OssClient {
+    fn provider(&self) -> Provider {
+        Provider::Oss
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        self.get_full_key(key)
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        let full_key = self.get_full_key(key);
+        let url = match &self.config.domain {
+            Some(domain) => format!("{}/{}", domain.trim_end_matches('/'), full_key),
+            None => format!("https://{}/{}", self.host(), uri_encode(&full_key, false)),
+        };
+        match self.config.image_style.as_deref().filter(|s| !s.is_empty()) {
+            Some(style) => format!("{}?x-oss-process=style/{}", url, style),
+            None => url,
+        }
+    }
+
+    // 仅在不存在时创建使用 x-oss-forbid-overwrite；比对 ETag 由锁对象互斥后进行
+    fn supports_conditional_put(&self) -> bool {
+        true
+    }
+
+    async fn put_file(
+        &self,
+        file_path: &str,
+        key: &str,
+        options: Option<UploadOptions>,
+        progress: Option<ProgressTracker>,
+    ) -> Result<UploadResult, CosError> {
+        let file_size = std::fs::metadata(file_path)?.len();
+        if let Some(tracker) = &progress {
+            tracker.set_total(file_size);
+        }
+
+        let headers = options.as_ref().and_then(|opts| opts.headers.as_ref());
+        let content_type = headers
+            .and_then(|h| h.content_type.clone())
+            .unwrap_or_else(|| content_type_for_path(file_path));
+        let image_process = headers.and_then(|h| h.image_process.clone());
+
+        let full_key = self.get_full_key(key);
+        let query = BTreeMap::new();
+
+        let condition = options.as_ref().and_then(|opts| opts.condition.clone());
+        let create_only = condition.as_ref().and_then(|c| c.if_none_match.as_deref()) == Some("*");
+        let lock = match &condition {
+            // 仅在不存在时创建由服务端的 x-oss-forbid-overwrite 保证
+            Some(condition) if create_only && condition.if_match.is_none() => None,
+            // PutObject 不支持 If-Match，持有锁对象时比对 ETag 后写入
+            Some(condition) => {
+                let lock = self.lock_object(key).await?;
+                if let Err(e) = self.head(key).await.and_then(|metadata| condition.check(&metadata)) {
+                    self.unlock_object(&lock).await;
+                    return Err(e);
+                }
+                Some(lock)
+            }
+            None => None,
+        };
+        let forbid_overwrite = if create_only { "true" } else { "" };
+
+        let response = send_with_retry(&self.network, "OSS PUT Object", |activity| {
+            Ok(self.signed_request(reqwest::Method::PUT, &full_key, None, &query, &[
+                ("Content-Type", &content_type),
+                ("x-oss-forbid-overwrite", forbid_overwrite),
+            ])
+                .header("Content-Length", file_size)
+                .body(file_body(file_path, activity, progress.as_ref())?))
+        }).await;
+        if let Some(lock) = &lock {
+            self.unlock_object(lock).await;
+        }
+        let response = response?;
+
+        if create_only && response.status() == reqwest::StatusCode::CONFLICT {
+            return Err(CosError::precondition_failed("对象已存在"));
+        }
+        if !response.status().is_success() {
+            return Err(CosError::from_response(response).await);
+        }
+
+        if let Some(tracker) = &progress {
+            tracker.finish();
+        }
+
+        let mut etag = response.headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.trim_matches('"').to_string());
+        let mut size = file_size;
+
+        // 图片处理会覆盖原文件，重新读取处理后的大小和 ETag
+        if let Some(process) = image_process.filter(|p| !p.is_empty()) {
+            self.process_and_overwrite(&full_key, &process).await?;
+            let metadata = self.head(key).await?;
+            size = metadata.size.unwrap_or(file_size);
+            etag = metadata.etag.or(etag);
+        }
+
+        Ok(UploadResult {
+            success: true,
+            key: key.to_string(),
+            url: self.object_url(key),
+            etag,
+            size,
+        })
+    }
+
+    async fn get_file(
+        &self,
+        key: &str,
+        save_path: &str,
+        progress: Option<ProgressTracker>,
+    ) -> Result<DownloadResult, CosError> {
+        let full_key = self.get_full_key(key);
+        let query = BTreeMap::new();
+
+        let response = send_with_retry(&self.network, "OSS GET Object", |_| {
+            Ok(self.signed_request(reqwest::Method::GET, &full_key, None, &query, &[]))
+        }).await?;
+
+        if !response.status().is_success() {
+            return Err(CosError::from_response(response).await);
+        }
+
+        let etag = super::response_etag(&response);
+        let size = super::save_response(response, save_path, progress.as_ref()).await?;
+
+        Ok(DownloadResult {
+            success: true,
+            file_path: save_path.to_string(),
+            size,
+            etag,
+        })
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMetadata, CosError> {
+        let full_key = self.get_full_key(key);
+        let query = BTreeMap::new();
+
+        let response = send_with_retry(&self.network, "OSS HEAD Object", |_| {
+            Ok(self.signed_request(reqwest::Method::HEAD, &full_key, None, &query, &[]))
+        }).await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(ObjectMetadata {
+                exists: false,
+                size: None,
+                etag: None,
+                last_modified: None,
+                content_type: None,
+            });
+        }
+        if !status.is_success() {
+            return Err(CosError::from_response(response).await);
+        }
+
+        let header = |name: &str| {
+            response.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string())
+        };
+
+        Ok(ObjectMetadata {
+            exists: true,
+            size: header("content-length").and_then(|s| s.parse().ok()),
+            etag: header("etag").map(|s| s.trim_matches('"').to_string()),
+            last_modified: header("last-modified"),
+            content_type: header("content-type"),
+        })
+    }
+
+    async fn delete(&self, keys: Vec<String>) -> Result<DeleteResult, CosError> {
+        let mut deleted_count = 0;
+        let mut failed_keys = Vec::new();
+
+        for chunk in keys.chunks(MAX_DELETE_KEYS) {
+            let full_keys: Vec<String> = chunk.iter().map(|key| self.get_full_key(key)).collect();
+            let result = self.delete_chunk(&full_keys).await?;
+            deleted_count += result.deleted_count;
+            failed_keys.extend(result.failed_keys);
+        }
+
+        Ok(DeleteResult {
+            success: failed_keys.is_empty(),
+            deleted_count,
+            failed_keys,
+        })
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<String>,
+        max_keys: Option<u32>,
+        marker: Option<String>,
+        delimiter: Option<String>,
+    ) -> Result<ListObjectsResult, CosError> {
+        let mut query = BTreeMap::new();
+        query.insert("prefix".to_string(), self.get_full_key(prefix.as_deref().unwrap_or("")));
+
+        if let Some(max_keys) = max_keys {
+            query.insert("max-keys".to_string(), max_keys.to_string());
+        }
+        if let Some(marker) = marker.filter(|m| !m.is_empty()) {
+            query.insert("marker".to_string(), marker);
+        }
+        if let Some(delimiter) = delimiter.filter(|d| !d.is_empty()) {
+            query.insert("delimiter".to_string(), delimiter);
+        }
+
+        let response = send_with_retry(&self.network, "OSS GetBucket", |_| {
+            Ok(self.signed_request(reqwest::Method::GET, "", None, &query, &[]))
+        }).await?;
+
+        if response.status().is_success() {
+            parse_list_objects(&response.text().await?)
+        } else {
+            Err(CosError::from_response(response).await)
+        }
+    }
+}
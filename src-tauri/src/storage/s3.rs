@@ -0,0 +1,494 @@
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use super::{
+    uri_encode, DeleteResult, DownloadResult, ListObjectsResult, ObjectInfo, ObjectMetadata, Provider, StorageBackend,
+    UploadOptions, UploadResult,
+};
+use crate::cos::{content_type_for_path, file_body, parse_delete_result, send_with_retry, xml_escape, NetworkOptions};
+use crate::error::CosError;
+use crate::progress::ProgressTracker;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 空请求体的 SHA-256，GET/HEAD 等请求使用
+const EMPTY_PAYLOAD_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+// 流式上传时不对请求体计算摘要
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+// DeleteObjects 单次最多 1000 个对象
+const MAX_DELETE_KEYS: usize = 1000;
+
+// S3 兼容存储配置（AWS S3、MinIO、Cloudflare R2 等）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Config {
+    // 服务地址，如 http://127.0.0.1:9000，未设置时使用 AWS S3 区域地址
+    #[serde(rename = "Endpoint")]
+    pub endpoint: Option<String>,
+    #[serde(rename = "Region", default = "default_region")]
+    pub region: String,
+    #[serde(rename = "Bucket")]
+    pub bucket: String,
+    #[serde(rename = "AccessKeyId")]
+    pub access_key_id: String,
+    #[serde(rename = "SecretAccessKey")]
+    pub secret_access_key: String,
+    // 临时凭证的会话令牌
+    #[serde(rename = "SessionToken")]
+    pub session_token: Option<String>,
+    // 使用路径风格地址 endpoint/bucket/key，MinIO 等自建服务通常需要开启
+    #[serde(rename = "ForcePathStyle", default)]
+    pub force_path_style: bool,
+    // 对外访问域名（CDN 或自定义域名）
+    #[serde(rename = "Domain")]
+    pub domain: Option<String>,
+    #[serde(rename = "Dir")]
+    pub dir: Option<String>,
+    #[serde(rename = "Network", default)]
+    pub network: Option<NetworkOptions>,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+// S3 兼容存储客户端，使用 AWS Signature Version 4 签名
+pub struct S3Client {
+    config: S3Config,
+    client: reqwest::Client,
+    network: NetworkOptions,
+    // 服务地址的协议与主机（含端口）
+    scheme: String,
+    endpoint_host: String,
+}
+
+impl S3Client {
+    pub fn new(config: S3Config) -> Result<Self, CosError> {
+        let endpoint = config.endpoint
+            .clone()
+            .filter(|e| !e.trim().is_empty())
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", config.region));
+        let url = url::Url::parse(endpoint.trim_end_matches('/'))
+            .map_err(|e| CosError::invalid(format!("无效的 S3 服务地址 {}: {}", endpoint, e)))?;
+        let host = url.host_str()
+            .ok_or_else(|| CosError::invalid(format!("S3 服务地址缺少主机名: {}", endpoint)))?;
+        let endpoint_host = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+
+        let network = config.network.clone().unwrap_or_default();
+        let client = network.build_client();
+
+        Ok(Self {
+            scheme: url.scheme().to_string(),
+            endpoint_host,
+            config,
+            client,
+            network,
+        })
+    }
+
+    fn get_full_key(&self, key: &str) -> String {
+        super::full_key(self.config.dir.as_deref(), key)
+    }
+
+    // 请求的主机名：虚拟主机风格为 bucket.endpoint
+    fn host(&self) -> String {
+        if self.config.force_path_style {
+            self.endpoint_host.clone()
+        } else {
+            format!("{}.{}", self.config.bucket, self.endpoint_host)
+        }
+    }
+
+    // 存储桶根路径（已编码）
+    fn bucket_path(&self) -> String {
+        if self.config.force_path_style {
+            format!("/{}", uri_encode(&self.config.bucket, true))
+        } else {
+            "/".to_string()
+        }
+    }
+
+    // 对象路径（已编码）
+    fn object_path(&self, full_key: &str) -> String {
+        if self.config.force_path_style {
+            format!("/{}/{}", uri_encode(&self.config.bucket, true), uri_encode(full_key, false))
+        } else {
+            format!("/{}", uri_encode(full_key, false))
+        }
+    }
+
+    fn request_url(&self, path: &str, query: &BTreeMap<String, String>) -> String {
+        let mut url = format!("{}://{}{}", self.scheme, self.host(), path);
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&canonical_query(query));
+        }
+        url
+    }
+
+    // 构造带 SigV4 签名的请求，每次重试时调用以刷新签名时间
+    fn signed_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        query: &BTreeMap<String, String>,
+        mut headers: BTreeMap<String, String>,
+        payload_hash: &str,
+    ) -> reqwest::RequestBuilder {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        headers.insert("host".to_string(), self.host());
+        headers.insert("x-amz-date".to_string(), amz_date.clone());
+        headers.insert("x-amz-content-sha256".to_string(), payload_hash.to_string());
+        if let Some(token) = &self.config.session_token {
+            headers.insert("x-amz-security-token".to_string(), token.clone());
+        }
+
+        let canonical_headers: String = headers.iter()
+            .map(|(k, v)| format!("{}:{}\n", k, v.trim()))
+            .collect();
+        let signed_headers = headers.keys().cloned().collect::<Vec<_>>().join(";");
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method.as_str(),
+            path,
+            canonical_query(query),
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let signing_key = [date.as_str(), self.config.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", self.config.secret_access_key).into_bytes(), |key, part| hmac_sha256(&key, part));
+        let signature = hex::encode(hmac_sha256(&signing_key, &string_to_sign));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.config.access_key_id, scope, signed_headers, signature
+        );
+
+        // host 由 reqwest 根据 URL 设置
+        let mut request = self.client
+            .request(method, self.request_url(path, query))
+            .header("Authorization", authorization);
+        for (name, value) in headers.iter().filter(|(name, _)| name.as_str() != "host") {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        request
+    }
+
+    // 发送单次 DeleteObjects 请求
+    async fn delete_chunk(&self, full_keys: &[String]) -> Result<DeleteResult, CosError> {
+        let mut xml_body = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Delete>\n<Quiet>false</Quiet>\n");
+        for full_key in full_keys {
+            xml_body.push_str(&format!("<Object><Key>{}</Key></Object>\n", xml_escape(full_key)));
+        }
+        xml_body.push_str("</Delete>");
+
+        let content_md5 = base64::engine::general_purpose::STANDARD.encode(md5::compute(xml_body.as_bytes()).0);
+        let payload_hash = hex::encode(Sha256::digest(xml_body.as_bytes()));
+
+        let path = self.bucket_path();
+        let mut query = BTreeMap::new();
+        query.insert("delete".to_string(), String::new());
+
+        let response = send_with_retry(&self.network, "S3 DeleteObjects", |_| {
+            let mut headers = BTreeMap::new();
+            headers.insert("content-type".to_string(), "application/xml".to_string());
+            headers.insert("content-md5".to_string(), content_md5.clone());
+            Ok(self.signed_request(reqwest::Method::POST, &path, &query, headers, &payload_hash)
+                .body(xml_body.clone()))
+        }).await?;
+
+        if response.status().is_success() {
+            parse_delete_result(&response.text().await?)
+        } else {
+            Err(CosError::from_response(response).await)
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Client {
+    fn provider(&self) -> Provider {
+        Provider::S3
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        self.get_full_key(key)
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        let full_key = self.get_full_key(key);
+        match &self.config.domain {
+            Some(domain) => format!("{}/{}", domain.trim_end_matches('/'), full_key),
+            None => format!("{}://{}{}", self.scheme, self.host(), self.object_path(&full_key)),
+        }
+    }
+
+    fn supports_conditional_put(&self) -> bool {
+        true
+    }
+
+    async fn put_file(
+        &self,
+        file_path: &str,
+        key: &str,
+        options: Option<UploadOptions>,
+        progress: Option<ProgressTracker>,
+    ) -> Result<UploadResult, CosError> {
+        let file_size = std::fs::metadata(file_path)?.len();
+        if let Some(tracker) = &progress {
+            tracker.set_total(file_size);
+        }
+
+        let content_type = options.as_ref()
+            .and_then(|opts| opts.headers.as_ref())
+            .and_then(|headers| headers.content_type.clone())
+            .unwrap_or_else(|| content_type_for_path(file_path));
+
+        // 条件写入头部参与签名，前置条件不满足时服务端返回 412
+        let condition_headers = options.as_ref()
+            .and_then(|opts| opts.condition.as_ref())
+            .map(|condition| condition.request_headers())
+            .unwrap_or_default();
+
+        let full_key = self.get_full_key(key);
+        let path = self.object_path(&full_key);
+        let query = BTreeMap::new();
+
+        let response = send_with_retry(&self.network, "S3 PUT Object", |activity| {
+            let mut headers = BTreeMap::new();
+            headers.insert("content-type".to_string(), content_type.clone());
+            for (name, value) in &condition_headers {
+                headers.insert(name.to_string(), value.clone());
+            }
+            Ok(self.signed_request(reqwest::Method::PUT, &path, &query, headers, UNSIGNED_PAYLOAD)
+                .header("Content-Length", file_size)
+                .body(file_body(file_path, activity, progress.as_ref())?))
+        }).await?;
+
+        if !response.status().is_success() {
+            return Err(CosError::from_response(response).await);
+        }
+
+        if let Some(tracker) = &progress {
+            tracker.finish();
+        }
+
+        let etag = response.headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.trim_matches('"').to_string());
+
+        Ok(UploadResult {
+            success: true,
+            key: key.to_string(),
+            url: self.object_url(key),
+            etag,
+            size: file_size,
+        })
+    }
+
+    async fn get_file(
+        &self,
+        key: &str,
+        save_path: &str,
+        progress: Option<ProgressTracker>,
+    ) -> Result<DownloadResult, CosError> {
+        let path = self.object_path(&self.get_full_key(key));
+        let query = BTreeMap::new();
+
+        let response = send_with_retry(&self.network, "S3 GET Object", |_| {
+            Ok(self.signed_request(reqwest::Method::GET, &path, &query, BTreeMap::new(), EMPTY_PAYLOAD_SHA256))
+        }).await?;
+
+        if !response.status().is_success() {
+            return Err(CosError::from_response(response).await);
+        }
+
+        let etag = super::response_etag(&response);
+        let size = super::save_response(response, save_path, progress.as_ref()).await?;
+
+        Ok(DownloadResult {
+            success: true,
+            file_path: save_path.to_string(),
+            size,
+            etag,
+        })
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMetadata, CosError> {
+        let path = self.object_path(&self.get_full_key(key));
+        let query = BTreeMap::new();
+
+        let response = send_with_retry(&self.network, "S3 HEAD Object", |_| {
+            Ok(self.signed_request(reqwest::Method::HEAD, &path, &query, BTreeMap::new(), EMPTY_PAYLOAD_SHA256))
+        }).await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(ObjectMetadata {
+                exists: false,
+                size: None,
+                etag: None,
+                last_modified: None,
+                content_type: None,
+            });
+        }
+        if !status.is_success() {
+            return Err(CosError::from_response(response).await);
+        }
+
+        let header = |name: &str| {
+            response.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string())
+        };
+
+        Ok(ObjectMetadata {
+            exists: true,
+            size: header("content-length").and_then(|s| s.parse().ok()),
+            etag: header("etag").map(|s| s.trim_matches('"').to_string()),
+            last_modified: header("last-modified"),
+            content_type: header("content-type"),
+        })
+    }
+
+    async fn delete(&self, keys: Vec<String>) -> Result<DeleteResult, CosError> {
+        let mut deleted_count = 0;
+        let mut failed_keys = Vec::new();
+
+        for chunk in keys.chunks(MAX_DELETE_KEYS) {
+            let full_keys: Vec<String> = chunk.iter().map(|key| self.get_full_key(key)).collect();
+            let result = self.delete_chunk(&full_keys).await?;
+            deleted_count += result.deleted_count;
+            failed_keys.extend(result.failed_keys);
+        }
+
+        Ok(DeleteResult {
+            success: failed_keys.is_empty(),
+            deleted_count,
+            failed_keys,
+        })
+    }
+
+    // ListObjectsV2：marker 对应 continuation-token，由上一页的 next_marker 原样传回
+    async fn list(
+        &self,
+        prefix: Option<String>,
+        max_keys: Option<u32>,
+        marker: Option<String>,
+        delimiter: Option<String>,
+    ) -> Result<ListObjectsResult, CosError> {
+        let path = self.bucket_path();
+        let mut query = BTreeMap::new();
+        query.insert("list-type".to_string(), "2".to_string());
+        query.insert("prefix".to_string(), self.get_full_key(prefix.as_deref().unwrap_or("")));
+
+        if let Some(max_keys) = max_keys {
+            query.insert("max-keys".to_string(), max_keys.to_string());
+        }
+        if let Some(token) = marker.filter(|m| !m.is_empty()) {
+            query.insert("continuation-token".to_string(), token);
+        }
+        if let Some(delimiter) = delimiter.filter(|d| !d.is_empty()) {
+            query.insert("delimiter".to_string(), delimiter);
+        }
+
+        let response = send_with_retry(&self.network, "S3 ListObjectsV2", |_| {
+            Ok(self.signed_request(reqwest::Method::GET, &path, &query, BTreeMap::new(), EMPTY_PAYLOAD_SHA256))
+        }).await?;
+
+        if response.status().is_success() {
+            parse_list_objects_v2(&response.text().await?)
+        } else {
+            Err(CosError::from_response(response).await)
+        }
+    }
+}
+
+// ListObjectsV2 响应结构（仅包含需要的字段）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListBucketResultV2Xml {
+    #[serde(default)]
+    is_truncated: bool,
+    next_continuation_token: Option<String>,
+    #[serde(default)]
+    contents: Vec<ContentsXml>,
+    #[serde(default)]
+    common_prefixes: Vec<CommonPrefixXml>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContentsXml {
+    key: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(rename = "ETag", default)]
+    etag: String,
+    #[serde(default)]
+    last_modified: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CommonPrefixXml {
+    prefix: String,
+}
+
+fn parse_list_objects_v2(xml: &str) -> Result<ListObjectsResult, CosError> {
+    let parsed: ListBucketResultV2Xml = quick_xml::de::from_str(xml)
+        .map_err(|e| CosError::parse(format!("解析对象列表失败: {}", e)))?;
+
+    Ok(ListObjectsResult {
+        objects: parsed.contents
+            .into_iter()
+            .map(|c| ObjectInfo {
+                key: c.key,
+                size: c.size,
+                etag: c.etag.trim_matches('"').to_string(),
+                last_modified: c.last_modified,
+            })
+            .collect(),
+        common_prefixes: parsed.common_prefixes.into_iter().map(|p| p.prefix).collect(),
+        is_truncated: parsed.is_truncated,
+        next_marker: parsed.next_continuation_token.filter(|_| parsed.is_truncated),
+    })
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC 接受任意长度的密钥");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// 按参数名排序并编码的查询字符串
+fn canonical_query(query: &BTreeMap<String, String>) -> String {
+    query.iter()
+        .map(|(k, v)| format!("{}={}", uri_encode(k, true), uri_encode(v, true)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
@@ -0,0 +1,664 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use ssh2::{CheckResult, ErrorCode, KnownHostFileKind, OpenFlags, OpenType, RenameFlags, Session, Sftp};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{
+    DeleteError, DeleteResult, DownloadResult, ListObjectsResult, ObjectInfo, ObjectMetadata, Provider,
+    StorageBackend, UploadOptions, UploadResult, WriteCondition, WRITE_LOCK_SUFFIX, WRITE_LOCK_TTL_SECS,
+};
+use crate::cos::NetworkOptions;
+use crate::error::{CosError, ServiceError};
+use crate::progress::ProgressTracker;
+
+// 读写远程文件的缓冲区大小
+const TRANSFER_CHUNK_SIZE: usize = 64 * 1024;
+// libssh2 SFTP 状态码
+const FX_NO_SUCH_FILE: i32 = 2;
+const FX_PERMISSION_DENIED: i32 = 3;
+// libssh2 会话错误码
+const ERROR_TIMEOUT: i32 = -9;
+const ERROR_AUTHENTICATION_FAILED: i32 = -18;
+const ERROR_PUBLICKEY_UNVERIFIED: i32 = -19;
+
+// SFTP 配置：图片上传到 VPS 上由 Web 服务器托管的目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SftpConfig {
+    #[serde(rename = "Host")]
+    pub host: String,
+    #[serde(rename = "Port", default = "default_port")]
+    pub port: u16,
+    #[serde(rename = "Username")]
+    pub username: String,
+    // 私钥路径，如 ~/.ssh/id_ed25519；未设置时依次尝试密码和 ssh-agent
+    #[serde(rename = "PrivateKeyPath")]
+    pub private_key_path: Option<String>,
+    #[serde(rename = "Passphrase")]
+    pub passphrase: Option<String>,
+    #[serde(rename = "Password")]
+    pub password: Option<String>,
+    // known_hosts 文件路径，默认 ~/.ssh/known_hosts
+    #[serde(rename = "KnownHostsPath")]
+    pub known_hosts_path: Option<String>,
+    // 远程根目录，如 /var/www/images
+    #[serde(rename = "RemoteDir")]
+    pub remote_dir: String,
+    // 远程根目录对应的访问地址，如 https://img.example.com
+    #[serde(rename = "UrlPrefix")]
+    pub url_prefix: String,
+    #[serde(rename = "Dir")]
+    pub dir: Option<String>,
+    #[serde(rename = "Network", default)]
+    pub network: Option<NetworkOptions>,
+}
+
+fn default_port() -> u16 {
+    22
+}
+
+// 已建立的 SFTP 连接，Session 需要与 Sftp 一起保留
+struct Connection {
+    _session: Session,
+    sftp: Sftp,
+}
+
+struct SftpInner {
+    config: SftpConfig,
+    network: NetworkOptions,
+    connection: Mutex<Option<Connection>>,
+    // 已确认存在的远程目录，避免每次上传都逐级检查
+    directories: Mutex<HashSet<String>>,
+}
+
+// SFTP 客户端（libssh2），连接在多次操作之间复用，断开后自动重连
+// libssh2 是阻塞 API，所有操作都在 spawn_blocking 中执行
+pub struct SftpClient {
+    inner: Arc<SftpInner>,
+}
+
+impl SftpClient {
+    pub fn new(config: SftpConfig) -> Self {
+        let network = config.network.clone().unwrap_or_default();
+        Self {
+            inner: Arc::new(SftpInner {
+                config,
+                network,
+                connection: Mutex::new(None),
+                directories: Mutex::new(HashSet::new()),
+            }),
+        }
+    }
+
+    fn get_full_key(&self, key: &str) -> String {
+        super::full_key(self.inner.config.dir.as_deref(), key)
+    }
+
+    // 在阻塞线程中执行 SFTP 操作
+    async fn run<T, F>(&self, operation: F) -> Result<T, CosError>
+    where
+        T: Send + 'static,
+        F: Fn(&SftpInner, &Sftp) -> Result<T, CosError> + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || inner.with_sftp(operation))
+            .await
+            .map_err(|e| CosError::invalid(format!("SFTP 任务异常退出: {}", e)))?
+    }
+}
+
+impl SftpInner {
+    fn remote_path(&self, full_key: &str) -> String {
+        format!("{}/{}", self.config.remote_dir.trim_end_matches('/'), full_key)
+    }
+
+    // 使用已有连接执行操作；连接失效时重新连接并重试一次
+    fn with_sftp<T>(&self, operation: impl Fn(&SftpInner, &Sftp) -> Result<T, CosError>) -> Result<T, CosError> {
+        let mut connection = self.connection
+            .lock()
+            .map_err(|e| CosError::invalid(format!("Failed to lock SFTP connection: {}", e)))?;
+
+        let reused = connection.is_some();
+        if connection.is_none() {
+            *connection = Some(self.connect()?);
+        }
+
+        let result = operation(self, &connection.as_ref().expect("连接已建立").sftp);
+        match result {
+            Err(CosError::Network { .. }) if reused => {
+                *connection = Some(self.connect()?);
+                operation(self, &connection.as_ref().expect("连接已建立").sftp)
+            }
+            Err(CosError::Network { .. }) => {
+                *connection = None;
+                result
+            }
+            _ => result,
+        }
+    }
+
+    fn connect(&self) -> Result<Connection, CosError> {
+        let config = &self.config;
+        let address = (config.host.as_str(), config.port)
+            .to_socket_addrs()
+            .map_err(io_error)?
+            .next()
+            .ok_or_else(|| CosError::invalid(format!("无法解析主机地址: {}", config.host)))?;
+        let tcp = TcpStream::connect_timeout(&address, Duration::from_secs(self.network.connect_timeout_secs))
+            .map_err(io_error)?;
+
+        let mut session = Session::new().map_err(sftp_error)?;
+        session.set_tcp_stream(tcp);
+        session.set_timeout((self.network.read_timeout_secs.max(1) * 1000) as u32);
+        session.handshake().map_err(sftp_error)?;
+
+        self.verify_host_key(&session)?;
+        self.authenticate(&session)?;
+
+        let sftp = session.sftp().map_err(sftp_error)?;
+
+        Ok(Connection { _session: session, sftp })
+    }
+
+    // 主机密钥必须已记录在 known_hosts 中，不自动信任新主机
+    fn verify_host_key(&self, session: &Session) -> Result<(), CosError> {
+        let (key, _) = session.host_key()
+            .ok_or_else(|| CosError::invalid("无法获取服务器主机密钥"))?;
+
+        let path = match &self.config.known_hosts_path {
+            Some(path) => expand_home(path),
+            None => home_dir().join(".ssh").join("known_hosts"),
+        };
+
+        let mut known_hosts = session.known_hosts().map_err(sftp_error)?;
+        known_hosts.read_file(&path, KnownHostFileKind::OpenSSH)
+            .map_err(|e| CosError::invalid(format!("读取 known_hosts 失败 ({}): {}", path.display(), e)))?;
+
+        match known_hosts.check_port(&self.config.host, self.config.port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::NotFound => Err(host_key_error(
+                "UnknownHostKey",
+                format!("{} 不在 known_hosts 中，请先使用 ssh 连接一次并确认主机指纹", self.config.host),
+            )),
+            CheckResult::Mismatch => Err(host_key_error(
+                "HostKeyMismatch",
+                format!("{} 的主机密钥与 known_hosts 记录不一致，可能存在中间人攻击", self.config.host),
+            )),
+            CheckResult::Failure => Err(host_key_error("HostKeyCheckFailed", "主机密钥校验失败".to_string())),
+        }
+    }
+
+    fn authenticate(&self, session: &Session) -> Result<(), CosError> {
+        let config = &self.config;
+
+        if let Some(key_path) = config.private_key_path.as_deref().filter(|p| !p.is_empty()) {
+            session.userauth_pubkey_file(&config.username, None, &expand_home(key_path), config.passphrase.as_deref())
+                .map_err(sftp_error)?;
+        } else if let Some(password) = config.password.as_deref().filter(|p| !p.is_empty()) {
+            session.userauth_password(&config.username, password).map_err(sftp_error)?;
+        } else {
+            session.userauth_agent(&config.username).map_err(sftp_error)?;
+        }
+
+        if session.authenticated() {
+            Ok(())
+        } else {
+            Err(CosError::Auth(ServiceError {
+                status: 401,
+                code: "AuthenticationFailed".to_string(),
+                message: format!("SFTP 认证失败: {}", config.username),
+                request_id: None,
+            }))
+        }
+    }
+
+    // 逐级创建远程目录
+    fn ensure_directories(&self, sftp: &Sftp, remote_path: &str) -> Result<(), CosError> {
+        let Some((parent, _)) = remote_path.rsplit_once('/') else {
+            return Ok(());
+        };
+
+        let mut path = String::new();
+        for segment in parent.split('/') {
+            if segment.is_empty() {
+                path.push('/');
+                continue;
+            }
+            if !path.is_empty() && !path.ends_with('/') {
+                path.push('/');
+            }
+            path.push_str(segment);
+
+            if self.directories.lock().map(|d| d.contains(&path)).unwrap_or(false) {
+                continue;
+            }
+            if sftp.stat(Path::new(&path)).is_err() {
+                sftp.mkdir(Path::new(&path), 0o755)
+                    .or_else(|e| sftp.stat(Path::new(&path)).map(|_| ()).map_err(|_| e))
+                    .map_err(sftp_error)?;
+            }
+            if let Ok(mut directories) = self.directories.lock() {
+                directories.insert(path.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for SftpClient {
+    fn provider(&self) -> Provider {
+        Provider::Sftp
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        self.get_full_key(key)
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        format!("{}/{}", self.inner.config.url_prefix.trim_end_matches('/'), self.get_full_key(key))
+    }
+
+    // 服务端不校验条件，由对象旁以 EXCLUSIVE 创建的锁文件互斥，持有锁时比对 ETag 后写入
+    fn supports_conditional_put(&self) -> bool {
+        true
+    }
+
+    async fn put_file(
+        &self,
+        file_path: &str,
+        key: &str,
+        options: Option<UploadOptions>,
+        progress: Option<ProgressTracker>,
+    ) -> Result<UploadResult, CosError> {
+        let file_size = std::fs::metadata(file_path)?.len();
+        if let Some(tracker) = &progress {
+            tracker.set_total(file_size);
+        }
+
+        let full_key = self.get_full_key(key);
+        let local_path = file_path.to_string();
+        let tracker = progress.clone();
+        let condition = options.and_then(|opts| opts.condition);
+
+        let stat = self.run(move |inner, sftp| {
+            let remote_path = inner.remote_path(&full_key);
+            inner.ensure_directories(sftp, &remote_path)?;
+
+            let lock = match &condition {
+                Some(_) => Some(lock_object(sftp, &remote_path)?),
+                None => None,
+            };
+            let result = write_remote(sftp, &remote_path, &local_path, condition.as_ref(), tracker.as_ref());
+            if let Some(lock) = lock {
+                let _ = sftp.unlink(Path::new(&lock));
+            }
+            result
+        }).await?;
+
+        if let Some(tracker) = &progress {
+            tracker.finish();
+        }
+
+        Ok(UploadResult {
+            success: true,
+            key: key.to_string(),
+            url: self.object_url(key),
+            etag: Some(stat_etag(&stat)),
+            size: stat.size.unwrap_or(file_size),
+        })
+    }
+
+    async fn get_file(
+        &self,
+        key: &str,
+        save_path: &str,
+        progress: Option<ProgressTracker>,
+    ) -> Result<DownloadResult, CosError> {
+        let full_key = self.get_full_key(key);
+        let save_path = save_path.to_string();
+        let target = save_path.clone();
+
+        let (size, etag) = self.run(move |inner, sftp| {
+            let remote_path = inner.remote_path(&full_key);
+            let mut remote = sftp.open(Path::new(&remote_path)).map_err(sftp_error)?;
+            // ETag 取自打开的文件句柄，与读取的内容对应
+            let stat = remote.stat().map_err(sftp_error)?;
+            if let Some(tracker) = &progress {
+                tracker.reset();
+                tracker.set_total(stat.size.unwrap_or(0));
+            }
+
+            let mut local = std::fs::File::create(&target)?;
+            let mut buffer = vec![0u8; TRANSFER_CHUNK_SIZE];
+            let mut size = 0u64;
+            loop {
+                let read = remote.read(&mut buffer).map_err(io_error)?;
+                if read == 0 {
+                    break;
+                }
+                local.write_all(&buffer[..read])?;
+                size += read as u64;
+                if let Some(tracker) = &progress {
+                    tracker.advance(read as u64);
+                }
+            }
+            local.flush()?;
+
+            if let Some(tracker) = &progress {
+                tracker.finish();
+            }
+            Ok((size, stat_etag(&stat)))
+        }).await?;
+
+        Ok(DownloadResult {
+            success: true,
+            file_path: save_path,
+            size,
+            etag: Some(etag),
+        })
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMetadata, CosError> {
+        let full_key = self.get_full_key(key);
+
+        let stat = self.run(move |inner, sftp| {
+            match sftp.stat(Path::new(&inner.remote_path(&full_key))) {
+                Ok(stat) => Ok(Some(stat)),
+                Err(e) if e.code() == ErrorCode::SFTP(FX_NO_SUCH_FILE) => Ok(None),
+                Err(e) => Err(sftp_error(e)),
+            }
+        }).await?;
+
+        Ok(stat_metadata(stat.as_ref(), key))
+    }
+
+    async fn delete(&self, keys: Vec<String>) -> Result<DeleteResult, CosError> {
+        let full_keys: Vec<String> = keys.iter().map(|key| self.get_full_key(key)).collect();
+
+        let failed_keys = self.run(move |inner, sftp| {
+            let mut failed_keys = Vec::new();
+            for full_key in &full_keys {
+                match sftp.unlink(Path::new(&inner.remote_path(full_key))) {
+                    // 文件已不存在时视为删除成功
+                    Ok(()) => {}
+                    Err(e) if e.code() == ErrorCode::SFTP(FX_NO_SUCH_FILE) => {}
+                    Err(e) => {
+                        let error = sftp_error(e);
+                        // 连接断开时交给 with_sftp 重连后整体重试
+                        if matches!(error, CosError::Network { .. }) {
+                            return Err(error);
+                        }
+                        failed_keys.push(DeleteError {
+                            key: full_key.clone(),
+                            code: error.code().unwrap_or("SftpError").to_string(),
+                            message: error.to_string(),
+                        });
+                    }
+                }
+            }
+            Ok(failed_keys)
+        }).await?;
+
+        Ok(DeleteResult {
+            success: failed_keys.is_empty(),
+            deleted_count: keys.len() - failed_keys.len(),
+            failed_keys,
+        })
+    }
+
+    // SFTP 没有服务端分页：逐级 readdir 后由 paginate 截取一页
+    async fn list(
+        &self,
+        prefix: Option<String>,
+        max_keys: Option<u32>,
+        marker: Option<String>,
+        delimiter: Option<String>,
+    ) -> Result<ListObjectsResult, CosError> {
+        let full_prefix = self.get_full_key(prefix.as_deref().unwrap_or(""));
+        let recursive = delimiter.filter(|d| !d.is_empty()).is_none();
+
+        let entries = self.run(move |inner, sftp| {
+            let start_dir = match full_prefix.rfind('/') {
+                Some(index) => full_prefix[..=index].to_string(),
+                None => String::new(),
+            };
+
+            let mut entries: BTreeMap<String, Option<ObjectInfo>> = BTreeMap::new();
+            let mut pending = VecDeque::from([start_dir]);
+
+            while let Some(dir) = pending.pop_front() {
+                let children = match sftp.readdir(Path::new(&inner.remote_path(&dir))) {
+                    Ok(children) => children,
+                    Err(e) if e.code() == ErrorCode::SFTP(FX_NO_SUCH_FILE) => continue,
+                    Err(e) => return Err(sftp_error(e)),
+                };
+
+                for (path, stat) in children {
+                    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                        continue;
+                    };
+                    // 跳过上传中的临时文件
+                    if name.ends_with(".part") {
+                        continue;
+                    }
+
+                    if stat.is_dir() {
+                        let key = format!("{}{}/", dir, name);
+                        if recursive {
+                            if key.starts_with(&full_prefix) || full_prefix.starts_with(&key) {
+                                pending.push_back(key);
+                            }
+                        } else if key.starts_with(&full_prefix) {
+                            entries.insert(key, None);
+                        }
+                    } else {
+                        let key = format!("{}{}", dir, name);
+                        if key.starts_with(&full_prefix) {
+                            entries.insert(key.clone(), Some(ObjectInfo {
+                                key,
+                                size: stat.size.unwrap_or(0),
+                                etag: stat_etag(&stat),
+                                last_modified: stat.mtime
+                                    .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0))
+                                    .map(|t| t.to_rfc3339())
+                                    .unwrap_or_default(),
+                            }));
+                        }
+                    }
+                }
+            }
+            Ok(entries)
+        }).await?;
+
+        Ok(super::paginate(entries, marker.as_deref(), max_keys))
+    }
+}
+
+// 写入远程文件：仅在不存在时创建的直接以 EXCLUSIVE 打开目标文件，其他情况先写入临时文件再重命名
+// 条件写入的比对由调用方持有锁文件时在这里进行
+fn write_remote(
+    sftp: &Sftp,
+    remote_path: &str,
+    local_path: &str,
+    condition: Option<&WriteCondition>,
+    tracker: Option<&ProgressTracker>,
+) -> Result<ssh2::FileStat, CosError> {
+    let previous = match sftp.stat(Path::new(remote_path)) {
+        Ok(stat) => Some(stat),
+        Err(e) if e.code() == ErrorCode::SFTP(FX_NO_SUCH_FILE) => None,
+        Err(e) => return Err(sftp_error(e)),
+    };
+    if let Some(condition) = condition {
+        condition.check(&stat_metadata(previous.as_ref(), remote_path))?;
+    }
+    let create_only = condition.and_then(|c| c.if_none_match.as_deref()) == Some("*");
+
+    let temp_path = format!("{}.part", remote_path);
+    let mut local = std::fs::File::open(local_path)?;
+    let mut remote = if create_only {
+        create_exclusive(sftp, remote_path)?.ok_or_else(|| CosError::precondition_failed("对象已存在"))?
+    } else {
+        sftp.create(Path::new(&temp_path)).map_err(sftp_error)?
+    };
+    if let Some(tracker) = tracker {
+        tracker.reset();
+    }
+
+    let mut buffer = vec![0u8; TRANSFER_CHUNK_SIZE];
+    loop {
+        let read = local.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        remote.write_all(&buffer[..read]).map_err(io_error)?;
+        if let Some(tracker) = tracker {
+            tracker.advance(read as u64);
+        }
+    }
+    drop(remote);
+
+    if !create_only {
+        let overwrite = RenameFlags::OVERWRITE | RenameFlags::ATOMIC | RenameFlags::NATIVE;
+        if sftp.rename(Path::new(&temp_path), Path::new(remote_path), Some(overwrite)).is_err() {
+            // 部分服务器不支持覆盖重命名，先删除旧文件
+            let _ = sftp.unlink(Path::new(remote_path));
+            sftp.rename(Path::new(&temp_path), Path::new(remote_path), None).map_err(sftp_error)?;
+        }
+    }
+
+    let mut stat = sftp.stat(Path::new(remote_path)).map_err(sftp_error)?;
+    // 修改时间只精确到秒，同一秒内覆盖为相同大小时 ETag 不变，将修改时间后移一秒使之后的条件写入能区分
+    if let Some(previous) = previous.filter(|previous| stat_etag(previous) == stat_etag(&stat)) {
+        let mtime = previous.mtime.unwrap_or(0) + 1;
+        sftp.setstat(Path::new(remote_path), ssh2::FileStat {
+            size: None,
+            uid: None,
+            gid: None,
+            perm: None,
+            atime: stat.atime.or(Some(mtime)),
+            mtime: Some(mtime),
+        }).map_err(sftp_error)?;
+        stat.mtime = Some(mtime);
+    }
+    Ok(stat)
+}
+
+// 以 EXCLUSIVE 创建文件，已存在时返回 None
+fn create_exclusive(sftp: &Sftp, path: &str) -> Result<Option<ssh2::File>, CosError> {
+    let flags = OpenFlags::WRITE | OpenFlags::EXCLUSIVE;
+    match sftp.open_mode(Path::new(path), flags, 0o644, OpenType::File) {
+        Ok(file) => Ok(Some(file)),
+        // SFTP v3 没有“已存在”状态码，服务器通常返回 FX_FAILURE，以 stat 确认
+        Err(_) if sftp.stat(Path::new(path)).is_ok() => Ok(None),
+        Err(e) => Err(sftp_error(e)),
+    }
+}
+
+// 创建对象旁的锁文件，返回其路径；锁文件超过 WRITE_LOCK_TTL_SECS 时视为崩溃遗留，删除后重试一次
+fn lock_object(sftp: &Sftp, remote_path: &str) -> Result<String, CosError> {
+    let lock_path = format!("{}{}", remote_path, WRITE_LOCK_SUFFIX);
+    if create_exclusive(sftp, &lock_path)?.is_some() {
+        return Ok(lock_path);
+    }
+
+    let stale = sftp
+        .stat(Path::new(&lock_path))
+        .ok()
+        .and_then(|stat| stat.mtime)
+        .is_some_and(|mtime| chrono::Utc::now().timestamp() - mtime as i64 > WRITE_LOCK_TTL_SECS);
+    if stale {
+        let _ = sftp.unlink(Path::new(&lock_path));
+        if create_exclusive(sftp, &lock_path)?.is_some() {
+            return Ok(lock_path);
+        }
+    }
+    Err(CosError::precondition_failed("其他设备正在写入该对象"))
+}
+
+fn stat_metadata(stat: Option<&ssh2::FileStat>, key: &str) -> ObjectMetadata {
+    match stat.filter(|s| s.is_file()) {
+        Some(stat) => ObjectMetadata {
+            exists: true,
+            size: stat.size,
+            etag: Some(stat_etag(stat)),
+            last_modified: stat.mtime.and_then(|t| http_date(t as i64)),
+            content_type: Some(crate::cos::content_type_for_path(key)),
+        },
+        None => ObjectMetadata {
+            exists: false,
+            size: None,
+            etag: None,
+            last_modified: None,
+            content_type: None,
+        },
+    }
+}
+
+// SFTP 没有 ETag，用修改时间和大小生成，上传、下载、head 和列举的结果一致，不读取文件内容
+// SFTP v3（libssh2）的修改时间只精确到秒；需要校验内容时（如迁移）由调用方计算 MD5
+fn stat_etag(stat: &ssh2::FileStat) -> String {
+    format!("{:x}-{:x}", stat.mtime.unwrap_or(0), stat.size.unwrap_or(0))
+}
+
+fn http_date(timestamp: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(timestamp, 0).map(|t| t.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+}
+
+fn home_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_default()
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => home_dir().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+fn host_key_error(code: &str, message: String) -> CosError {
+    CosError::Auth(ServiceError {
+        status: 0,
+        code: code.to_string(),
+        message,
+        request_id: None,
+    })
+}
+
+// 将 libssh2 错误映射为与 HTTP 后端一致的错误类型
+fn sftp_error(error: ssh2::Error) -> CosError {
+    let service_error = |status: u16, code: &str| ServiceError {
+        status,
+        code: code.to_string(),
+        message: error.message().to_string(),
+        request_id: None,
+    };
+
+    match error.code() {
+        ErrorCode::SFTP(FX_NO_SUCH_FILE) => CosError::NotFound(service_error(404, "NoSuchFile")),
+        ErrorCode::SFTP(FX_PERMISSION_DENIED) => CosError::Auth(service_error(403, "PermissionDenied")),
+        ErrorCode::SFTP(code) => CosError::Service(service_error(500, &format!("SFTP_{}", code))),
+        ErrorCode::Session(ERROR_AUTHENTICATION_FAILED | ERROR_PUBLICKEY_UNVERIFIED) => {
+            CosError::Auth(service_error(401, "AuthenticationFailed"))
+        }
+        ErrorCode::Session(code) => CosError::Network {
+            message: error.message().to_string(),
+            timeout: code == ERROR_TIMEOUT,
+        },
+    }
+}
+
+// 连接及远程文件读写返回的 io::Error 通常意味着网络异常
+fn io_error(error: std::io::Error) -> CosError {
+    CosError::Network {
+        message: error.to_string(),
+        timeout: error.kind() == std::io::ErrorKind::TimedOut,
+    }
+}
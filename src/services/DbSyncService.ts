@@ -1,13 +1,23 @@
-import { tauriCosService, TauriCosService } from './TauriCosService';
+import { CosError, tauriCosService, TauriCosService, type OplogSyncReport, type SyncLease, type WriteCondition } from './TauriCosService';
 import { sqliteService } from './SqliteService';
 import { mConsole } from '../main';
 import { mkdir, readFile, remove, writeFile } from '@tauri-apps/plugin-fs';
 import { appDataDir, join } from '@tauri-apps/api/path';
 
+// 其他设备持有同步锁时的等待次数和间隔
+const LOCK_WAIT_ATTEMPTS = 5;
+const LOCK_WAIT_INTERVAL = 3000;
+
 export class DbSyncService {
   private static instance: DbSyncService;
   // 修复：使用返回boolean的锁类型
   private syncLock: Promise<boolean> = Promise.resolve(true);
+  // 上次推送是否因云端数据库已被其他设备更新而被拒绝
+  private lastPushConflict = false;
+  // 其他设备持有同步锁时为其租约，此时本机只读
+  private lockHolder: SyncLease | null = null;
+  // 未能获取同步锁时等待租约过期后重新推送的定时器
+  private pendingPush: ReturnType<typeof setTimeout> | null = null;
   
   private constructor() {}
   
@@ -131,6 +141,7 @@ export class DbSyncService {
   // 推送本地数据库到云端 - 修复：使用正确的数据库路径
   async pushDb(): Promise<boolean> {
     this.syncLock = this.syncLock.then(async () => {
+      this.lastPushConflict = false;
       try {
         // 确保本地数据库已初始化
         if (!sqliteService.dbReady) {
@@ -149,7 +160,12 @@ export class DbSyncService {
           const dbKey = this.getDbKey();
           mConsole.log('推送数据库到:', dbKey);
           
-          const result = await tauriCosService.upload(tempFilePath, dbKey);
+          // 以上次同步时的云端 ETag 为前提条件，云端已被其他设备更新时拒绝覆盖
+          const localHash = await sqliteService.getDbHash();
+          const condition: WriteCondition = localHash && localHash !== 'null'
+            ? { if_match: localHash }
+            : { if_none_match: '*' };
+          const result = await tauriCosService.uploadConditional(tempFilePath, dbKey, condition);
           
           // 更新本地数据库哈希
           if (result.etag) {
@@ -164,6 +180,11 @@ export class DbSyncService {
           this.cleanupTempFile(tempFilePath);
         }
       } catch (error) {
+        if (error instanceof CosError && error.kind === 'conflict') {
+          this.lastPushConflict = true;
+          mConsole.error('云端数据库已被其他设备更新，已拒绝覆盖，请先拉取云端数据库:', error.message);
+          return false;
+        }
         mConsole.error('推送数据库失败:', error);
         return false;
       }
@@ -185,7 +206,10 @@ export class DbSyncService {
           mConsole.log('从云端拉取数据库:', dbKey);
           
           // 从云端下载数据库
-          await tauriCosService.download(dbKey, tempFilePath);
+          const download = await tauriCosService.download(dbKey, tempFilePath);
+          
+          // 云端数据库由更新版本的应用写入时拒绝载入，保留本地数据库
+          await sqliteService.checkSchema(tempFilePath);
           
           // 读取下载的数据库文件
           const dbData = await this.readTempFile(tempFilePath);
@@ -193,10 +217,9 @@ export class DbSyncService {
           // 加载到本地数据库
           await sqliteService.loadFromBinary(new Uint8Array(dbData));
           
-          // 获取云端数据库的哈希并更新本地记录
-          const metadata = await tauriCosService.head(dbKey);
-          if (metadata.etag) {
-            const hash = metadata.etag.replace(/"/g, '');
+          // 记录下载内容的 ETag，而不是再 head 一次：两次请求之间云端可能已被其他设备更新
+          if (download.etag) {
+            const hash = download.etag.replace(/"/g, '');
             await sqliteService.updateDbHash(hash);
           }
           
@@ -245,14 +268,116 @@ export class DbSyncService {
     }
   }
   
-  // 同步本地数据库到云端（pushDb 的别名）
+  // 其他设备正在同步时本机只读，返回持有同步锁的租约；租约过期后恢复可写
+  get readOnlyLease(): SyncLease | null {
+    if (this.lockHolder && this.lockHolder.expires_at <= Date.now()) {
+      this.lockHolder = null;
+    }
+    return this.lockHolder;
+  }
+
+  // 同步本地数据库到云端：持有同步锁期间推送，其他设备持有时等待，超时后本机只读
+  // 本地修改已写入但未能推送时，租约过期后自动重新推送
   async syncToCloud(): Promise<boolean> {
+    if (!(await this.acquireSyncLock())) {
+      this.schedulePendingPush();
+      return false;
+    }
+    if (this.pendingPush) {
+      clearTimeout(this.pendingPush);
+      this.pendingPush = null;
+    }
+    try {
+      return await this.pushWithMerge();
+    } finally {
+      await tauriCosService.releaseSyncLock();
+    }
+  }
+
+  // 在其他设备的租约过期后重新推送，已有等待中的推送时不重复安排
+  private schedulePendingPush(): void {
+    const lease = this.lockHolder;
+    if (this.pendingPush || !lease) {
+      return;
+    }
+    const delay = Math.max(lease.expires_at - Date.now(), 0) + LOCK_WAIT_INTERVAL;
+    mConsole.log(`本地修改暂未推送，将在 ${Math.ceil(delay / 1000)} 秒后重试`);
+    this.pendingPush = setTimeout(() => {
+      this.pendingPush = null;
+      this.syncToCloud().catch(error => mConsole.error('重新推送数据库失败:', error));
+    }, delay);
+  }
+
+  // 获取同步锁，最多等待 LOCK_WAIT_ATTEMPTS 次
+  private async acquireSyncLock(): Promise<boolean> {
+    for (let attempt = 1; attempt <= LOCK_WAIT_ATTEMPTS; attempt++) {
+      try {
+        const status = await tauriCosService.acquireSyncLock();
+        if (status.acquired) {
+          this.lockHolder = null;
+          return true;
+        }
+        this.lockHolder = status.lease ?? null;
+      } catch (error) {
+        mConsole.error('获取同步锁失败:', error);
+        return false;
+      }
+
+      if (attempt < LOCK_WAIT_ATTEMPTS) {
+        mConsole.log(`设备 ${this.lockHolder?.device_id} 正在同步，等待后重试 (${attempt}/${LOCK_WAIT_ATTEMPTS})`);
+        await new Promise(resolve => setTimeout(resolve, LOCK_WAIT_INTERVAL));
+      }
+    }
+
+    mConsole.error('其他设备正在同步，本机暂时只读:', this.lockHolder);
+    return false;
+  }
+
+  // 先交换操作记录，再推送数据库快照
+  // 快照已被其他设备更新时，拉取云端快照并回放操作记录后重新推送，两端的修改都不会丢失
+  private async pushWithMerge(): Promise<boolean> {
+    await this.syncOperations();
+    if (await this.pushDb()) {
+      return true;
+    }
+    if (!this.lastPushConflict) {
+      return false;
+    }
+
+    mConsole.log('云端数据库已被其他设备更新，拉取后回放操作记录再推送');
+    if (!(await this.pullDb())) {
+      return false;
+    }
+    await this.syncOperations();
     return await this.pushDb();
   }
   
-  // 从云端同步数据库到本地（pullDb 的别名）
+  // 从云端同步数据库到本地：拉取快照前先上传本机的操作记录，拉取后回放快照之外的记录
   async syncFromCloud(): Promise<boolean> {
-    return await this.pullDb();
+    const report = await this.syncOperations();
+    if (report && report.pending > 0) {
+      mConsole.error(`有 ${report.pending} 条本机操作记录未能上传，暂不拉取云端数据库`);
+      return false;
+    }
+
+    const pulled = await this.pullDb();
+    if (pulled) {
+      await this.syncOperations();
+    }
+    return pulled;
+  }
+
+  // 交换操作记录，失败时不影响数据库快照的同步
+  private async syncOperations(): Promise<OplogSyncReport | null> {
+    if (!sqliteService.dbReady) {
+      return null;
+    }
+    try {
+      return await tauriCosService.syncOperations();
+    } catch (error) {
+      mConsole.error('操作日志同步失败:', error);
+      return null;
+    }
   }
   
   // 辅助方法：保存数据库到临时文件
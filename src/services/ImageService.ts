@@ -1,5 +1,26 @@
 import { sqliteService } from './SqliteService';
-import { tauriCosService } from './TauriCosService';
+import {
+  CosError,
+  tauriCosService,
+  type CosConfig,
+  type DuplicateOptions,
+  type DuplicateReport,
+  type ExistingImage,
+  type ImageMetadata,
+  type LibraryOperation,
+  type MigrationOptions,
+  type MigrationProgress,
+  type MigrationReport,
+  type MirrorUpload,
+  type RebuildOptions,
+  type RebuildProgress,
+  type RebuildReport,
+  type ReconcileFix,
+  type ReconcileFixResult,
+  type ReconcileReport,
+  type SimilarOptions,
+  type SimilarReport
+} from './TauriCosService';
 import { dbSyncService } from './DbSyncService';
 import { useConfigStore } from '../stores/UseConfigStore';
 import { rename } from '../utils/tools';
@@ -13,14 +34,47 @@ export interface ImageInfo {
   image_location: string;
   image_path: string;
   image_size: number;
+  // 0 为回收站，1 为正常，2 为对账时发现存储中对象已丢失
   image_state: number;
   create_time: number;
+  // 上传时读取的图片信息，旧记录和重建的记录可能为空
+  image_width?: number | null;
+  image_height?: number | null;
+  image_mime?: string | null;
+  // 原始文件的 SHA-256
+  image_hash?: string | null;
+  original_name?: string | null;
+  source_device?: string | null;
+  // 感知哈希（16 位十六进制），用于查找相似图片
+  image_dhash?: string | null;
+  image_phash?: string | null;
+  // 镜像链接，换行分隔，查询时由 imsheet_mirror 汇总
+  mirror_locations?: string | null;
 }
 
+// 上传图片的选项
+export interface UploadImageOptions {
+  // 图库中已有相同内容的图片时仍然上传
+  force?: boolean;
+  // 图库中已有相同图片时回调该图片，此时不上传也不新增记录
+  onDuplicate?: (image: ExistingImage) => void;
+}
+
+// 图片列表查询的字段，附带各镜像中的链接
+const IMAGE_COLUMNS = `imsheet.*, (
+  SELECT group_concat(m.image_location, char(10)) FROM imsheet_mirror m WHERE m.image_path = imsheet.image_path
+) AS mirror_locations`;
+
+// 主存储可用性探测结果的有效期
+const AVAILABILITY_CHECK_INTERVAL = 60 * 1000;
+
 export class ImageService {
   private static instance: ImageService;
   // 修复：使用返回boolean的锁类型
   private dbOperationLock: Promise<boolean> = Promise.resolve(true);
+  // 主存储链接是否可访问，不可访问时图片链接切换到镜像
+  private primaryAvailable = true;
+  private availabilityCheckedAt = 0;
   
   private constructor() {}
   
@@ -32,9 +86,10 @@ export class ImageService {
   }
   
   // 上传图片 - 修复数据库操作同步
-  async uploadImage(file: File, onProgress?: (progress: any) => void): Promise<boolean> {
+  async uploadImage(file: File, onProgress?: (progress: any) => void, options?: UploadImageOptions): Promise<boolean> {
     try {
       mConsole.log('开始上传图片:', file.name);
+      this.ensureWritable();
       
       // 获取配置存储实例
       const configStore = useConfigStore();
@@ -46,7 +101,14 @@ export class ImageService {
       const uploadOptions = this.prepareUploadOptions(configStore);
       
       // 上传到 COS
-      const uploadResult = await tauriCosService.push(file, fileName, onProgress, uploadOptions);
+      const uploadResult = await tauriCosService.push(file, fileName, onProgress, { ...uploadOptions, originalName: file.name, force: options?.force });
+
+      // 图库中已有相同图片，直接使用已有的链接
+      if (uploadResult.Duplicate) {
+        mConsole.log('图库中已有相同图片，跳过上传:', uploadResult.Duplicate.image_path);
+        options?.onDuplicate?.(uploadResult.Duplicate);
+        return true;
+      }
       
       // 保存到数据库 - 使用互斥锁确保数据库操作的原子性
       const imageInfo: ImageInfo = {
@@ -55,13 +117,14 @@ export class ImageService {
         image_path: uploadResult.Key || fileName,
         image_size: uploadResult.size || file.size,
         image_state: 1, // 正常状态
-        create_time: Date.now()
+        create_time: Date.now(),
+        ...this.metadataFields(uploadResult.Metadata)
       };
       
       // 等待之前的数据库操作完成，然后执行当前操作
       this.dbOperationLock = this.dbOperationLock.then(async () => {
         try {
-          return await this.saveImageToDatabase(imageInfo);
+          return await this.saveImageToDatabase(imageInfo, uploadResult.Mirrors);
         } catch (error) {
           mConsole.error('数据库操作失败:', error);
           return false;
@@ -85,9 +148,10 @@ export class ImageService {
   }
 
   // 新增：处理拖拽上传
-  async uploadImageFromDrag(file: File, onProgress?: (progress: any) => void): Promise<boolean> {
+  async uploadImageFromDrag(file: File, onProgress?: (progress: any) => void, options?: UploadImageOptions): Promise<boolean> {
     try {
       mConsole.log('开始处理拖拽上传图片:', file.name);
+      this.ensureWritable();
       
       // 获取配置存储实例
       const configStore = useConfigStore();
@@ -99,7 +163,14 @@ export class ImageService {
       const uploadOptions = this.prepareUploadOptions(configStore);
       
       // 使用拖拽上传方法上传到 COS
-      const uploadResult = await tauriCosService.handleDragUpload(file, fileName, onProgress, uploadOptions);
+      const uploadResult = await tauriCosService.handleDragUpload(file, fileName, onProgress, { ...uploadOptions, originalName: file.name, force: options?.force });
+
+      // 图库中已有相同图片，直接使用已有的链接
+      if (uploadResult.Duplicate) {
+        mConsole.log('图库中已有相同图片，跳过上传:', uploadResult.Duplicate.image_path);
+        options?.onDuplicate?.(uploadResult.Duplicate);
+        return true;
+      }
       
       // 保存到数据库 - 使用互斥锁确保数据库操作的原子性
       const imageInfo: ImageInfo = {
@@ -108,13 +179,14 @@ export class ImageService {
         image_path: uploadResult.Key || fileName,
         image_size: uploadResult.size || file.size,
         image_state: 1, // 正常状态
-        create_time: Date.now()
+        create_time: Date.now(),
+        ...this.metadataFields(uploadResult.Metadata)
       };
       
       // 等待之前的数据库操作完成，然后执行当前操作
       this.dbOperationLock = this.dbOperationLock.then(async () => {
         try {
-          return await this.saveImageToDatabase(imageInfo);
+          return await this.saveImageToDatabase(imageInfo, uploadResult.Mirrors);
         } catch (error) {
           mConsole.error('数据库操作失败:', error);
           return false;
@@ -158,12 +230,26 @@ export class ImageService {
     return uploadOptions;
   }
 
-  // 获取图片列表
-  async getImagesList(page: number, pageSize: number, state: number, dateRange?: [number, number]): Promise<ImageInfo[]> {
+  // 上传结果中的图片信息对应的 imsheet 字段
+  private metadataFields(metadata?: ImageMetadata): Partial<ImageInfo> {
+    return {
+      image_width: metadata?.width ?? null,
+      image_height: metadata?.height ?? null,
+      image_mime: metadata?.mime_type ?? null,
+      image_hash: metadata?.content_hash ?? null,
+      original_name: metadata?.original_name ?? null,
+      source_device: metadata?.source_device ?? null,
+      image_dhash: metadata?.dhash ?? null,
+      image_phash: metadata?.phash ?? null
+    };
+  }
+
+  // 获取图片列表，mimeType 不为空时只返回该类型的图片
+  async getImagesList(page: number, pageSize: number, state: number, dateRange?: [number, number], mimeType?: string): Promise<ImageInfo[]> {
     try {
       const offset = (page - 1) * pageSize;
       let sql = `
-        SELECT * FROM imsheet 
+        SELECT ${IMAGE_COLUMNS} FROM imsheet 
         WHERE image_state = ? 
       `;
       const params: any[] = [state];
@@ -174,11 +260,17 @@ export class ImageService {
         params.push(dateRange[0], dateRange[1]);
       }
       
+      if (mimeType) {
+        sql += ` AND image_mime = ? `;
+        params.push(mimeType);
+      }
+      
       sql += ` ORDER BY create_time DESC LIMIT ? OFFSET ? `;
       params.push(pageSize, offset);
       
-      const images = await sqliteService.all(sql, params);
-      return images as ImageInfo[];
+      const images = await sqliteService.all(sql, params) as ImageInfo[];
+      await this.refreshPrimaryAvailability(images);
+      return images;
     } catch (error) {
       mConsole.error('获取图片列表失败:', error);
       return [];
@@ -186,7 +278,7 @@ export class ImageService {
   }
 
   // 获取图片数量
-  async getImagesCount(state: number, dateRange?: [number, number]): Promise<number> {
+  async getImagesCount(state: number, dateRange?: [number, number], mimeType?: string): Promise<number> {
     try {
       let sql = `SELECT COUNT(*) as count FROM imsheet WHERE image_state = ?`;
       const params: any[] = [state];
@@ -197,6 +289,11 @@ export class ImageService {
         params.push(dateRange[0], dateRange[1]);
       }
       
+      if (mimeType) {
+        sql += ` AND image_mime = ?`;
+        params.push(mimeType);
+      }
+      
       const result = await sqliteService.get(sql, params);
       return result?.count || 0;
     } catch (error) {
@@ -205,9 +302,24 @@ export class ImageService {
     }
   }
 
+  // 指定状态的图片中出现过的类型，用于按类型筛选
+  async getMimeTypes(state: number): Promise<string[]> {
+    try {
+      const rows = await sqliteService.all(
+        'SELECT DISTINCT image_mime FROM imsheet WHERE image_state = ? AND image_mime IS NOT NULL ORDER BY image_mime',
+        [state]
+      );
+      return rows.map((row: any) => row.image_mime);
+    } catch (error) {
+      mConsole.error('获取图片类型失败:', error);
+      return [];
+    }
+  }
+
   // 移动到回收站（改变状态为0，更新时间）
   async moveToRecycleBin(imageId: number): Promise<boolean> {
     try {
+      this.ensureWritable();
       // 与原electron项目的changeImagesState逻辑一致
       const currentTime = Date.now();
       const sql = `
@@ -217,6 +329,7 @@ export class ImageService {
       `;
       
       await sqliteService.run(sql, [currentTime, imageId]);
+      await this.recordOperationById(imageId, image_path => ({ type: 'recycle', image_path, time: currentTime }));
       
       // 同步数据库到云端
       await dbSyncService.syncToCloud();
@@ -232,6 +345,7 @@ export class ImageService {
   // 从回收站恢复（改变状态为1，更新时间）
   async restoreFromRecycleBin(imageId: number): Promise<boolean> {
     try {
+      this.ensureWritable();
       // 与原electron项目的changeImagesState逻辑一致
       const currentTime = Date.now();
       const sql = `
@@ -241,6 +355,7 @@ export class ImageService {
       `;
       
       await sqliteService.run(sql, [currentTime, imageId]);
+      await this.recordOperationById(imageId, image_path => ({ type: 'restore', image_path, time: currentTime }));
       
       // 同步数据库到云端
       await dbSyncService.syncToCloud();
@@ -256,6 +371,7 @@ export class ImageService {
   // 清空回收站（与原electron项目的deleteImages逻辑完全一致）
   async emptyRecycleBin(): Promise<boolean> {
     try {
+      this.ensureWritable();
       mConsole.log('开始清空回收站...');
       
       // 1. 先同步云端数据库，确保数据一致性
@@ -275,31 +391,44 @@ export class ImageService {
       
       mConsole.log(`回收站中有 ${recycleBinImages.length} 张图片需要删除`);
       
-      // 3. 计算需要删除的总大小
-      const totalSize = recycleBinImages.reduce((sum, img) => sum + (img.image_size || 0), 0);
-      const totalCount = recycleBinImages.length;
-      
-      // 4. 准备要删除的 COS 对象键
+      // 3. 准备要删除的 COS 对象键
       const cosKeys = recycleBinImages.map(img => img.image_path);
       
-      // 5. 从 COS 批量删除文件
+      // 4. 从 COS 批量删除文件
       mConsole.log('从 COS 删除文件:', cosKeys);
-      await tauriCosService.delete(cosKeys);
+      const deleteResult = await tauriCosService.delete(cosKeys);
+      
+      // 5. 只处理 COS 确认删除的图片，删除失败的保留在回收站中
+      const failedKeys = new Set(deleteResult.failed_keys.map(failed => failed.key));
+      const deletedImages = recycleBinImages.filter(
+        img => !failedKeys.has(tauriCosService.resolveObjectKey(img.image_path))
+      );
+      
+      if (failedKeys.size > 0) {
+        mConsole.error('部分文件删除失败，已保留在回收站中:', deleteResult.failed_keys);
+      }
       
-      // 6. 从数据库删除记录
-      await sqliteService.run('DELETE FROM imsheet WHERE image_state = 0');
+      // 6. 从数据库删除记录，并清理镜像中的文件
+      for (const img of deletedImages) {
+        await sqliteService.run('DELETE FROM imsheet WHERE id = ?', [img.id]);
+        await sqliteService.run('DELETE FROM imsheet_mirror WHERE image_path = ?', [img.image_path]);
+        await this.recordOperation({ type: 'delete', image_path: img.image_path });
+      }
+      await tauriCosService.deleteFromMirrors(deletedImages.map(img => img.image_path));
       
       // 7. 优化数据库（释放空间）
       await sqliteService.run('VACUUM');
       
       // 8. 更新统计信息（减去删除的大小和数量）
+      const totalSize = deletedImages.reduce((sum, img) => sum + (img.image_size || 0), 0);
+      const totalCount = deletedImages.length;
       await this.updateStatistics(-totalSize, -totalCount);
       
       // 9. 同步数据库到云端
       await dbSyncService.syncToCloud();
       
       mConsole.log(`回收站清空完成，删除了 ${totalCount} 张图片，释放了 ${totalSize} 字节空间`);
-      return true;
+      return failedKeys.size === 0;
       
     } catch (error) {
       mConsole.error('清空回收站失败:', error);
@@ -310,6 +439,7 @@ export class ImageService {
   // 永久删除单张图片
   async permanentlyDeleteImage(imageId: number): Promise<boolean> {
     try {
+      this.ensureWritable();
       // 1. 获取图片信息
       const image = await sqliteService.get(
         'SELECT * FROM imsheet WHERE id = ? AND image_state = 0', 
@@ -321,11 +451,18 @@ export class ImageService {
         return false;
       }
       
-      // 2. 从 COS 删除文件
-      await tauriCosService.deleteObject(image.image_path);
+      // 2. 从 COS 删除文件，失败时保留数据库记录
+      const deleted = await tauriCosService.deleteObject(image.image_path);
+      if (!deleted) {
+        mConsole.error('从 COS 删除文件失败，保留记录:', image.image_path);
+        return false;
+      }
       
-      // 3. 从数据库删除记录
+      // 3. 从数据库删除记录，并清理镜像中的文件
       await sqliteService.run('DELETE FROM imsheet WHERE id = ?', [imageId]);
+      await sqliteService.run('DELETE FROM imsheet_mirror WHERE image_path = ?', [image.image_path]);
+      await this.recordOperation({ type: 'delete', image_path: image.image_path });
+      await tauriCosService.deleteFromMirrors([image.image_path]);
       
       // 4. 更新统计信息
       await this.updateStatistics(-image.image_size, -1);
@@ -341,9 +478,189 @@ export class ImageService {
     }
   }
 
-  // 获取图片URL
-  // 获取图片URL - 修复兼容旧项目数据的问题和自定义域名前缀
+  // 将整个图库迁移到新的存储配置，迁移前后同步云端数据库
+  // 全部图片和操作日志都迁移成功后切换到目标配置，并将改写后的数据库推送到目标存储；其他设备需自行切换配置
+  async migrateLibrary(
+    targetConfig: CosConfig,
+    options?: MigrationOptions,
+    onProgress?: (progress: MigrationProgress) => void
+  ): Promise<MigrationReport> {
+    // 与上传、删除共用数据库锁，避免迁移期间的改写被覆盖
+    const migration = this.dbOperationLock.then(async () => {
+      if (!options?.dry_run) {
+        this.ensureWritable();
+      }
+      const configStore = useConfigStore();
+      // 复制的操作日志以原密钥签名，目标配置未填写时沿用当前的同步密钥
+      const target = { ...targetConfig, SyncSecret: targetConfig.SyncSecret || configStore.cosConfig?.SyncSecret };
+      await dbSyncService.syncFromCloud();
+      const report = await tauriCosService.migrateLibrary(target, options, onProgress);
+      if (options?.dry_run) {
+        return report;
+      }
+      if (report.migrated > 0) {
+        await dbSyncService.syncToCloud();
+      }
+      if (report.failed.length === 0 && report.oplog_failed.length === 0) {
+        await this.switchToMigratedConfig(target);
+      }
+      return report;
+    });
+    this.dbOperationLock = migration.then(() => true, () => false);
+
+    const report = await migration;
+    if (report.failed.length > 0) {
+      mConsole.error(`图库迁移有 ${report.failed.length} 张图片失败，报告: ${report.report_path}`, report.failed);
+    }
+    if (report.oplog_failed.length > 0) {
+      mConsole.error(`图库迁移有 ${report.oplog_failed.length} 条操作日志复制失败，报告: ${report.report_path}`, report.oplog_failed);
+    }
+    return report;
+  }
+
+  // 切换到迁移后的配置；本地记录的云端 ETag 属于源存储，清空后按目标中是否已有数据库推送或合并
+  private async switchToMigratedConfig(target: CosConfig): Promise<void> {
+    await sqliteService.updateDbHash('');
+    const result = await useConfigStore().saveCosConfig(target);
+    if (!result.success) {
+      throw new CosError(`图库已迁移，但切换到目标配置失败: ${result.message}`, 'MIGRATION_SWITCH_FAILED');
+    }
+    await dbSyncService.syncToCloud();
+    mConsole.log('图库迁移完成，已切换到目标配置');
+  }
+
+  // 数据库丢失或损坏时按存储桶内容重建图库；fresh 为 true 时先清空本地数据库
+  async rebuildLibrary(
+    options?: RebuildOptions & { fresh?: boolean },
+    onProgress?: (progress: RebuildProgress) => void
+  ): Promise<RebuildReport> {
+    const rebuild = this.dbOperationLock.then(async () => {
+      if (!options?.dry_run) {
+        this.ensureWritable();
+      }
+      if (options?.fresh) {
+        await sqliteService.createFreshDb();
+      }
+      const report = await tauriCosService.rebuildLibrary(
+        { concurrency: options?.concurrency, dry_run: options?.dry_run },
+        onProgress
+      );
+      if (!options?.dry_run) {
+        await dbSyncService.syncToCloud();
+      }
+      return report;
+    });
+    this.dbOperationLock = rebuild.then(() => true, () => false);
+
+    const report = await rebuild;
+    if (report.failed.length > 0) {
+      mConsole.error(`图库重建有 ${report.failed.length} 个对象查询失败`, report.failed);
+    }
+    return report;
+  }
+
+  // 对比数据库与存储桶，先拉取云端数据库以免对比过期的本地副本
+  async reconcileLibrary(): Promise<ReconcileReport> {
+    const reconcile = this.dbOperationLock.then(async () => {
+      await dbSyncService.syncFromCloud();
+      return await tauriCosService.reconcileLibrary();
+    });
+    this.dbOperationLock = reconcile.then(() => true, () => false);
+    return await reconcile;
+  }
+
+  // 执行对账修复，改写了数据库时同步到云端
+  async applyReconcileFix(fix: ReconcileFix): Promise<ReconcileFixResult> {
+    const apply = this.dbOperationLock.then(async () => {
+      this.ensureWritable();
+      const result = await tauriCosService.applyReconcileFix(fix);
+      if (fix.action !== 'delete_orphans' && result.applied > 0) {
+        await dbSyncService.syncToCloud();
+      }
+      return result;
+    });
+    this.dbOperationLock = apply.then(() => true, () => false);
+
+    const result = await apply;
+    if (result.failed.length > 0) {
+      mConsole.error(`对账修复有 ${result.failed.length} 项失败`, result.failed);
+    }
+    return result;
+  }
+
+  // 查找内容完全相同的图片；补写了哈希时同步数据库到云端
+  async findDuplicates(options?: DuplicateOptions): Promise<DuplicateReport> {
+    const find = this.dbOperationLock.then(async () => {
+      if (options?.hash_missing) {
+        this.ensureWritable();
+      }
+      const report = await tauriCosService.findDuplicates(options);
+      if (report.hashed > 0) {
+        await dbSyncService.syncToCloud();
+      }
+      return report;
+    });
+    this.dbOperationLock = find.then(() => true, () => false);
+
+    const report = await find;
+    if (report.failed.length > 0) {
+      mConsole.error(`有 ${report.failed.length} 张图片计算哈希失败`, report.failed);
+    }
+    return report;
+  }
+
+  // 按感知哈希查找相似图片（缩放、重新压缩的副本等）；补写了哈希时同步数据库到云端
+  async findSimilar(options?: SimilarOptions): Promise<SimilarReport> {
+    const find = this.dbOperationLock.then(async () => {
+      if (options?.hash_missing) {
+        this.ensureWritable();
+      }
+      const report = await tauriCosService.findSimilar(options);
+      // 无法解码的图片已写入标记，同样需要同步，之后不再重复下载
+      if (report.hashed > 0 || report.undecodable > 0) {
+        await dbSyncService.syncToCloud();
+      }
+      return report;
+    });
+    this.dbOperationLock = find.then(() => true, () => false);
+
+    const report = await find;
+    if (report.failed.length > 0) {
+      mConsole.error(`有 ${report.failed.length} 张图片计算哈希失败`, report.failed);
+    }
+    return report;
+  }
+
+  // 获取图片URL，主存储不可访问时返回镜像链接
   getImageUrl(image: ImageInfo): string {
+    return this.getFailoverUrl(image) ?? this.getPrimaryImageUrl(image);
+  }
+
+  // 主存储不可访问时使用的第一个镜像链接
+  private getFailoverUrl(image: ImageInfo): string | null {
+    if (this.primaryAvailable) {
+      return null;
+    }
+    return image.mirror_locations?.split('\n').find(Boolean) ?? null;
+  }
+
+  // 配置了镜像时探测主存储是否可访问（结果缓存一分钟）
+  private async refreshPrimaryAvailability(images: ImageInfo[]): Promise<void> {
+    const sample = images.find(img => img.mirror_locations);
+    if (!sample || Date.now() - this.availabilityCheckedAt < AVAILABILITY_CHECK_INTERVAL) {
+      return;
+    }
+    this.availabilityCheckedAt = Date.now();
+
+    const [available] = await tauriCosService.probeUrls([this.getPrimaryImageUrl(sample)]);
+    if (available !== this.primaryAvailable) {
+      mConsole.log(available ? '主存储已恢复，使用主存储链接' : '主存储不可访问，切换到镜像链接');
+    }
+    this.primaryAvailable = available;
+  }
+
+  // 获取主存储中的图片URL - 修复兼容旧项目数据的问题和自定义域名前缀
+  private getPrimaryImageUrl(image: ImageInfo): string {
     // 如果 image_location 已经是完整URL，直接返回
     if (image.image_location.startsWith('http')) {
       return image.image_location;
@@ -392,7 +709,13 @@ export class ImageService {
 
   // 新增：获取应用自定义域名的图片URL（用于复制功能）
   getImageUrlWithCustomDomain(image: ImageInfo): string {
-    const originalUrl = this.getImageUrl(image);
+    // 镜像链接不使用主存储的自定义域名
+    const failoverUrl = this.getFailoverUrl(image);
+    if (failoverUrl) {
+      return failoverUrl;
+    }
+
+    const originalUrl = this.getPrimaryImageUrl(image);
     const configStore = useConfigStore();
     const customDomain = configStore.cosConfig?.Domain;
     
@@ -427,7 +750,7 @@ export class ImageService {
     try {
       const offset = (page - 1) * pageSize;
       const sql = `
-        SELECT * FROM imsheet 
+        SELECT ${IMAGE_COLUMNS} FROM imsheet 
         WHERE image_state = ? AND image_name LIKE ?
         ORDER BY create_time DESC 
         LIMIT ? OFFSET ?
@@ -508,15 +831,16 @@ export class ImageService {
   }
 
   // 保存图片到数据库
-  private async saveImageToDatabase(imageInfo: ImageInfo): Promise<boolean> {
+  private async saveImageToDatabase(imageInfo: ImageInfo, mirrors: MirrorUpload[] = []): Promise<boolean> {
     try {
       // 1. 先同步云端数据库
       await dbSyncService.syncFromCloud();
       
       // 2. 插入图片记录
       const sql = `
-        INSERT INTO imsheet (image_name, image_location, image_path, image_size, image_state, create_time)
-        VALUES (?, ?, ?, ?, ?, ?)
+        INSERT INTO imsheet (image_name, image_location, image_path, image_size, image_state, create_time,
+          image_width, image_height, image_mime, image_hash, original_name, source_device, image_dhash, image_phash)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
       `;
       
       await sqliteService.run(sql, [
@@ -525,13 +849,54 @@ export class ImageService {
         imageInfo.image_path,
         imageInfo.image_size,
         imageInfo.image_state,
-        imageInfo.create_time
+        imageInfo.create_time,
+        imageInfo.image_width ?? null,
+        imageInfo.image_height ?? null,
+        imageInfo.image_mime ?? null,
+        imageInfo.image_hash ?? null,
+        imageInfo.original_name ?? null,
+        imageInfo.source_device ?? null,
+        imageInfo.image_dhash ?? null,
+        imageInfo.image_phash ?? null
       ]);
       
-      // 3. 更新统计信息
+      // 3. 记录镜像链接，上传失败的镜像不记录
+      const mirrorRecords: Array<{ target: string; image_location: string; etag?: string | null }> = [];
+      for (const mirror of mirrors) {
+        if (!mirror.url) {
+          mConsole.error(`镜像 ${mirror.name} 上传失败:`, mirror.error);
+          continue;
+        }
+        await sqliteService.run(`
+          INSERT OR REPLACE INTO imsheet_mirror (image_path, target, image_location, etag, create_time)
+          VALUES (?, ?, ?, ?, ?)
+        `, [imageInfo.image_path, mirror.name, mirror.url, mirror.etag ?? null, imageInfo.create_time]);
+        mirrorRecords.push({ target: mirror.name, image_location: mirror.url, etag: mirror.etag ?? null });
+      }
+      await this.recordOperation({
+        type: 'insert',
+        image: {
+          image_name: imageInfo.image_name,
+          image_location: imageInfo.image_location,
+          image_path: imageInfo.image_path,
+          image_size: imageInfo.image_size,
+          create_time: imageInfo.create_time,
+          image_width: imageInfo.image_width,
+          image_height: imageInfo.image_height,
+          image_mime: imageInfo.image_mime,
+          image_hash: imageInfo.image_hash,
+          original_name: imageInfo.original_name,
+          source_device: imageInfo.source_device,
+          image_dhash: imageInfo.image_dhash,
+          image_phash: imageInfo.image_phash
+        },
+        mirrors: mirrorRecords
+      });
+
+      // 4. 更新统计信息
       await this.updateStatistics(imageInfo.image_size, 1);
       
-      // 4. 同步数据库到云端
+      // 5. 同步数据库到云端
       await dbSyncService.syncToCloud();
       
       return true;
@@ -541,6 +906,30 @@ export class ImageService {
     }
   }
 
+  // 其他设备持有同步锁时本机只读：拒绝修改图库，否则本地修改在租约过期前都无法推送
+  private ensureWritable(): void {
+    const lease = dbSyncService.readOnlyLease;
+    if (lease) {
+      throw new CosError(`设备 ${lease.device_id} 正在同步，本机暂时只读`, 'READ_ONLY');
+    }
+  }
+
+  // 将已写入本地数据库的修改追加到操作日志，失败时只记录错误，不影响本地修改
+  private async recordOperation(op: LibraryOperation): Promise<void> {
+    try {
+      await tauriCosService.recordOperation(op);
+    } catch (error) {
+      mConsole.error('记录图库操作失败:', error);
+    }
+  }
+
+  private async recordOperationById(imageId: number, build: (imagePath: string) => LibraryOperation): Promise<void> {
+    const image = await sqliteService.get('SELECT image_path FROM imsheet WHERE id = ?', [imageId]);
+    if (image) {
+      await this.recordOperation(build(image.image_path));
+    }
+  }
+
   // 更新统计信息（与原electron项目的updateImagesDB逻辑一致）
   private async updateStatistics(sizeChange: number, countChange: number): Promise<void> {
     try {
@@ -1,5 +1,6 @@
 // 使用 Tauri SQL 插件实现 SQLite 服务
 import Database from '@tauri-apps/plugin-sql';
+import { invoke } from '@tauri-apps/api/core';
 import { mConsole } from '../main';
 import { appDataDir } from '@tauri-apps/api/path';
 import { readFile, writeFile } from '@tauri-apps/plugin-fs';
@@ -28,8 +29,8 @@ export class SqliteService {
             // 使用 Tauri SQL 插件加载数据库
             this.db = await Database.load('sqlite:imsheet.db');
             
-            // 创建表结构
-            await this.createTables();
+            // 创建或迁移表结构
+            await this.migrateSchema();
             mConsole.log('SQLite service initialized successfully with Tauri SQL plugin');
         } catch (error) {
             mConsole.error("Failed to initialize SQLite database:", error);
@@ -37,49 +38,24 @@ export class SqliteService {
         }
     }
 
-    private async createTables(): Promise<void> {
-        if (!this.db) return;
-
+    // 由 Rust 端按 user_version 执行未完成的结构迁移，数据库版本高于应用支持的版本时抛出错误
+    private async migrateSchema(): Promise<void> {
         try {
-            // 创建图片表
-            await this.db.execute(`
-                CREATE TABLE IF NOT EXISTS imsheet(
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    image_name TEXT NOT NULL,
-                    image_location TEXT NOT NULL,
-                    image_path TEXT NOT NULL UNIQUE,
-                    image_size INTEGER NOT NULL,
-                    image_state INTEGER NOT NULL,
-                    create_time INTEGER NOT NULL
-                )
-            `);
-
-            // 创建统计表
-            await this.db.execute(`
-                CREATE TABLE IF NOT EXISTS imsheet_statistical(
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    size INTEGER NOT NULL,
-                    quantity INTEGER NOT NULL,
-                    last_hash VARCHAR(255) NOT NULL
-                )
-            `);
-
-            // 检查统计表中是否有数据，如果没有，初始化一条记录
-            const stats = await this.db.select<Array<{ id: number }>>("SELECT * FROM imsheet_statistical");
-            if (!stats.length) {
-                await this.db.execute(`
-                    INSERT INTO imsheet_statistical (size, quantity, last_hash)
-                    VALUES (?, ?, ?)
-                `, [0, 0, 'null']);
+            const version = await invoke<{ previous: number; current: number }>('db_migrate');
+            if (version.previous !== version.current) {
+                mConsole.log(`Database schema migrated from version ${version.previous} to ${version.current}`);
             }
-
-            mConsole.log('Database tables created successfully');
         } catch (error) {
-            mConsole.error('Error creating tables:', error);
+            mConsole.error('Error migrating database schema:', error);
             throw error;
         }
     }
 
+    // 检查数据库文件能否被当前版本打开（载入云端数据库前调用），返回其结构版本
+    public async checkSchema(path: string): Promise<number> {
+        return await invoke<number>('db_check_schema', { path });
+    }
+
     // 从二进制数据加载数据库（用于云端同步）
     public async loadFromBinary(data: Uint8Array): Promise<void> {
         try {
@@ -98,6 +74,9 @@ export class SqliteService {
             
             // 重新加载数据库
             this.db = await Database.load('sqlite:imsheet.db');
+
+            // 旧版本同步的数据库需要迁移到当前结构
+            await this.migrateSchema();
             
             mConsole.log('Database loaded from binary data');
         } catch (error) {
@@ -294,9 +273,13 @@ export class SqliteService {
             // 删除所有现有数据
             await this.db.execute('DROP TABLE IF EXISTS imsheet');
             await this.db.execute('DROP TABLE IF EXISTS imsheet_statistical');
+            await this.db.execute('DROP TABLE IF EXISTS imsheet_mirror');
+            await this.db.execute('DROP TABLE IF EXISTS imsheet_oplog');
+            await this.db.execute('DROP TABLE IF EXISTS imsheet_oplog_cursor');
+            await this.db.execute('PRAGMA user_version = 0');
             
             // 重新创建表结构
-            await this.createTables();
+            await this.migrateSchema();
             
             mConsole.log('✅ 全新数据库创建完成');
         } catch (error) {
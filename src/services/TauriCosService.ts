@@ -1,10 +1,17 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { mConsole } from '../main';
 import { appDataDir, join } from '@tauri-apps/api/path';
 import { mkdir, readFile, remove, writeFile } from '@tauri-apps/plugin-fs';
 
 // 导入类型定义
+// 存储服务商，未设置时为腾讯云 COS
+export type StorageProvider = 'cos' | 's3' | 'oss' | 'webdav' | 'sftp' | 'local' | 'github' | 'gitea';
+
 export interface CosConfig {
+  Provider?: StorageProvider;
+  // 操作日志签名密钥，各设备需一致；只在创建客户端时传给 Rust 端，未设置时不记录操作日志
+  SyncSecret?: string;
   APPID: string;
   SecretId: string;
   SecretKey: string;
@@ -12,16 +19,167 @@ export interface CosConfig {
   Region: string;
   Domain?: string;
   Dir?: string;
+  Network?: NetworkOptions;
+  // 镜像目标：上传时同时写入，主存储不可用时图片链接切换到镜像
+  Mirrors?: MirrorConfig[];
+}
+
+// 镜像目标配置：Name 用于在数据库中标识镜像，其余字段与对应服务商的配置相同
+export interface MirrorConfig {
+  Name: string;
+  Provider?: StorageProvider;
+  [field: string]: unknown;
+}
+
+// S3 兼容存储配置（AWS S3、MinIO 等），Provider 为 's3'
+export interface S3Config {
+  Provider: 's3';
+  Endpoint?: string;
+  Region?: string;
+  Bucket: string;
+  AccessKeyId: string;
+  SecretAccessKey: string;
+  SessionToken?: string;
+  // MinIO 等自建服务通常需要开启路径风格地址
+  ForcePathStyle?: boolean;
+  Domain?: string;
+  Dir?: string;
+  Network?: NetworkOptions;
+}
+
+// 阿里云 OSS 配置，Provider 为 'oss'
+export interface OssConfig {
+  Provider: 'oss';
+  AccessKeyId: string;
+  AccessKeySecret: string;
+  Bucket: string;
+  // 地域，如 oss-cn-hangzhou
+  Region: string;
+  Endpoint?: string;
+  Domain?: string;
+  Dir?: string;
+  // 图片样式名，生成链接时附加 x-oss-process=style/<name>
+  ImageStyle?: string;
+  Network?: NetworkOptions;
+}
+
+// WebDAV 配置（Nextcloud、nginx-dav 等），Provider 为 'webdav'
+export interface WebDavConfig {
+  Provider: 'webdav';
+  // WebDAV 根地址，如 https://cloud.example.com/remote.php/dav/files/alice
+  Url: string;
+  Username?: string;
+  Password?: string;
+  // 对外访问的基础地址，生成图片链接时使用
+  PublicUrl?: string;
+  Dir?: string;
+  // 服务端在 PUT 时校验 If-Match / If-None-Match（Nextcloud、Apache mod_dav），nginx dav 不支持；未开启时无法多设备同步
+  ConditionalPut?: boolean;
+  Network?: NetworkOptions;
+}
+
+// SFTP 配置（自有 VPS + Web 服务器），Provider 为 'sftp'
+export interface SftpConfig {
+  Provider: 'sftp';
+  Host: string;
+  Port?: number;
+  Username: string;
+  // 私钥路径，未设置时依次尝试 Password 和 ssh-agent
+  PrivateKeyPath?: string;
+  Passphrase?: string;
+  Password?: string;
+  // 主机必须已在 known_hosts 中，默认 ~/.ssh/known_hosts
+  KnownHostsPath?: string;
+  // 远程根目录，如 /var/www/images
+  RemoteDir: string;
+  // 远程根目录对应的访问地址，如 https://img.example.com
+  UrlPrefix: string;
+  Dir?: string;
+  Network?: NetworkOptions;
+}
+
+// 本地目录配置（离线使用、暂存与测试），Provider 为 'local'
+export interface LocalConfig {
+  Provider: 'local';
+  // 存储根目录，对象按 Dir/key 的结构写入
+  Root: string;
+  // 根目录对应的 HTTP 地址，未设置时生成 file:// 链接
+  UrlPrefix?: string;
+  Dir?: string;
+}
+
+// Git 仓库配置（GitHub / Gitea contents API），Provider 为 'github' 或 'gitea'
+export interface GitRepoConfig {
+  Provider: 'github' | 'gitea';
+  // 站点根地址：GitHub 默认 https://github.com，Gitea 必填，如 http://localhost:3000
+  ServerUrl?: string;
+  Owner: string;
+  Repo: string;
+  // 默认 main
+  Branch?: string;
+  Token: string;
+  // 仓库内的存放目录，如 images
+  Path?: string;
+  // 链接模板，支持 {owner} {repo} {branch} {path}，如 https://cdn.jsdelivr.net/gh/{owner}/{repo}@{branch}/{path}
+  UrlTemplate?: string;
+  Dir?: string;
+  Network?: NetworkOptions;
+}
+
+// 超时与重试策略，未设置的字段使用 Rust 端默认值
+export interface NetworkOptions {
+  connect_timeout_secs?: number;
+  read_timeout_secs?: number;
+  max_retries?: number;
+  base_delay_ms?: number;
+  max_delay_ms?: number;
 }
 
 export interface UploadOptions {
   callback?: string;
   headers?: CosHeaders;
+  multipart?: MultipartOptions;
+  // 同时上传的镜像目标
+  mirrors?: MirrorTarget[];
+  // 用户选择的原始文件名，记录到图片信息
+  original_name?: string;
+  // 图库中已有相同内容的图片时仍然上传
+  force?: boolean;
+}
+
+// 条件写入：if_match 为期望的当前 ETag，if_none_match 为 '*' 时仅在对象不存在时写入
+export interface WriteCondition {
+  if_match?: string;
+  if_none_match?: string;
+}
+
+// Rust 端客户端池中的镜像客户端
+export interface MirrorTarget {
+  name: string;
+  client_id: string;
+}
+
+// 单个镜像的上传结果，失败时 error 不为空
+export interface MirrorUpload {
+  name: string;
+  provider: StorageProvider;
+  url?: string;
+  etag?: string;
+  error?: CosErrorPayload;
+}
+
+// 分块上传选项：文件大小超过 threshold 时分块并行上传，中断后可续传
+export interface MultipartOptions {
+  threshold?: number;
+  part_size?: number;
+  concurrency?: number;
 }
 
 export interface CosHeaders {
   content_type?: string;
   pic_operations?: string;
+  // OSS 图片处理参数，处理结果覆盖原文件
+  image_process?: string;
 }
 
 export interface UploadResult {
@@ -30,18 +188,56 @@ export interface UploadResult {
   url: string;
   etag?: string;
   size: number;
+  mirrors?: MirrorUpload[];
+  // 图片上传命令返回的图片信息
+  metadata?: ImageMetadata;
+  // 图库中已有相同内容的图片时未上传，key 和 url 指向该图片
+  duplicate?: ExistingImage | null;
+}
+
+// 图库中已有的图片记录
+export interface ExistingImage {
+  id: number;
+  image_name: string;
+  image_location: string;
+  image_path: string;
+  image_size: number;
+  image_state: number;
+  create_time: number;
+}
+
+// 上传前从原始文件读取的图片信息（与 src-tauri/src/metadata.rs 对应）
+export interface ImageMetadata {
+  width?: number | null;
+  height?: number | null;
+  mime_type?: string | null;
+  // 原始文件的 SHA-256
+  content_hash: string;
+  // 感知哈希（16 位十六进制），无法解码的图片为空
+  dhash?: string | null;
+  phash?: string | null;
+  original_name?: string | null;
+  source_device: string;
 }
 
 export interface DownloadResult {
   success: boolean;
   file_path: string;
   size: number;
+  // 下载内容对应的 ETag，与 head 返回的格式一致
+  etag?: string | null;
 }
 
 export interface DeleteResult {
   success: boolean;
   deleted_count: number;
-  failed_keys: string[];
+  failed_keys: DeleteError[];
+}
+
+export interface DeleteError {
+  key: string;
+  code: string;
+  message: string;
 }
 
 export interface ObjectMetadata {
@@ -54,6 +250,7 @@ export interface ObjectMetadata {
 
 export interface ListObjectsResult {
   objects: ObjectInfo[];
+  common_prefixes: string[];
   is_truncated: boolean;
   next_marker?: string;
 }
@@ -70,37 +267,296 @@ export interface UploadProgress {
   percent: number;
   loaded: number;
   total: number;
+  speed?: number;
   stage: 'preparing' | 'uploading' | 'completed' | 'error';
 }
 
+// Rust 端推送的传输进度（cos-transfer-progress 事件）
+export interface TransferProgress {
+  task_id: string;
+  transferred: number;
+  total: number;
+  speed: number;
+}
+
+const TRANSFER_PROGRESS_EVENT = 'cos-transfer-progress';
+
+// 图库迁移选项
+export interface MigrationOptions {
+  concurrency?: number;
+  // 只复制和校验对象，不改写数据库
+  dry_run?: boolean;
+}
+
+// Rust 端推送的迁移进度（library-migration-progress 事件）
+export interface MigrationProgress {
+  task_id?: string;
+  processed: number;
+  total: number;
+  image_path: string;
+}
+
+export interface MigrationFailure {
+  id: number;
+  image_path: string;
+  error: CosErrorPayload;
+}
+
+export interface OplogMigrationFailure {
+  key: string;
+  error: CosErrorPayload;
+}
+
+// 迁移报告，report_path 为本地保存的 JSON 报告
+export interface MigrationReport {
+  total: number;
+  migrated: number;
+  skipped: number;
+  failed: MigrationFailure[];
+  // 复制到目标存储的操作日志数
+  oplog_copied: number;
+  oplog_failed: OplogMigrationFailure[];
+  report_path?: string;
+}
+
+const MIGRATION_PROGRESS_EVENT = 'library-migration-progress';
+
+export interface RebuildOptions {
+  concurrency?: number;
+  // 只扫描存储桶并生成报告，不写入数据库
+  dry_run?: boolean;
+}
+
+// Rust 端推送的重建进度（library-rebuild-progress 事件）
+export interface RebuildProgress {
+  task_id?: string;
+  processed: number;
+  total: number;
+  key: string;
+}
+
+export interface RebuildFailure {
+  key: string;
+  error: CosErrorPayload;
+}
+
+// 重建报告，size 和 quantity 为重建后的统计值
+export interface RebuildReport {
+  scanned: number;
+  inserted: number;
+  updated: number;
+  ignored: number;
+  failed: RebuildFailure[];
+  size: number;
+  quantity: number;
+}
+
+const REBUILD_PROGRESS_EVENT = 'library-rebuild-progress';
+
+// 图库操作记录，各设备追加到存储中的操作日志并互相回放
+export type LibraryOperation =
+  | {
+      type: 'insert';
+      image: {
+        image_name: string;
+        image_location: string;
+        image_path: string;
+        image_size: number;
+        create_time: number;
+        image_width?: number | null;
+        image_height?: number | null;
+        image_mime?: string | null;
+        image_hash?: string | null;
+        original_name?: string | null;
+        source_device?: string | null;
+        image_dhash?: string | null;
+        image_phash?: string | null;
+      };
+      mirrors?: Array<{ target: string; image_location: string; etag?: string | null }>;
+    }
+  // time 为新的 create_time
+  | { type: 'recycle'; image_path: string; time: number }
+  | { type: 'restore'; image_path: string; time: number }
+  | { type: 'delete'; image_path: string };
+
+export interface OplogSyncReport {
+  uploaded: number;
+  // 上传失败、仍待上传的本机记录
+  pending: number;
+  applied: number;
+  // 早于同一图片已回放记录的操作，只保存不回放
+  superseded: number;
+  rejected: Array<{ key: string; reason: string }>;
+}
+
+// 存储中的同步锁租约，expires_at 为毫秒时间戳
+export interface SyncLease {
+  lease_id: string;
+  device_id: string;
+  acquired_at: number;
+  expires_at: number;
+}
+
+// 获取同步锁的结果，acquired 为 false 时 lease 为其他设备持有的租约
+export interface SyncLockStatus {
+  acquired: boolean;
+  lease?: SyncLease | null;
+}
+
+// 对账报告：孤立对象（存储中有、数据库中没有）、悬空记录（数据库中有、对象已不存在）和大小不一致的记录
+export interface ReconcileReport {
+  object_count: number;
+  row_count: number;
+  orphans: Array<{ key: string; size: number; last_modified: string }>;
+  dangling: Array<{ id: number; image_path: string; image_state: number }>;
+  size_mismatches: Array<{ id: number; image_path: string; recorded_size: number; object_size: number }>;
+}
+
+// 对账后执行的修复
+export type ReconcileFix =
+  | { action: 'import_orphans'; keys: string[] }
+  | { action: 'delete_orphans'; keys: string[] }
+  | { action: 'mark_dangling'; ids: number[] }
+  | { action: 'update_sizes'; ids: number[] };
+
+export interface ReconcileFixResult {
+  applied: number;
+  // 复查后已无需修复的条目
+  skipped: number;
+  failed: Array<{ target: string; error: CosErrorPayload }>;
+}
+
+// 查找重复图片的选项：hash_missing 为 true 时下载未记录哈希的图片补写哈希
+export interface DuplicateOptions {
+  hash_missing?: boolean;
+  concurrency?: number;
+}
+
+// 内容完全相同的图片分组，组内按上传时间排序
+export interface DuplicateReport {
+  groups: Array<{ content_hash: string; images: ExistingImage[] }>;
+  // 本次补写哈希的记录数
+  hashed: number;
+  // 仍没有哈希、未参与比较的记录数
+  unhashed: number;
+  failed: Array<{ id: number; image_path: string; error: CosErrorPayload }>;
+}
+
+// 查找相似图片的选项：threshold 为最大汉明距离（0 ~ 64，默认 10）
+export interface SimilarOptions {
+  algorithm?: 'dhash' | 'phash';
+  threshold?: number;
+  // 下载未记录感知哈希的图片并补写
+  hash_missing?: boolean;
+  concurrency?: number;
+}
+
+// 相似图片分组，组内按上传时间排序，distance 为与组内第一张图片的汉明距离
+export interface SimilarReport {
+  groups: Array<{ images: Array<ExistingImage & { distance: number }>; total_size: number }>;
+  hashed: number;
+  // 本次下载后无法解码、已标记不再补写的记录数
+  undecodable: number;
+  // 仍没有感知哈希（未补写或无法解码）、未参与比较的记录数
+  unhashed: number;
+  failed: Array<{ id: number; image_path: string; error: CosErrorPayload }>;
+}
+
 // 新增：上传选项类型
 export interface PushUploadOptions {
   onProgress?: (progress: UploadProgress) => void;
   contentType?: string;
   headers?: CosHeaders;
+  // 原始文件名，缺省时使用 File 的 name
+  originalName?: string;
+  // 图库中已有相同内容的图片时仍然上传
+  force?: boolean;
   webp?: {
     enabled: boolean;
     quality: number;
   };
 }
 
+// Rust 端返回的错误类型（与 src-tauri/src/error.rs 中的 CosError 对应）
+export type CosErrorKind =
+  | 'auth'
+  | 'not_found'
+  | 'throttled'
+  | 'conflict'
+  | 'service'
+  | 'network'
+  | 'io'
+  | 'parse'
+  | 'invalid'
+  | 'database';
+
+export interface CosErrorPayload {
+  kind: CosErrorKind;
+  message: string;
+  status?: number;
+  code?: string;
+  request_id?: string;
+  timeout?: boolean;
+}
+
+const isCosErrorPayload = (error: unknown): error is CosErrorPayload =>
+  typeof error === 'object' && error !== null && 'kind' in error && 'message' in error;
+
 // 新增：COS 错误类型
 export class CosError extends Error {
   constructor(
     message: string,
     public code?: string,
     public statusCode?: number,
-    public originalError?: unknown
+    public originalError?: unknown,
+    public kind?: CosErrorKind,
+    public requestId?: string
   ) {
     super(message);
     this.name = 'CosError';
   }
+
+  // 将 invoke 抛出的错误转换为 CosError，保留 Rust 端的错误类型和 COS 错误码
+  static from(error: unknown, context: string, fallbackCode: string = 'UNKNOWN'): CosError {
+    if (error instanceof CosError) {
+      return error;
+    }
+
+    if (isCosErrorPayload(error)) {
+      return new CosError(
+        `${context}: ${error.message}`,
+        error.code ?? fallbackCode,
+        error.status,
+        error,
+        error.kind,
+        error.request_id
+      );
+    }
+
+    return new CosError(
+      `${context}: ${error instanceof Error ? error.message : String(error)}`,
+      fallbackCode,
+      undefined,
+      error
+    );
+  }
+
+  // 网络错误、限流和服务端 5xx 错误可以重试
+  get retryable(): boolean {
+    return this.kind === 'network'
+      || this.kind === 'throttled'
+      || (this.kind === 'service' && (this.statusCode ?? 0) >= 500);
+  }
 }
 
 // Tauri COS 管理器
 export class TauriCosService {
   private static instance: TauriCosService;
   private config: CosConfig | null = null;
+  // Rust 端客户端池中的客户端 ID，密钥只在初始化时传递一次
+  private clientId: Promise<string> | null = null;
+  // 镜像名称 -> Rust 端客户端 ID
+  private mirrorClients = new Map<string, Promise<string>>();
   private readonly isDev = import.meta.env.DEV;
 
   private constructor() {}
@@ -144,26 +600,346 @@ export class TauriCosService {
     return JSON.stringify(picOperations);
   }
 
+  // 按服务商设置 WebP 处理参数：COS 使用 Pic-Operations，OSS 使用图片处理并覆盖原文件
+  private applyWebpHeaders(headers: CosHeaders, picOperations: string, quality: number): void {
+    if (this.config?.Provider === 'oss') {
+      headers.image_process = `image/format,webp/quality,q_${quality}`;
+    } else {
+      headers.pic_operations = picOperations;
+    }
+  }
+
+  // 生成传输任务 ID，用于匹配 Rust 端推送的进度事件
+  private createTaskId(key: string): string {
+    return `${key}_${Date.now()}_${Math.random().toString(36).slice(2, 8)}`;
+  }
+
+  // 监听指定任务的传输进度并转换为 UploadProgress
+  private async listenProgress(
+    taskId: string,
+    onProgress?: (progress: UploadProgress) => void
+  ): Promise<UnlistenFn | null> {
+    if (!onProgress) {
+      return null;
+    }
+
+    return await listen<TransferProgress>(TRANSFER_PROGRESS_EVENT, (event) => {
+      const { task_id, transferred, total, speed } = event.payload;
+      if (task_id !== taskId) {
+        return;
+      }
+
+      onProgress({
+        percent: total > 0 ? Math.min(100, (transferred / total) * 100) : 0,
+        loaded: transferred,
+        total,
+        speed,
+        stage: 'uploading'
+      });
+    });
+  }
+
   // 初始化 COS
   initialize(config: CosConfig): void {
     this.config = config;
+    this.clientId = null;
+    this.mirrorClients.clear();
+    this.ensureClient().catch(error => this.logError('COS 客户端创建失败', error));
     this.log('COS 服务初始化成功');
-    this.log('配置详情', { ...config, SecretKey: '***' }); // 隐藏敏感信息
+    this.log('配置详情', { ...config, SecretKey: '***', SyncSecret: '***' }); // 隐藏敏感信息
   }
 
   // 重置配置（用于清除可能的测试配置）
   resetConfig(): void {
+    const clientIds = [this.clientId, ...this.mirrorClients.values()];
     this.config = null;
+    this.clientId = null;
+    this.mirrorClients.clear();
+    for (const clientId of clientIds) {
+      clientId
+        ?.then(id => invoke('cos_release', { clientId: id }))
+        .catch(() => {});
+    }
     this.log('COS 配置已重置');
   }
 
+  // 获取 Rust 端客户端 ID，首次调用时创建客户端
+  private ensureClient(): Promise<string> {
+    if (!this.config) {
+      return Promise.reject(new CosError('COS 未初始化', 'NOT_INITIALIZED'));
+    }
+
+    if (!this.clientId) {
+      const pending = invoke<string>('cos_initialize', { config: this.config });
+      this.clientId = pending;
+      // 创建失败时清除缓存，下次调用重新创建
+      pending.catch(() => {
+        if (this.clientId === pending) {
+          this.clientId = null;
+        }
+      });
+    }
+    return this.clientId;
+  }
+
+  // 获取镜像目标的客户端 ID，创建失败的镜像本次跳过，不影响主存储上传
+  private async ensureMirrors(): Promise<MirrorTarget[]> {
+    const mirrors = this.config?.Mirrors ?? [];
+    const targets = await Promise.all(mirrors.map(async (mirror): Promise<MirrorTarget | null> => {
+      try {
+        return { name: mirror.Name, client_id: await this.ensureMirrorClient(mirror) };
+      } catch (error) {
+        this.logError(`镜像客户端创建失败: ${mirror.Name}`, error);
+        return null;
+      }
+    }));
+    return targets.filter((target): target is MirrorTarget => target !== null);
+  }
+
+  private ensureMirrorClient(mirror: MirrorConfig): Promise<string> {
+    const existing = this.mirrorClients.get(mirror.Name);
+    if (existing) {
+      return existing;
+    }
+
+    const { Name, ...config } = mirror;
+    const pending = invoke<string>('cos_initialize', { config });
+    this.mirrorClients.set(Name, pending);
+    pending.catch(() => {
+      if (this.mirrorClients.get(Name) === pending) {
+        this.mirrorClients.delete(Name);
+      }
+    });
+    return pending;
+  }
+
+  // 是否配置了镜像
+  hasMirrors(): boolean {
+    return (this.config?.Mirrors?.length ?? 0) > 0;
+  }
+
+  // 从所有镜像删除对象，失败只记录日志（镜像中的残留文件不影响图库）
+  async deleteFromMirrors(keys: string[]): Promise<void> {
+    if (keys.length === 0) {
+      return;
+    }
+
+    for (const mirror of await this.ensureMirrors()) {
+      try {
+        const result = await invoke<DeleteResult>('cos_delete_multiple', { keys, clientId: mirror.client_id });
+        if (!result.success) {
+          this.logError(`镜像 ${mirror.name} 部分文件删除失败`, result.failed_keys);
+        }
+      } catch (error) {
+        this.logError(`镜像 ${mirror.name} 删除失败`, error);
+      }
+    }
+  }
+
+  // 探测链接是否可访问，返回与 urls 一一对应的结果
+  async probeUrls(urls: string[], timeoutMs?: number): Promise<boolean[]> {
+    try {
+      return await invoke<boolean[]>('storage_probe_urls', { urls, timeoutMs });
+    } catch (error) {
+      this.logError('探测链接失败', error);
+      return urls.map(() => false);
+    }
+  }
+
+  // 将图库迁移到目标存储，数据库中的链接改写为目标存储的链接；中断后重新调用即可继续
+  async migrateLibrary(
+    targetConfig: CosConfig,
+    options?: MigrationOptions,
+    onProgress?: (progress: MigrationProgress) => void
+  ): Promise<MigrationReport> {
+    const taskId = this.createTaskId('migration');
+    const unlisten = onProgress
+      ? await listen<MigrationProgress>(MIGRATION_PROGRESS_EVENT, (event) => {
+          if (event.payload.task_id === taskId) {
+            onProgress(event.payload);
+          }
+        })
+      : null;
+
+    let targetClientId: string | null = null;
+    try {
+      targetClientId = await invoke<string>('cos_initialize', { config: targetConfig });
+      const report = await invoke<MigrationReport>('library_migrate', {
+        sourceClientId: await this.ensureClient(),
+        targetClientId,
+        options,
+        taskId
+      });
+      this.log('图库迁移完成', report);
+      return report;
+    } catch (error) {
+      this.logError('图库迁移失败', error);
+      throw CosError.from(error, '图库迁移失败', 'MIGRATION_FAILED');
+    } finally {
+      unlisten?.();
+      // 目标配置与当前配置相同时客户端仍在使用，不释放
+      const sourceClientId = await this.clientId?.catch(() => null);
+      if (targetClientId && targetClientId !== sourceClientId) {
+        invoke('cos_release', { clientId: targetClientId }).catch(() => {});
+      }
+    }
+  }
+
+  // 按存储桶中的对象重建本地数据库的图片表和统计表
+  async rebuildLibrary(
+    options?: RebuildOptions,
+    onProgress?: (progress: RebuildProgress) => void
+  ): Promise<RebuildReport> {
+    const taskId = this.createTaskId('rebuild');
+    const unlisten = onProgress
+      ? await listen<RebuildProgress>(REBUILD_PROGRESS_EVENT, (event) => {
+          if (event.payload.task_id === taskId) {
+            onProgress(event.payload);
+          }
+        })
+      : null;
+
+    try {
+      const report = await invoke<RebuildReport>('library_rebuild', {
+        clientId: await this.ensureClient(),
+        options,
+        taskId
+      });
+      this.log('图库重建完成', report);
+      return report;
+    } catch (error) {
+      this.logError('图库重建失败', error);
+      throw CosError.from(error, '图库重建失败', 'REBUILD_FAILED');
+    } finally {
+      unlisten?.();
+    }
+  }
+
+  // 记录本机对图库的修改，返回是否已上传；未上传的记录在下次同步时重传
+  async recordOperation(op: LibraryOperation): Promise<boolean> {
+    try {
+      return await invoke<boolean>('oplog_record', {
+        clientId: await this.ensureClient(),
+        op
+      });
+    } catch (error) {
+      this.logError('记录图库操作失败', error);
+      throw CosError.from(error, '记录图库操作失败', 'OPLOG_RECORD_FAILED');
+    }
+  }
+
+  // 上传本机待上传的操作记录，并回放其他设备的新记录
+  async syncOperations(): Promise<OplogSyncReport> {
+    try {
+      const report = await invoke<OplogSyncReport>('oplog_sync', {
+        clientId: await this.ensureClient()
+      });
+      this.log('操作日志同步完成', report);
+      return report;
+    } catch (error) {
+      this.logError('操作日志同步失败', error);
+      throw CosError.from(error, '操作日志同步失败', 'OPLOG_SYNC_FAILED');
+    }
+  }
+
+  // 获取同步锁，持有期间 Rust 端自动续期
+  async acquireSyncLock(ttlMs?: number): Promise<SyncLockStatus> {
+    try {
+      return await invoke<SyncLockStatus>('sync_lock_acquire', {
+        clientId: await this.ensureClient(),
+        ttlMs
+      });
+    } catch (error) {
+      this.logError('获取同步锁失败', error);
+      throw CosError.from(error, '获取同步锁失败', 'LOCK_FAILED');
+    }
+  }
+
+  // 释放本机持有的同步锁
+  async releaseSyncLock(): Promise<void> {
+    try {
+      await invoke('sync_lock_release', { clientId: await this.ensureClient() });
+    } catch (error) {
+      this.logError('释放同步锁失败', error);
+    }
+  }
+
+  // 当前持有同步锁的设备，没有时返回 null
+  async getSyncLock(): Promise<SyncLease | null> {
+    try {
+      return await invoke<SyncLease | null>('sync_lock_status', { clientId: await this.ensureClient() });
+    } catch (error) {
+      this.logError('读取同步锁失败', error);
+      return null;
+    }
+  }
+
+  // 对比本地数据库与存储中的对象
+  async reconcileLibrary(): Promise<ReconcileReport> {
+    try {
+      const report = await invoke<ReconcileReport>('library_reconcile', {
+        clientId: await this.ensureClient()
+      });
+      this.log('图库对账完成', report);
+      return report;
+    } catch (error) {
+      this.logError('图库对账失败', error);
+      throw CosError.from(error, '图库对账失败', 'RECONCILE_FAILED');
+    }
+  }
+
+  // 执行对账报告中的修复
+  async applyReconcileFix(fix: ReconcileFix): Promise<ReconcileFixResult> {
+    try {
+      const result = await invoke<ReconcileFixResult>('library_reconcile_fix', {
+        clientId: await this.ensureClient(),
+        fix
+      });
+      this.log('对账修复完成', result);
+      return result;
+    } catch (error) {
+      this.logError('对账修复失败', error);
+      throw CosError.from(error, '对账修复失败', 'RECONCILE_FIX_FAILED');
+    }
+  }
+
+  // 查找内容完全相同的图片
+  async findDuplicates(options?: DuplicateOptions): Promise<DuplicateReport> {
+    try {
+      const report = await invoke<DuplicateReport>('library_find_duplicates', {
+        clientId: await this.ensureClient(),
+        options
+      });
+      this.log('查找重复图片完成', { groups: report.groups.length, hashed: report.hashed, unhashed: report.unhashed });
+      return report;
+    } catch (error) {
+      this.logError('查找重复图片失败', error);
+      throw CosError.from(error, '查找重复图片失败', 'FIND_DUPLICATES_FAILED');
+    }
+  }
+
+  // 按感知哈希查找相似图片
+  async findSimilar(options?: SimilarOptions): Promise<SimilarReport> {
+    try {
+      const report = await invoke<SimilarReport>('library_find_similar', {
+        clientId: await this.ensureClient(),
+        options
+      });
+      this.log('查找相似图片完成', { groups: report.groups.length, hashed: report.hashed, unhashed: report.unhashed });
+      return report;
+    } catch (error) {
+      this.logError('查找相似图片失败', error);
+      throw CosError.from(error, '查找相似图片失败', 'FIND_SIMILAR_FAILED');
+    }
+  }
+
   // 获取配置
   getConfig(): CosConfig | null {
     return this.config;
   }
 
   // 上传文件
-  async upload(filePath: string, key: string, options?: UploadOptions): Promise<UploadResult> {
+  async upload(filePath: string, key: string, options?: UploadOptions, taskId?: string): Promise<UploadResult> {
     if (!this.config) {
       throw new CosError('COS 未初始化', 'NOT_INITIALIZED');
     }
@@ -173,13 +949,35 @@ export class TauriCosService {
         filePath,
         key,
         options,
-        config: this.config
+        taskId,
+        clientId: await this.ensureClient()
       });
       mConsole.log('上传结果:', result);
       return result;
     } catch (error) {
       this.logError('上传失败', error);
-      throw new CosError(`上传失败: ${error}`, 'UPLOAD_FAILED', undefined, error);
+      throw CosError.from(error, '上传失败', 'UPLOAD_FAILED');
+    }
+  }
+
+  // 条件上传，前置条件不满足时抛出 kind 为 conflict 的 CosError，云端对象保持不变
+  async uploadConditional(filePath: string, key: string, condition: WriteCondition): Promise<UploadResult> {
+    if (!this.config) {
+      throw new CosError('COS 未初始化', 'NOT_INITIALIZED');
+    }
+
+    try {
+      const result = await invoke<UploadResult>('cos_upload_conditional', {
+        filePath,
+        key,
+        condition,
+        clientId: await this.ensureClient()
+      });
+      mConsole.log('条件上传结果:', result);
+      return result;
+    } catch (error) {
+      this.logError('条件上传失败', error);
+      throw CosError.from(error, '条件上传失败', 'UPLOAD_FAILED');
     }
   }
 
@@ -193,11 +991,11 @@ export class TauriCosService {
       const result = await invoke<DownloadResult>('cos_download', {
         key,
         savePath,
-        config: this.config
+        clientId: await this.ensureClient()
       });
       return result;
     } catch (error) {
-      throw new Error(`下载失败: ${error}`);
+      throw CosError.from(error, '下载失败');
     }
   }
 
@@ -210,11 +1008,11 @@ export class TauriCosService {
     try {
       const result = await invoke<ObjectMetadata>('cos_head_object', {
         key,
-        config: this.config
+        clientId: await this.ensureClient()
       });
       return result;
     } catch (error) {
-      throw new Error(`检查对象失败: ${error}`);
+      throw CosError.from(error, '检查对象失败');
     }
   }
 
@@ -225,15 +1023,7 @@ export class TauriCosService {
     }
 
     // 预处理 keys - 确保使用正确的路径格式
-    const processedKeys = keys.map(key => {
-      // 如果 key 已经包含完整路径信息，直接使用
-      if (key.includes('/')) {
-        return key;
-      }
-      
-      // 否则使用 getFullKey 构建完整路径
-      return this.getFullKey(key);
-    });
+    const processedKeys = keys.map(key => this.resolveObjectKey(key));
 
     mConsole.log('删除操作 - 原始keys:', keys);
     mConsole.log('删除操作 - 处理后keys:', processedKeys);
@@ -241,17 +1031,28 @@ export class TauriCosService {
     try {
       const result = await invoke<DeleteResult>('cos_delete_multiple', {
         keys: processedKeys,
-        config: this.config
+        clientId: await this.ensureClient()
       });
       
       mConsole.log('删除结果:', result);
       return result;
     } catch (error) {
       mConsole.error('批量删除失败:', error);
-      throw new Error(`删除失败: ${error}`);
+      throw CosError.from(error, '删除失败');
     }
   }
 
+  // 将数据库中的 image_path 转换为 COS 对象键（与 failed_keys 中的 key 一致）
+  resolveObjectKey(key: string): string {
+    // 如果 key 已经包含完整路径信息，直接使用
+    if (key.includes('/')) {
+      return key;
+    }
+    
+    // 否则使用 getFullKey 构建完整路径
+    return this.getFullKey(key);
+  }
+
   // 单个文件删除方法
   async deleteObject(key: string): Promise<boolean> {
     try {
@@ -263,8 +1064,13 @@ export class TauriCosService {
     }
   }
 
-  // 列出对象
-  async listObjects(prefix?: string, maxKeys?: number): Promise<ListObjectsResult> {
+  // 列出对象（marker 用于翻页，delimiter 为 '/' 时返回子目录）
+  async listObjects(
+    prefix?: string,
+    maxKeys?: number,
+    marker?: string,
+    delimiter?: string
+  ): Promise<ListObjectsResult> {
     if (!this.config) {
       throw new Error('COS 未初始化');
     }
@@ -273,11 +1079,13 @@ export class TauriCosService {
       const result = await invoke<ListObjectsResult>('cos_list_objects', {
         prefix,
         maxKeys,
-        config: this.config
+        marker,
+        delimiter,
+        clientId: await this.ensureClient()
       });
       return result;
     } catch (error) {
-      throw new Error(`列出对象失败: ${error}`);
+      throw CosError.from(error, '列出对象失败');
     }
   }
 
@@ -290,11 +1098,11 @@ export class TauriCosService {
     try {
       const url = await invoke<string>('cos_get_object_url', {
         key,
-        config: this.config
+        clientId: await this.ensureClient()
       });
       return url;
     } catch (error) {
-      throw new Error(`获取对象 URL 失败: ${error}`);
+      throw CosError.from(error, '获取对象 URL 失败');
     }
   }
 
@@ -311,6 +1119,9 @@ export class TauriCosService {
     Location: string;
     Key: string;
     size: number;
+    Mirrors: MirrorUpload[];
+    Metadata?: ImageMetadata;
+    Duplicate?: ExistingImage;
   }> {
     this.log('开始上传', { key, fileSize: file.size });
     
@@ -327,23 +1138,23 @@ export class TauriCosService {
     });
     
     let tempFilePath: string | null = null;
+    const taskId = this.createTaskId(key);
+    let unlisten: UnlistenFn | null = null;
     
     try {
       // 保存到临时文件
       tempFilePath = await this.saveFileToTemp(file, key);
       
-      // 发送上传开始进度
-      onProgress?.({
-        percent: 10,
-        loaded: 0,
-        total: file.size,
-        stage: 'uploading'
-      });
+      // 订阅 Rust 端推送的真实上传进度
+      unlisten = await this.listenProgress(taskId, onProgress);
       
       // 准备上传选项
       const options: UploadOptions = {
         callback: onProgress ? 'progress' : undefined,
-        headers: uploadOptions?.headers
+        headers: uploadOptions?.headers,
+        mirrors: await this.ensureMirrors(),
+        original_name: uploadOptions?.originalName ?? (file instanceof File ? file.name : undefined),
+        force: uploadOptions?.force
       };
 
       // 如果启用了WebP转换，构建万象云处理头部
@@ -354,12 +1165,12 @@ export class TauriCosService {
         if (!options.headers) {
           options.headers = {};
         }
-        options.headers.pic_operations = picOperations;
+        this.applyWebpHeaders(options.headers, picOperations, quality);
         
         this.log('启用万象云WebP处理', { key, quality, picOperations });
       }
       
-      const result = await this.upload(tempFilePath, key, options);
+      const result = await this.upload(tempFilePath, key, options, taskId);
       
       // 发送上传完成进度
       onProgress?.({
@@ -378,7 +1189,10 @@ export class TauriCosService {
         ETag: result.etag,
         Location: fullUrl,
         Key: result.key,
-        size: result.size
+        size: result.size,
+        Mirrors: result.mirrors ?? [],
+        Metadata: result.metadata,
+        Duplicate: result.duplicate ?? undefined
       };
     } catch (error) {
       this.logError('上传过程失败', error);
@@ -391,17 +1205,10 @@ export class TauriCosService {
         stage: 'error'
       });
       
-      if (error instanceof CosError) {
-        throw error;
-      }
-      
-      throw new CosError(
-        `上传失败: ${error instanceof Error ? error.message : String(error)}`,
-        'UPLOAD_FAILED',
-        undefined,
-        error
-      );
+      throw CosError.from(error, '上传失败', 'UPLOAD_FAILED');
     } finally {
+      unlisten?.();
+      
       // 清理临时文件
       if (tempFilePath) {
         await this.cleanupTempFile(tempFilePath);
@@ -527,14 +1334,89 @@ export class TauriCosService {
       // 移除末尾的斜杠
       domain = domain.replace(/\/+$/, '');
       url = `${domain}/${fullKey}`;
+    } else if (this.config.Provider === 's3') {
+      url = this.getS3ObjectUrl(this.config as unknown as S3Config, fullKey);
+    } else if (this.config.Provider === 'oss') {
+      url = this.getOssObjectUrl(this.config as unknown as OssConfig, fullKey);
+    } else if (this.config.Provider === 'webdav') {
+      const webdav = this.config as unknown as WebDavConfig;
+      url = webdav.PublicUrl
+        ? `${webdav.PublicUrl.replace(/\/+$/, '')}/${fullKey}`
+        : `${webdav.Url.replace(/\/+$/, '')}/${fullKey.split('/').map(encodeURIComponent).join('/')}`;
+    } else if (this.config.Provider === 'sftp') {
+      const sftp = this.config as unknown as SftpConfig;
+      url = `${sftp.UrlPrefix.replace(/\/+$/, '')}/${fullKey}`;
+    } else if (this.config.Provider === 'local') {
+      url = this.getLocalObjectUrl(this.config as unknown as LocalConfig, fullKey);
+    } else if (this.config.Provider === 'github' || this.config.Provider === 'gitea') {
+      url = this.getGitObjectUrl(this.config as unknown as GitRepoConfig, fullKey);
     } else {
       url = `https://${this.config.Bucket}.cos.${this.config.Region}.myqcloud.com/${fullKey}`;
     }
 
+    // OSS 图片样式对自定义域名同样生效
+    const ossStyle = this.config.Provider === 'oss' ? (this.config as unknown as OssConfig).ImageStyle : undefined;
+    if (ossStyle) {
+      url = `${url}?x-oss-process=style/${ossStyle}`;
+    }
+
     this.log('生成对象 URL', { key, fullKey, url });
     return url;
   }
 
+  // S3 对象地址，与 Rust 端 S3Client::object_url 保持一致
+  private getS3ObjectUrl(config: S3Config, fullKey: string): string {
+    const endpoint = new URL(config.Endpoint || `https://s3.${config.Region || 'us-east-1'}.amazonaws.com`);
+    const path = fullKey.split('/').map(encodeURIComponent).join('/');
+    if (config.ForcePathStyle) {
+      return `${endpoint.protocol}//${endpoint.host}/${config.Bucket}/${path}`;
+    }
+    return `${endpoint.protocol}//${config.Bucket}.${endpoint.host}/${path}`;
+  }
+
+  // OSS 对象地址，与 Rust 端 OssClient::object_url 保持一致
+  private getOssObjectUrl(config: OssConfig, fullKey: string): string {
+    const endpoint = config.Endpoint
+      ? config.Endpoint.replace(/^https?:\/\//, '').replace(/\/+$/, '')
+      : `${config.Region.startsWith('oss-') ? config.Region : `oss-${config.Region}`}.aliyuncs.com`;
+    return `https://${config.Bucket}.${endpoint}/${fullKey.split('/').map(encodeURIComponent).join('/')}`;
+  }
+
+  // Git 仓库链接：优先使用 UrlTemplate，否则为 raw 文件地址
+  private getGitObjectUrl(config: GitRepoConfig, fullKey: string): string {
+    const serverUrl = (config.ServerUrl || 'https://github.com').replace(/\/+$/, '');
+    const basePath = (config.Path || '').replace(/^\/+|\/+$/g, '');
+    const repoPath = `${basePath ? `${basePath}/` : ''}${fullKey}`.split('/').map(encodeURIComponent).join('/');
+    const branch = config.Branch || 'main';
+
+    let template = config.UrlTemplate;
+    if (!template) {
+      if (config.Provider === 'gitea') {
+        template = `${serverUrl}/{owner}/{repo}/raw/branch/{branch}/{path}`;
+      } else if (serverUrl === 'https://github.com') {
+        template = 'https://raw.githubusercontent.com/{owner}/{repo}/{branch}/{path}';
+      } else {
+        template = `${serverUrl}/{owner}/{repo}/raw/{branch}/{path}`;
+      }
+    }
+
+    return template
+      .replace(/\{owner\}/g, config.Owner)
+      .replace(/\{repo\}/g, config.Repo)
+      .replace(/\{branch\}/g, branch)
+      .replace(/\{path\}/g, repoPath);
+  }
+
+  // 本地目录链接：优先使用 UrlPrefix，否则为 file:// 绝对路径
+  private getLocalObjectUrl(config: LocalConfig, fullKey: string): string {
+    if (config.UrlPrefix) {
+      return `${config.UrlPrefix.replace(/\/+$/, '')}/${fullKey}`;
+    }
+    const root = config.Root.replace(/\\/g, '/').replace(/\/+$/, '');
+    const path = `${root.startsWith('/') ? '' : '/'}${root}/${fullKey}`;
+    return `file://${path.split('/').map(segment => /^[A-Za-z]:$/.test(segment) ? segment : encodeURIComponent(segment)).join('/')}`;
+  }
+
   // 新增：从 base64 数据上传文件（用于拖拽上传）
   async uploadFromBase64(
     base64Data: string,
@@ -546,6 +1428,9 @@ export class TauriCosService {
     Location: string;
     Key: string;
     size: number;
+    Mirrors: MirrorUpload[];
+    Metadata?: ImageMetadata;
+    Duplicate?: ExistingImage;
   }> {
     this.log('开始从 base64 上传', { key, dataLength: base64Data.length });
     
@@ -561,11 +1446,17 @@ export class TauriCosService {
       stage: 'preparing'
     });
     
+    const taskId = this.createTaskId(key);
+    let unlisten: UnlistenFn | null = null;
+    
     try {
       // 准备上传选项
       const options: UploadOptions = {
         callback: onProgress ? 'progress' : undefined,
-        headers: uploadOptions?.headers
+        headers: uploadOptions?.headers,
+        mirrors: await this.ensureMirrors(),
+        original_name: uploadOptions?.originalName,
+        force: uploadOptions?.force
       };
 
       // 如果启用了WebP转换，构建万象云处理头部
@@ -576,24 +1467,20 @@ export class TauriCosService {
         if (!options.headers) {
           options.headers = {};
         }
-        options.headers.pic_operations = picOperations;
+        this.applyWebpHeaders(options.headers, picOperations, quality);
         
         this.log('启用万象云WebP处理 (Base64)', { key, quality, picOperations });
       }
       
-      // 发送上传开始进度
-      onProgress?.({
-        percent: 10,
-        loaded: 0,
-        total: 0,
-        stage: 'uploading'
-      });
+      // 订阅 Rust 端推送的真实上传进度
+      unlisten = await this.listenProgress(taskId, onProgress);
       
       const result = await invoke<UploadResult>('cos_upload_from_base64', {
         base64Data,
         key,
         options,
-        config: this.config
+        taskId,
+        clientId: await this.ensureClient()
       });
       
       // 发送上传完成进度
@@ -613,7 +1500,10 @@ export class TauriCosService {
         ETag: result.etag,
         Location: fullUrl,
         Key: result.key,
-        size: result.size
+        size: result.size,
+        Mirrors: result.mirrors ?? [],
+        Metadata: result.metadata,
+        Duplicate: result.duplicate ?? undefined
       };
     } catch (error) {
       this.logError('Base64 上传过程失败', error);
@@ -626,16 +1516,9 @@ export class TauriCosService {
         stage: 'error'
       });
       
-      if (error instanceof CosError) {
-        throw error;
-      }
-      
-      throw new CosError(
-        `Base64 上传失败: ${error instanceof Error ? error.message : String(error)}`,
-        'UPLOAD_FAILED',
-        undefined,
-        error
-      );
+      throw CosError.from(error, 'Base64 上传失败', 'UPLOAD_FAILED');
+    } finally {
+      unlisten?.();
     }
   }
 
@@ -650,6 +1533,9 @@ export class TauriCosService {
     Location: string;
     Key: string;
     size: number;
+    Mirrors: MirrorUpload[];
+    Metadata?: ImageMetadata;
+    Duplicate?: ExistingImage;
   }> {
     this.log('开始处理拖拽上传', { fileName, fileSize: file.size });
     
@@ -665,22 +1551,23 @@ export class TauriCosService {
       stage: 'preparing'
     });
     
+    const taskId = this.createTaskId(fileName);
+    let unlisten: UnlistenFn | null = null;
+    
     try {
       // 将文件转换为 base64
       const base64Data = await this.fileToBase64(file);
       
-      // 发送转换完成进度
-      onProgress?.({
-        percent: 20,
-        loaded: 0,
-        total: file.size,
-        stage: 'uploading'
-      });
+      // 订阅 Rust 端推送的真实上传进度
+      unlisten = await this.listenProgress(taskId, onProgress);
       
       // 准备上传选项
       const options: UploadOptions = {
         callback: onProgress ? 'progress' : undefined,
-        headers: uploadOptions?.headers
+        headers: uploadOptions?.headers,
+        mirrors: await this.ensureMirrors(),
+        original_name: uploadOptions?.originalName ?? file.name,
+        force: uploadOptions?.force
       };
 
       // 如果启用了WebP转换，构建万象云处理头部
@@ -691,7 +1578,7 @@ export class TauriCosService {
         if (!options.headers) {
           options.headers = {};
         }
-        options.headers.pic_operations = picOperations;
+        this.applyWebpHeaders(options.headers, picOperations, quality);
         
         this.log('启用万象云WebP处理 (拖拽上传)', { fileName, quality, picOperations });
       }
@@ -699,8 +1586,9 @@ export class TauriCosService {
       const result = await invoke<UploadResult>('handle_drag_upload', {
         fileData: base64Data,
         fileName,
-        config: this.config,
-        options
+        clientId: await this.ensureClient(),
+        options,
+        taskId
       });
       
       // 发送上传完成进度
@@ -720,7 +1608,10 @@ export class TauriCosService {
         ETag: result.etag,
         Location: fullUrl,
         Key: result.key,
-        size: result.size
+        size: result.size,
+        Mirrors: result.mirrors ?? [],
+        Metadata: result.metadata,
+        Duplicate: result.duplicate ?? undefined
       };
     } catch (error) {
       this.logError('拖拽上传过程失败', error);
@@ -733,16 +1624,9 @@ export class TauriCosService {
         stage: 'error'
       });
       
-      if (error instanceof CosError) {
-        throw error;
-      }
-      
-      throw new CosError(
-        `拖拽上传失败: ${error instanceof Error ? error.message : String(error)}`,
-        'UPLOAD_FAILED',
-        undefined,
-        error
-      );
+      throw CosError.from(error, '拖拽上传失败', 'UPLOAD_FAILED');
+    } finally {
+      unlisten?.();
     }
   }
 
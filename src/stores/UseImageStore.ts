@@ -1,6 +1,6 @@
 import { defineStore } from 'pinia'
 import { ref, computed } from 'vue'
-import { imageService, type ImageInfo } from '../services/ImageService'
+import { imageService, type ImageInfo, type UploadImageOptions } from '../services/ImageService'
 import { mConsole } from '../main'
 
 export const useImageStore = defineStore('image', () => {
@@ -97,9 +97,9 @@ export const useImageStore = defineStore('image', () => {
   }
 
   // 上传图片
-  const uploadImage = async (file: File, onProgress?: (progress: any) => void): Promise<boolean> => {
+  const uploadImage = async (file: File, onProgress?: (progress: any) => void, options?: UploadImageOptions): Promise<boolean> => {
     try {
-      const success = await imageService.uploadImage(file, onProgress)
+      const success = await imageService.uploadImage(file, onProgress, options)
       
       if (success) {
         // 刷新图片列表
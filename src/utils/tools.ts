@@ -91,14 +91,21 @@ export function formatUrl(exgText: string, url: string, domain?: string): string
   return value.replace(/%url/g, fullUrl);
 }
 
-export function toExgText(exgText: string, url: string): string {
+export function toExgText(
+    exgText: string,
+    url: string,
+    image?: { image_width?: number | null; image_height?: number | null }
+): string {
     // 如果没有格式文本，直接返回URL
     if (!exgText) {
         return url
     }
     
-    // 使用正则表达式替换 %url 占位符
-    const result = exgText.replace(/%url/g, url)
+    // 使用正则表达式替换 %url 占位符，%width / %height 在尺寸未知时替换为空
+    const result = exgText
+        .replace(/%url/g, url)
+        .replace(/%width/g, image?.image_width != null ? `${image.image_width}` : '')
+        .replace(/%height/g, image?.image_height != null ? `${image.image_height}` : '')
     
     /* mConsole.log('🔄 格式化文本:', { 
         input: exgText, 